@@ -54,6 +54,25 @@ module.exports = {
       merge_logs: true,
       time: true,
       log_date_format: 'YYYY-MM-DD HH:mm:ss Z'
+    },
+    {
+      // Restarts the server above when /health/ready keeps answering 503 or times out
+      name: '8887: Forge health',
+      cwd: PROJECT_ROOT,
+      script: path.join(PROJECT_ROOT, 'scripts/health-watchdog.cjs'),
+      env: {
+        ...envVars,
+        FORGE_PM2_APP: '8887: Forge'
+      },
+      instances: 1,
+      exec_mode: 'fork',
+      autorestart: true,
+      watch: false,
+      error_file: path.join(PROJECT_ROOT, 'logs/forge-health-err.log'),
+      out_file: path.join(PROJECT_ROOT, 'logs/forge-health-out.log'),
+      merge_logs: true,
+      time: true,
+      log_date_format: 'YYYY-MM-DD HH:mm:ss Z'
     }
   ]
 };
@@ -1,6 +1,19 @@
 use std::{env, fs, path::Path};
 
 use anyhow::{Context, Result, bail};
+use forge_app_lib::services::{
+    CircuitState, OmniWorkerStatus,
+    api_tokens::{ApiTokenRecord, CreateApiToken, CreatedApiToken, TokenScope},
+    notification_events::NotificationEvent,
+    notification_history::{NotificationFilter, NotificationPurgeResult, NotificationRecord},
+    notification_sinks::{
+        NotificationDelivery, NotificationSettings, NotificationSinkConfig, NotificationSinkKind,
+        SmtpSecurity,
+    },
+    notification_templates::{
+        DiffStats, NotificationContext, NotificationTemplates, RenderedNotification,
+    },
+};
 use forge_core_services::services::{
     forge_config::{ForgeProjectSettings, ProjectConfig},
     omni::{OmniConfig, OmniInstance, RecipientType, SendTextRequest, SendTextResponse},
@@ -20,6 +33,25 @@ fn main() -> Result<()> {
         OmniInstance::decl(),
         SendTextRequest::decl(),
         SendTextResponse::decl(),
+        OmniWorkerStatus::decl(),
+        CircuitState::decl(),
+        NotificationSettings::decl(),
+        NotificationSinkConfig::decl(),
+        NotificationSinkKind::decl(),
+        SmtpSecurity::decl(),
+        NotificationDelivery::decl(),
+        NotificationTemplates::decl(),
+        NotificationContext::decl(),
+        DiffStats::decl(),
+        RenderedNotification::decl(),
+        NotificationEvent::decl(),
+        NotificationFilter::decl(),
+        NotificationRecord::decl(),
+        NotificationPurgeResult::decl(),
+        TokenScope::decl(),
+        CreateApiToken::decl(),
+        ApiTokenRecord::decl(),
+        CreatedApiToken::decl(),
     ];
 
     let body = declarations
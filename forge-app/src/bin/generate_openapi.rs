@@ -0,0 +1,51 @@
+use std::{env, fs, path::Path};
+
+use anyhow::{Context, Result, bail};
+use forge_app_lib::{openapi::openapi_spec, router::api_operations};
+use serde_json::Value;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let check_mode = args.iter().any(|arg| arg == "--check");
+
+    let spec = openapi_spec(&api_operations(), env!("CARGO_PKG_VERSION"));
+    let content = format!("{}\n", serde_json::to_string_pretty(&spec)?);
+
+    let output_path = Path::new("shared/openapi.json");
+
+    if check_mode {
+        if !output_path.exists() {
+            bail!(
+                "{} missing; run `pnpm run generate-openapi`",
+                output_path.display()
+            );
+        }
+        let existing = fs::read_to_string(output_path).with_context(|| {
+            format!(
+                "failed to read {} during OpenAPI check",
+                output_path.display()
+            )
+        })?;
+        let existing: Value = serde_json::from_str(&existing)
+            .with_context(|| format!("{} is not valid JSON", output_path.display()))?;
+        // Release bumps change info.version without touching any route
+        if without_version(existing) != without_version(spec) {
+            bail!("OpenAPI spec is out of date; run `pnpm run generate-openapi`");
+        }
+        println!("OpenAPI spec up to date");
+    } else {
+        fs::write(output_path, content).with_context(|| {
+            format!("failed to write OpenAPI spec to {}", output_path.display())
+        })?;
+        println!("OpenAPI spec written to {}", output_path.display());
+    }
+
+    Ok(())
+}
+
+fn without_version(mut spec: Value) -> Value {
+    if let Some(info) = spec.get_mut("info").and_then(Value::as_object_mut) {
+        info.remove("version");
+    }
+    spec
+}
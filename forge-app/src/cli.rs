@@ -0,0 +1,495 @@
+//! Command Line Interface
+//!
+//! `forge` with no subcommand (or `forge serve`) runs the server; the other
+//! subcommands inspect or prepare the local installation (`doctor`, `migrate`,
+//! `config print`) or talk to a running instance over its REST API (`projects`,
+//! `tasks`, `notifications`).
+
+mod client;
+mod doctor;
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use forge_core_utils::browser::open_browser;
+
+use crate::{
+    config::{
+        ConfigLayer, ForgeServerConfig, LimitsLayer, LogLayer, PortSpec, SocketLayer,
+        TelemetryLayer,
+    },
+    discovery::ServerInfo,
+    instance_lock::AlreadyRunning,
+    logging::{self, LogFormat, LogSettings},
+    telemetry,
+};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "forge",
+    version,
+    about = "Automagik Forge: AI coding agent task board",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    /// TOML config file [env: FORGE_CONFIG] [default: <asset dir>/config.toml]
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// SQLite database file or URL [env: DATABASE_URL]
+    #[arg(long, global = true, value_name = "PATH")]
+    pub db: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Server options when no subcommand is given
+    #[command(flatten)]
+    pub serve: ServeArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the server (the default)
+    Serve(ServeArgs),
+    /// Check the asset directory, database, port, TLS files and git
+    Doctor(ServeArgs),
+    /// Apply database migrations and exit
+    Migrate,
+    /// Inspect the effective configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Projects on a running server
+    Projects {
+        #[command(subcommand)]
+        command: ProjectsCommand,
+    },
+    /// Tasks on a running server
+    Tasks {
+        #[command(subcommand)]
+        command: TasksCommand,
+    },
+    /// Omni notifications on a running server
+    Notifications {
+        #[command(subcommand)]
+        command: NotificationsCommand,
+    },
+}
+
+#[derive(Debug, Clone, Default, Args)]
+pub struct ServeArgs {
+    /// Address to bind [env: HOST] [default: 127.0.0.1]
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// Port to listen on; `8887+` or `auto` takes the first free port from there
+    /// [env: BACKEND_PORT, PORT] [default: 8887]
+    #[arg(short, long)]
+    pub port: Option<PortSpec>,
+
+    /// Require GitHub sign-in or an API token for the API [env: AUTH_REQUIRED]
+    #[arg(short, long)]
+    pub auth: bool,
+
+    /// GitHub login allowed to sign in besides the one already signed in
+    /// (repeatable) [env: FORGE_ALLOWED_LOGINS]
+    #[arg(long = "allow-login", value_name = "LOGIN")]
+    pub allowed_logins: Vec<String>,
+
+    /// Do not open the UI in a browser [env: DISABLE_BROWSER_OPEN]
+    #[arg(long)]
+    pub no_browser: bool,
+
+    /// Extra origin allowed to call the API from a browser (repeatable)
+    #[arg(long = "cors-origin", value_name = "ORIGIN")]
+    pub cors_origins: Vec<String>,
+
+    /// Serve Prometheus metrics at /metrics [env: FORGE_METRICS]
+    #[arg(long)]
+    pub metrics: bool,
+
+    /// Also listen on this Unix socket [env: FORGE_SOCKET]
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+
+    /// Serve only on the Unix socket, without the TCP listener
+    #[arg(long)]
+    pub socket_only: bool,
+
+    /// Log filter, e.g. debug or forge_app_lib=debug,info
+    /// [env: FORGE_LOG_LEVEL, RUST_LOG] [default: info]
+    #[arg(long, value_name = "FILTER")]
+    pub log_level: Option<String>,
+
+    /// Log line format: pretty or json [env: FORGE_LOG_FORMAT] [default: pretty]
+    #[arg(long, value_name = "FORMAT")]
+    pub log_format: Option<LogFormat>,
+
+    /// Also write daily rolling log files to <asset dir>/logs [env: FORGE_LOG_FILES]
+    #[arg(long)]
+    pub log_files: bool,
+
+    /// Export task lifecycle traces to this OTLP/HTTP collector, e.g.
+    /// http://localhost:4318 [env: OTEL_EXPORTER_OTLP_ENDPOINT]
+    #[arg(long, value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+
+    /// Agent starts each client may make per minute, 0 for no limit
+    /// [env: FORGE_AGENT_STARTS_PER_MINUTE] [default: 30]
+    #[arg(long, value_name = "N")]
+    pub agent_starts_per_minute: Option<u32>,
+
+    /// Attempts that may run an agent at once; further starts queue, 0 for no cap
+    /// [env: FORGE_MAX_RUNNING_AGENTS] [default: 8]
+    #[arg(long, value_name = "N")]
+    pub max_running_agents: Option<u32>,
+
+    /// Accepted for the npx wrapper, which starts the binary with --mcp
+    #[arg(long, hide = true)]
+    pub mcp: bool,
+}
+
+impl ServeArgs {
+    /// The flags as the highest-precedence configuration layer
+    fn overrides(&self) -> ConfigLayer {
+        ConfigLayer {
+            host: self.host.clone(),
+            port: self.port,
+            auth_required: self.auth.then_some(true),
+            allowed_logins: Some(self.allowed_logins.clone()).filter(|logins| !logins.is_empty()),
+            open_browser: self.no_browser.then_some(false),
+            cors_origins: self.cors_origins.clone(),
+            metrics: self.metrics.then_some(true),
+            socket: SocketLayer {
+                path: self.socket.clone(),
+                only: self.socket_only.then_some(true),
+                mode: None,
+            },
+            log: LogLayer {
+                level: self.log_level.clone(),
+                format: self.log_format,
+                files: self.log_files.then_some(true),
+                max_files: None,
+            },
+            telemetry: TelemetryLayer {
+                otlp_endpoint: self.otlp_endpoint.clone(),
+                service_name: None,
+            },
+            limits: LimitsLayer {
+                agent_starts_per_minute: self.agent_starts_per_minute,
+                max_running_agents: self.max_running_agents,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ApiArgs {
+    /// Base URL of the running server
+    #[arg(long, env = "FORGE_API_URL", default_value = "http://127.0.0.1:8887")]
+    pub url: String,
+
+    /// API token, required when the server runs with AUTH_REQUIRED
+    #[arg(long, env = "FORGE_API_TOKEN", hide_env_values = true)]
+    pub token: Option<String>,
+
+    /// Print the raw JSON response
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the merged configuration in config file syntax
+    Print(ServeArgs),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProjectsCommand {
+    /// List projects
+    List {
+        #[command(flatten)]
+        api: ApiArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TasksCommand {
+    /// Create a task, optionally starting an attempt right away
+    Create(CreateTaskArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CreateTaskArgs {
+    /// Project ID, name or repository path
+    #[arg(long)]
+    pub project: String,
+
+    #[arg(long)]
+    pub title: String,
+
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// Start an attempt with --executor on --base-branch
+    #[arg(long, requires_all = ["executor", "base_branch"])]
+    pub start: bool,
+
+    /// Executor profile for --start, e.g. CLAUDE_CODE or CODEX
+    #[arg(long, requires = "start")]
+    pub executor: Option<String>,
+
+    /// Branch the attempt's worktree is created from
+    #[arg(long, requires = "start")]
+    pub base_branch: Option<String>,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum NotificationsCommand {
+    /// Re-queue a dead-lettered or skipped notification
+    Retry {
+        id: String,
+
+        #[command(flatten)]
+        api: ApiArgs,
+    },
+}
+
+/// Where configuration comes from besides the environment
+struct ConfigSource {
+    file: Option<PathBuf>,
+    db: Option<String>,
+}
+
+impl ConfigSource {
+    fn load(&self, args: &ServeArgs) -> Result<ForgeServerConfig> {
+        let mut overrides = args.overrides();
+        overrides.database_url = self.db.as_deref().map(database_url);
+        ForgeServerConfig::load(self.file.as_deref(), overrides)
+    }
+}
+
+/// Run the parsed command
+pub fn run(cli: Cli) -> Result<()> {
+    let source = ConfigSource {
+        file: cli.config,
+        db: cli.db,
+    };
+
+    let command = cli.command.unwrap_or(Command::Serve(cli.serve));
+    // The server and `migrate` log as configured once their config is loaded
+    if !matches!(command, Command::Serve(_) | Command::Migrate) {
+        logging::init(&LogSettings::default())?;
+    }
+
+    match command {
+        Command::Serve(args) => {
+            let config = source.load(&args)?;
+            let _log_guard = logging::init(&config.log)?;
+            // Outside the runtime, see `TelemetryGuard`
+            let _telemetry_guard = config
+                .telemetry
+                .as_ref()
+                .map(telemetry::install)
+                .transpose()?;
+            // Set before the runtime spawns worker threads
+            config.export_database_url();
+            runtime()?.block_on(serve(config))
+        }
+        Command::Migrate => {
+            let config = source.load(&ServeArgs::default())?;
+            let _log_guard = logging::init(&config.log)?;
+            config.export_database_url();
+            runtime()?.block_on(migrate())
+        }
+        Command::Doctor(args) => runtime()?.block_on(doctor::run(source.load(&args))),
+        Command::Config {
+            command: ConfigCommand::Print(args),
+        } => print_config(&source.load(&args)?),
+        Command::Projects {
+            command: ProjectsCommand::List { api },
+        } => runtime()?.block_on(client::list_projects(&api)),
+        Command::Tasks {
+            command: TasksCommand::Create(args),
+        } => runtime()?.block_on(client::create_task(&args)),
+        Command::Notifications {
+            command: NotificationsCommand::Retry { id, api },
+        } => runtime()?.block_on(client::retry_notification(&api, &id)),
+    }
+}
+
+fn runtime() -> std::io::Result<tokio::runtime::Runtime> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+}
+
+async fn serve(config: ForgeServerConfig) -> Result<()> {
+    let open = config.open_browser;
+
+    // Open the browser once the listener is up, at the port it actually bound
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    if open {
+        tokio::spawn(async move {
+            if let Ok(ServerInfo { url: Some(url), .. }) = ready_rx.await
+                && let Err(e) = open_browser(&url).await
+            {
+                tracing::warn!("Failed to open browser: {}", e);
+            }
+        });
+    }
+
+    let result = crate::run_server_with_readiness(config, Some(ready_tx)).await;
+
+    // A second launch brings up the running instance instead
+    if let Err(e) = &result
+        && let Some(AlreadyRunning {
+            info: Some(ServerInfo { url: Some(url), .. }),
+            ..
+        }) = e.downcast_ref::<AlreadyRunning>()
+        && open
+    {
+        println!("Forge is already running at {url}; opening it in the browser");
+        return open_browser(url)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to open browser: {e}"));
+    }
+    result
+}
+
+async fn migrate() -> Result<()> {
+    // Runs the upstream migrations on connect
+    let db = forge_core_db::DBService::new().await?;
+    crate::services::migrate_database(&db.pool).await?;
+    println!("Database is up to date");
+    Ok(())
+}
+
+fn print_config(config: &ForgeServerConfig) -> Result<()> {
+    println!("# Precedence: defaults < config file < environment < command-line flags");
+    match &config.config_file {
+        Some(path) => println!("# Config file: {}", path.display()),
+        None => println!("# Config file: none"),
+    }
+    print!("{}", config.to_toml()?);
+    Ok(())
+}
+
+/// `--db` accepts a plain file path or a full `sqlite:` URL
+fn database_url(db: &str) -> String {
+    if db.starts_with("sqlite:") {
+        db.to_string()
+    } else {
+        format!("sqlite://{db}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("forge").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn no_subcommand_serves_and_accepts_the_wrapper_flags() {
+        let cli = parse(&[
+            "--mcp",
+            "-p",
+            "9000",
+            "--no-browser",
+            "--db",
+            "/tmp/forge.db",
+        ])
+        .unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.serve.port, Some(PortSpec::Fixed(9000)));
+        assert!(cli.serve.no_browser);
+        assert_eq!(cli.db.as_deref(), Some("/tmp/forge.db"));
+
+        let cli = parse(&["serve", "--host", "0.0.0.0", "--auth", "--port", "auto"]).unwrap();
+        let Some(Command::Serve(args)) = cli.command else {
+            panic!("expected serve");
+        };
+        assert_eq!(args.host.as_deref(), Some("0.0.0.0"));
+        assert!(args.auth);
+        assert_eq!(args.port, Some(PortSpec::FirstFree(8887)));
+    }
+
+    #[test]
+    fn typos_and_bad_values_are_errors() {
+        assert!(parse(&["--prot", "9000"]).is_err());
+        assert!(parse(&["--port", "http"]).is_err());
+        assert!(parse(&["--log-format", "xml"]).is_err());
+        assert!(parse(&["tasks", "create", "--project", "forge"]).is_err());
+        assert!(
+            parse(&[
+                "tasks",
+                "create",
+                "--project",
+                "forge",
+                "--title",
+                "Fix it",
+                "--start"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn flags_are_the_top_configuration_layer() {
+        let mut layer = ConfigLayer {
+            port: Some(PortSpec::Fixed(9000)),
+            cors_origins: vec!["https://a.example".into()],
+            ..Default::default()
+        };
+        let cli = parse(&[
+            "config",
+            "print",
+            "--host",
+            "::1",
+            "--no-browser",
+            "--cors-origin",
+            "https://b.example",
+            "--socket",
+            "/tmp/forge.sock",
+            "--socket-only",
+            "--log-format",
+            "json",
+        ])
+        .unwrap();
+        let Some(Command::Config {
+            command: ConfigCommand::Print(args),
+        }) = cli.command
+        else {
+            panic!("expected config print");
+        };
+        layer.merge(args.overrides());
+
+        let config = ForgeServerConfig::resolve(layer, std::path::Path::new("/var/forge")).unwrap();
+        assert_eq!(config.host, "::1");
+        assert_eq!(config.port, PortSpec::Fixed(9000));
+        assert!(!config.open_browser);
+        assert_eq!(
+            config.cors_origins,
+            ["https://a.example", "https://b.example"]
+        );
+        assert!(!config.tcp_enabled());
+        assert_eq!(config.log.format, LogFormat::Json);
+    }
+
+    #[test]
+    fn db_accepts_paths_and_urls() {
+        assert_eq!(database_url("/tmp/forge.db"), "sqlite:///tmp/forge.db");
+        assert_eq!(database_url("sqlite::memory:"), "sqlite::memory:");
+    }
+}
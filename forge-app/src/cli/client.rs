@@ -0,0 +1,185 @@
+//! Subcommands that call a running server's REST API, authenticating with
+//! `--token`/`FORGE_API_TOKEN` like `scripts/create-forge-task-from-diff.mjs` does
+
+use anyhow::{Context, Result, bail};
+use reqwest::{Method, RequestBuilder};
+use serde_json::{Value, json};
+
+use super::{ApiArgs, CreateTaskArgs};
+
+struct ApiClient<'a> {
+    http: reqwest::Client,
+    args: &'a ApiArgs,
+}
+
+impl<'a> ApiClient<'a> {
+    fn new(args: &'a ApiArgs) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            args,
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let url = format!("{}{path}", self.args.url.trim_end_matches('/'));
+        let request = self.http.request(method, url);
+        match &self.args.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Send the request and return the JSON body, turning error statuses and
+    /// `success: false` envelopes into errors
+    async fn send(&self, request: RequestBuilder) -> Result<Value> {
+        let response = request.send().await.with_context(|| {
+            format!("Failed to reach Forge at {}; is it running?", self.args.url)
+        })?;
+        let status = response.status();
+        let body = response.text().await?;
+        let json: Option<Value> = serde_json::from_str(&body).ok();
+
+        let failed = json
+            .as_ref()
+            .and_then(|json| json.get("success"))
+            .and_then(Value::as_bool)
+            == Some(false);
+        if !status.is_success() || failed {
+            let message = json
+                .as_ref()
+                .and_then(|json| json.get("message"))
+                .and_then(Value::as_str)
+                .unwrap_or(&body);
+            bail!("{status}: {message}");
+        }
+        json.with_context(|| format!("Unexpected non-JSON response: {body}"))
+    }
+
+    /// `data` of an `ApiResponse` envelope
+    async fn send_enveloped(&self, request: RequestBuilder) -> Result<Value> {
+        let mut json = self.send(request).await?;
+        Ok(json["data"].take())
+    }
+}
+
+pub async fn list_projects(args: &ApiArgs) -> Result<()> {
+    let client = ApiClient::new(args);
+    let projects = fetch_projects(&client).await?;
+    if args.json {
+        return print_json(&projects);
+    }
+
+    let projects = projects.as_array().map(Vec::as_slice).unwrap_or_default();
+    if projects.is_empty() {
+        println!("No projects");
+    }
+    for project in projects {
+        println!(
+            "{}  {}  {}",
+            field(project, "id"),
+            field(project, "name"),
+            field(project, "git_repo_path")
+        );
+    }
+    Ok(())
+}
+
+pub async fn create_task(args: &CreateTaskArgs) -> Result<()> {
+    let client = ApiClient::new(&args.api);
+    let project_id = find_project_id(&client, &args.project).await?;
+    let task = json!({
+        "project_id": project_id,
+        "title": args.title,
+        "description": args.description,
+    });
+
+    let created = match (&args.executor, &args.base_branch) {
+        (Some(executor), Some(base_branch)) if args.start => {
+            let payload = json!({
+                "task": task,
+                "executor_profile_id": {
+                    "executor": executor.to_uppercase(),
+                    "variant": null,
+                },
+                "base_branch": base_branch,
+                "use_worktree": true,
+            });
+            client
+                .send_enveloped(
+                    client
+                        .request(Method::POST, "/api/tasks/create-and-start")
+                        .json(&payload),
+                )
+                .await?
+        }
+        _ => {
+            client
+                .send_enveloped(client.request(Method::POST, "/api/tasks").json(&task))
+                .await?
+        }
+    };
+
+    if args.api.json {
+        return print_json(&created);
+    }
+    println!("Created task {}", field(&created, "id"));
+    if let Some(branch) = created["latest_attempt"]["branch"].as_str() {
+        println!("Started attempt on branch {branch}");
+    }
+    Ok(())
+}
+
+pub async fn retry_notification(args: &ApiArgs, id: &str) -> Result<()> {
+    let client = ApiClient::new(args);
+    let path = format!("/api/forge/notifications/{}/retry", urlencoding::encode(id));
+    let result = client.send(client.request(Method::POST, &path)).await?;
+
+    if args.json {
+        return print_json(&result);
+    }
+    println!("Notification {id} re-queued");
+    Ok(())
+}
+
+async fn fetch_projects(client: &ApiClient<'_>) -> Result<Value> {
+    client
+        .send_enveloped(client.request(Method::GET, "/api/projects"))
+        .await
+}
+
+/// Match an exact ID first, then a name or repository path (case-insensitive)
+async fn find_project_id(client: &ApiClient<'_>, identifier: &str) -> Result<String> {
+    let projects = fetch_projects(client).await?;
+    let projects = projects.as_array().map(Vec::as_slice).unwrap_or_default();
+    if projects.is_empty() {
+        bail!("No projects found; add one in the UI first");
+    }
+
+    if let Some(project) = projects.iter().find(|p| field(p, "id") == identifier) {
+        return Ok(field(project, "id").to_string());
+    }
+    let normalized = identifier.to_lowercase();
+    let matches = |p: &&Value| {
+        let path = field(p, "git_repo_path").to_lowercase();
+        field(p, "name").to_lowercase() == normalized || path.contains(&normalized)
+    };
+    if let Some(project) = projects.iter().find(matches) {
+        return Ok(field(project, "id").to_string());
+    }
+
+    let available = projects
+        .iter()
+        .map(|p| format!("- {} :: {}", field(p, "id"), field(p, "git_repo_path")))
+        .collect::<Vec<_>>()
+        .join("\n");
+    bail!("Project \"{identifier}\" not found. Available:\n{available}")
+}
+
+fn print_json(value: &Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn field<'v>(value: &'v Value, key: &str) -> &'v str {
+    value[key].as_str().unwrap_or_default()
+}
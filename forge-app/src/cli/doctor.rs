@@ -0,0 +1,217 @@
+//! `forge doctor`: read-only checks of the local installation, so problems show up
+//! before `forge serve` fails halfway through startup
+
+use std::{path::Path, str::FromStr};
+
+use anyhow::{Context, Result, bail};
+use sqlx::{ConnectOptions, sqlite::SqliteConnectOptions};
+
+use crate::{
+    config::ForgeServerConfig,
+    instance_lock::{AlreadyRunning, InstanceLock},
+    tls,
+};
+
+#[derive(Default)]
+struct Report {
+    failures: usize,
+}
+
+impl Report {
+    fn record(&mut self, name: &str, result: Result<String>) {
+        match result {
+            Ok(detail) => println!("✓ {name}: {detail}"),
+            Err(e) => {
+                self.failures += 1;
+                println!("✗ {name}: {e:#}");
+            }
+        }
+    }
+}
+
+pub async fn run(config: Result<ForgeServerConfig>) -> Result<()> {
+    let mut report = Report::default();
+    let version = config
+        .as_ref()
+        .ok()
+        .and_then(|config| config.version.as_deref());
+    println!("Forge {}", version.unwrap_or(crate::version::get_version()));
+
+    let asset_dir = forge_core_utils::assets::asset_dir();
+    let database_url = config.as_ref().ok().and_then(|c| c.database_url.as_deref());
+    report.record("Asset directory", check_asset_dir(&asset_dir));
+    report.record("Database", check_database(&asset_dir, database_url).await);
+    report.record("Git", check_git());
+    let running = check_instance(&asset_dir);
+    let idle = matches!(running, Ok(None));
+    report.record(
+        "Running instance",
+        running.map(|info| info.unwrap_or_else(|| "none".into())),
+    );
+
+    match config {
+        Ok(config) => {
+            report.record("Configuration", Ok(describe(&config)));
+            // The running instance owns the port and socket
+            if config.tcp_enabled() && idle {
+                report.record("Port", check_port(&config));
+            }
+            if config.tls.is_some() {
+                report.record("TLS certificate", check_tls(&config).await);
+            }
+            if config.socket.is_some() && idle {
+                report.record("Unix socket", check_socket(&config));
+            }
+        }
+        Err(e) => report.record("Configuration", Err(e)),
+    }
+
+    if report.failures > 0 {
+        bail!("{} check(s) failed", report.failures);
+    }
+    Ok(())
+}
+
+fn check_asset_dir(asset_dir: &Path) -> Result<String> {
+    let probe = asset_dir.join(".forge-doctor");
+    std::fs::write(&probe, b"")
+        .with_context(|| format!("{} is not writable", asset_dir.display()))?;
+    let _ = std::fs::remove_file(probe);
+    Ok(asset_dir.display().to_string())
+}
+
+async fn check_database(asset_dir: &Path, database_url: Option<&str>) -> Result<String> {
+    let url = match database_url {
+        Some(url) => url.to_string(),
+        None => {
+            let path = asset_dir.join("db.sqlite");
+            if !path.exists() {
+                return Ok(format!("{} will be created on first start", path.display()));
+            }
+            format!("sqlite://{}", path.display())
+        }
+    };
+
+    // Read-only, so checking never migrates or creates anything
+    let mut conn = SqliteConnectOptions::from_str(&url)
+        .with_context(|| format!("Invalid DATABASE_URL '{url}'"))?
+        .read_only(true)
+        .connect()
+        .await
+        .with_context(|| format!("Failed to open {url}"))?;
+
+    let integrity: String = sqlx::query_scalar("PRAGMA quick_check")
+        .fetch_one(&mut conn)
+        .await?;
+    if integrity != "ok" {
+        bail!("{url} failed the integrity check: {integrity}");
+    }
+
+    let migrations: Option<i64> = sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations")
+        .fetch_one(&mut conn)
+        .await
+        .ok();
+    Ok(match migrations {
+        Some(count) => format!("{url} ({count} migrations applied)"),
+        None => format!("{url} (not migrated yet; run `forge migrate`)"),
+    })
+}
+
+fn check_git() -> Result<String> {
+    let output = std::process::Command::new("git")
+        .arg("--version")
+        .output()
+        .context("git was not found on PATH")?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Details of the server holding the instance lock, if one is running
+fn check_instance(asset_dir: &Path) -> Result<Option<String>> {
+    match InstanceLock::acquire(asset_dir) {
+        Ok(_lock) => Ok(None),
+        Err(e) => match e.downcast::<AlreadyRunning>() {
+            Ok(AlreadyRunning {
+                info: Some(info), ..
+            }) => Ok(Some(format!(
+                "PID {} (version {}) at {}",
+                info.pid,
+                info.version,
+                info.url
+                    .or_else(|| info.socket.map(|p| p.display().to_string()))
+                    .unwrap_or_default()
+            ))),
+            Ok(AlreadyRunning { info: None, .. }) => Ok(Some("starting up".into())),
+            Err(e) => Err(e),
+        },
+    }
+}
+
+fn describe(config: &ForgeServerConfig) -> String {
+    let mut listeners = Vec::new();
+    if let Some(url) = config.browser_url() {
+        listeners.push(url);
+    }
+    if let Some(socket) = &config.socket {
+        listeners.push(format!("unix:{}", socket.path.display()));
+    }
+    let auth = if config.auth_required {
+        "sign-in required"
+    } else {
+        "no sign-in"
+    };
+    let source = match &config.config_file {
+        Some(path) => format!("from {}", path.display()),
+        None => "no config file".to_string(),
+    };
+    format!("{} ({auth}; {source})", listeners.join(", "))
+}
+
+fn check_port(config: &ForgeServerConfig) -> Result<String> {
+    let addr = crate::bind_tcp_listener(config)?.local_addr()?;
+    if addr.port() == config.port.first() {
+        Ok(format!("{addr} is free"))
+    } else {
+        Ok(format!(
+            "{addr} is free ({} is in use)",
+            config.port.first()
+        ))
+    }
+}
+
+async fn check_tls(config: &ForgeServerConfig) -> Result<String> {
+    let Some(settings) = &config.tls else {
+        return Ok("disabled".into());
+    };
+    let missing = !(settings.cert_path.exists() && settings.key_path.exists());
+    if settings.self_signed && missing {
+        return Ok(format!(
+            "self-signed certificate will be generated at {}",
+            settings.cert_path.display()
+        ));
+    }
+    tls::load_rustls_config(settings, &config.host).await?;
+    Ok(settings.cert_path.display().to_string())
+}
+
+#[cfg(unix)]
+fn check_socket(config: &ForgeServerConfig) -> Result<String> {
+    let Some(settings) = &config.socket else {
+        return Ok("disabled".into());
+    };
+    if std::os::unix::net::UnixStream::connect(&settings.path).is_ok() {
+        bail!(
+            "Another process is already listening on {}",
+            settings.path.display()
+        );
+    }
+    Ok(format!(
+        "{} (mode {:o})",
+        settings.path.display(),
+        settings.mode
+    ))
+}
+
+#[cfg(not(unix))]
+fn check_socket(_config: &ForgeServerConfig) -> Result<String> {
+    bail!("Unix socket listeners are not supported on this platform")
+}
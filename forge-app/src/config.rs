@@ -0,0 +1,938 @@
+//! Server Configuration
+//!
+//! Everything `run_server_with_readiness` needs, resolved once at startup from layers
+//! that override each other field by field, lowest precedence first:
+//!
+//! 1. built-in defaults
+//! 2. the TOML config file (`--config`, `FORGE_CONFIG`, or `<asset dir>/config.toml`)
+//! 3. environment variables (`HOST`, `BACKEND_PORT`/`PORT`, `AUTH_REQUIRED`, ...)
+//! 4. command-line flags
+//!
+//! CORS origins are the exception: origins from every layer are combined. Bad values
+//! are reported as errors rather than replaced with defaults.
+//! `forge config print` shows the merged result in config file syntax.
+
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+
+use crate::{
+    logging::{LogFormat, LogSettings},
+    middleware::rate_limit::LimitSettings,
+    telemetry::TelemetrySettings,
+    tls::TlsSettings,
+    unix_socket::SocketSettings,
+};
+
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+pub const DEFAULT_PORT: u16 = 8887;
+const CONFIG_FILE_NAME: &str = "config.toml";
+/// How many ports `8887+` / `auto` try before giving up
+pub const MAX_PORT_PROBES: u16 = 100;
+
+/// Port to listen on: `8887`, or `8887+` / `auto` for the first free port from there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortSpec {
+    Fixed(u16),
+    FirstFree(u16),
+}
+
+impl PortSpec {
+    /// The preferred port
+    pub fn first(self) -> u16 {
+        match self {
+            Self::Fixed(port) | Self::FirstFree(port) => port,
+        }
+    }
+
+    /// Ports to try, in order
+    pub fn candidates(self) -> impl Iterator<Item = u16> {
+        let (start, count) = match self {
+            Self::Fixed(port) => (port, 1),
+            Self::FirstFree(port) => (port, MAX_PORT_PROBES),
+        };
+        (start..=u16::MAX).take(count as usize)
+    }
+}
+
+impl Default for PortSpec {
+    fn default() -> Self {
+        Self::Fixed(DEFAULT_PORT)
+    }
+}
+
+impl FromStr for PortSpec {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let raw = raw.trim();
+        let invalid = || "expected a port number such as 8887, 8887+ or auto".to_string();
+        if raw.eq_ignore_ascii_case("auto") {
+            return Ok(Self::FirstFree(DEFAULT_PORT));
+        }
+        match raw.strip_suffix('+') {
+            Some(start) => start.parse().map(Self::FirstFree).map_err(|_| invalid()),
+            None => raw.parse().map(Self::Fixed).map_err(|_| invalid()),
+        }
+    }
+}
+
+impl fmt::Display for PortSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed(port) => write!(f, "{port}"),
+            Self::FirstFree(port) => write!(f, "{port}+"),
+        }
+    }
+}
+
+impl Serialize for PortSpec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Fixed(port) => serializer.serialize_u16(*port),
+            Self::FirstFree(_) => serializer.collect_str(self),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PortSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u16),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(port) => Ok(Self::Fixed(port)),
+            Raw::Text(raw) => raw.parse().map_err(D::Error::custom),
+        }
+    }
+}
+
+/// Configuration of the running server, set once by `run_server_with_readiness`
+static ACTIVE: OnceLock<ForgeServerConfig> = OnceLock::new();
+
+/// One source of settings; unset fields fall through to lower-precedence layers
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<PortSpec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_required: Option<bool>,
+    /// GitHub logins that may sign in when authentication is required
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_logins: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_browser: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_base_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cors_origins: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<bool>,
+    #[serde(default, skip_serializing_if = "TlsLayer::is_empty")]
+    pub tls: TlsLayer,
+    #[serde(default, skip_serializing_if = "SocketLayer::is_empty")]
+    pub socket: SocketLayer,
+    #[serde(default, skip_serializing_if = "LogLayer::is_empty")]
+    pub log: LogLayer,
+    #[serde(default, skip_serializing_if = "TelemetryLayer::is_empty")]
+    pub telemetry: TelemetryLayer,
+    #[serde(default, skip_serializing_if = "LimitsLayer::is_empty")]
+    pub limits: LimitsLayer,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub self_signed: Option<bool>,
+    /// Extra DNS names and IPs for the self-signed certificate
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub self_signed_names: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SocketLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only: Option<bool>,
+    /// Octal permission bits as a string, e.g. "660"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogLayer {
+    /// `EnvFilter` directives, e.g. "info" or "forge_app_lib=debug,info"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<LogFormat>,
+    /// Also write daily rolling log files to `<asset dir>/logs`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelemetryLayer {
+    /// OTLP/HTTP collector for task lifecycle traces, e.g. "http://localhost:4318"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub otlp_endpoint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+}
+
+/// Limits on starting coding agents; 0 turns a limit off
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_starts_per_minute: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_start_burst: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_running_agents: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_timeout_secs: Option<u64>,
+}
+
+impl TlsLayer {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl SocketLayer {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl LogLayer {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl TelemetryLayer {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl LimitsLayer {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl ConfigLayer {
+    /// Parse a TOML config file; unknown keys are errors so typos do not go unnoticed
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&raw).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Settings from environment variables; empty variables count as unset. Flags take
+    /// `1`/`true`/`yes` or `0`/`false`/`no`, except the legacy `AUTH_REQUIRED`, which
+    /// is enabled by presence so that no existing value turns authentication off.
+    pub fn from_env() -> Result<Self> {
+        let port = match (env("BACKEND_PORT"), env("PORT")) {
+            (Some(raw), _) => Some(parse_env("BACKEND_PORT", &raw)?),
+            (None, Some(raw)) => Some(parse_env("PORT", &raw)?),
+            (None, None) => None,
+        };
+
+        Ok(Self {
+            host: env("HOST"),
+            port,
+            auth_required: env("AUTH_REQUIRED").map(|_| true),
+            allowed_logins: env("FORGE_ALLOWED_LOGINS").map(|logins| split_list(&logins)),
+            open_browser: env_flag("DISABLE_BROWSER_OPEN")?.map(|disable| !disable),
+            database_url: env("DATABASE_URL"),
+            public_base_url: env("PUBLIC_BASE_URL"),
+            cors_origins: env("FORGE_CORS_ORIGINS")
+                .map(|origins| split_list(&origins))
+                .unwrap_or_default(),
+            version: env("FORGE_VERSION"),
+            metrics: env_flag("FORGE_METRICS")?,
+            tls: TlsLayer {
+                cert: env("FORGE_TLS_CERT").map(PathBuf::from),
+                key: env("FORGE_TLS_KEY").map(PathBuf::from),
+                self_signed: env_flag("FORGE_TLS_SELF_SIGNED")?,
+                self_signed_names: env("FORGE_TLS_SELF_SIGNED_NAMES")
+                    .map(|names| split_list(&names))
+                    .unwrap_or_default(),
+                redirect_port: env("FORGE_TLS_REDIRECT_PORT")
+                    .map(|raw| parse_env("FORGE_TLS_REDIRECT_PORT", &raw))
+                    .transpose()?,
+            },
+            socket: SocketLayer {
+                path: env("FORGE_SOCKET").map(PathBuf::from),
+                only: env_flag("FORGE_SOCKET_ONLY")?,
+                mode: env("FORGE_SOCKET_MODE"),
+            },
+            log: LogLayer {
+                level: env("FORGE_LOG_LEVEL").or_else(|| env("RUST_LOG")),
+                format: env("FORGE_LOG_FORMAT")
+                    .map(|raw| parse_env("FORGE_LOG_FORMAT", &raw))
+                    .transpose()?,
+                files: env_flag("FORGE_LOG_FILES")?,
+                max_files: env("FORGE_LOG_MAX_FILES")
+                    .map(|raw| parse_env("FORGE_LOG_MAX_FILES", &raw))
+                    .transpose()?,
+            },
+            telemetry: TelemetryLayer {
+                otlp_endpoint: env("OTEL_EXPORTER_OTLP_ENDPOINT"),
+                service_name: env("OTEL_SERVICE_NAME"),
+            },
+            limits: LimitsLayer {
+                agent_starts_per_minute: env("FORGE_AGENT_STARTS_PER_MINUTE")
+                    .map(|raw| parse_env("FORGE_AGENT_STARTS_PER_MINUTE", &raw))
+                    .transpose()?,
+                agent_start_burst: env("FORGE_AGENT_START_BURST")
+                    .map(|raw| parse_env("FORGE_AGENT_START_BURST", &raw))
+                    .transpose()?,
+                max_running_agents: env("FORGE_MAX_RUNNING_AGENTS")
+                    .map(|raw| parse_env("FORGE_MAX_RUNNING_AGENTS", &raw))
+                    .transpose()?,
+                queue_timeout_secs: env("FORGE_AGENT_QUEUE_TIMEOUT_SECS")
+                    .map(|raw| parse_env("FORGE_AGENT_QUEUE_TIMEOUT_SECS", &raw))
+                    .transpose()?,
+            },
+        })
+    }
+
+    /// Apply `other` on top of this layer
+    pub fn merge(&mut self, other: Self) {
+        overlay(&mut self.host, other.host);
+        overlay(&mut self.port, other.port);
+        overlay(&mut self.auth_required, other.auth_required);
+        overlay(&mut self.allowed_logins, other.allowed_logins);
+        overlay(&mut self.open_browser, other.open_browser);
+        overlay(&mut self.database_url, other.database_url);
+        overlay(&mut self.public_base_url, other.public_base_url);
+        self.cors_origins.extend(other.cors_origins);
+        overlay(&mut self.version, other.version);
+        overlay(&mut self.metrics, other.metrics);
+        overlay(&mut self.tls.cert, other.tls.cert);
+        overlay(&mut self.tls.key, other.tls.key);
+        overlay(&mut self.tls.self_signed, other.tls.self_signed);
+        if !other.tls.self_signed_names.is_empty() {
+            self.tls.self_signed_names = other.tls.self_signed_names;
+        }
+        overlay(&mut self.tls.redirect_port, other.tls.redirect_port);
+        overlay(&mut self.socket.path, other.socket.path);
+        overlay(&mut self.socket.only, other.socket.only);
+        overlay(&mut self.socket.mode, other.socket.mode);
+        overlay(&mut self.log.level, other.log.level);
+        overlay(&mut self.log.format, other.log.format);
+        overlay(&mut self.log.files, other.log.files);
+        overlay(&mut self.log.max_files, other.log.max_files);
+        overlay(
+            &mut self.telemetry.otlp_endpoint,
+            other.telemetry.otlp_endpoint,
+        );
+        overlay(
+            &mut self.telemetry.service_name,
+            other.telemetry.service_name,
+        );
+        overlay(
+            &mut self.limits.agent_starts_per_minute,
+            other.limits.agent_starts_per_minute,
+        );
+        overlay(
+            &mut self.limits.agent_start_burst,
+            other.limits.agent_start_burst,
+        );
+        overlay(
+            &mut self.limits.max_running_agents,
+            other.limits.max_running_agents,
+        );
+        overlay(
+            &mut self.limits.queue_timeout_secs,
+            other.limits.queue_timeout_secs,
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForgeServerConfig {
+    /// IP address to bind, or `localhost`
+    pub host: String,
+    /// Resolved to the bound port once the TCP listener is up
+    pub port: PortSpec,
+    /// Require a GitHub session or API token on non-public routes
+    pub auth_required: bool,
+    /// GitHub logins besides the one already signed in that may start a session
+    pub allowed_logins: Vec<String>,
+    /// Open the UI in a browser once the server starts
+    pub open_browser: bool,
+    /// `sqlite:` URL; `None` uses `db.sqlite` in the asset directory
+    pub database_url: Option<String>,
+    /// Externally reachable URL, used for notification links and allowed as a CORS origin
+    pub public_base_url: Option<String>,
+    /// Extra origins allowed to call the API from a browser
+    pub cors_origins: Vec<String>,
+    /// Reported version; the npx wrapper passes its package version via `FORGE_VERSION`
+    pub version: Option<String>,
+    /// Serve Prometheus metrics at `/metrics`
+    pub metrics: bool,
+    pub tls: Option<TlsSettings>,
+    pub socket: Option<SocketSettings>,
+    pub log: LogSettings,
+    /// Task lifecycle trace export; `None` when disabled
+    pub telemetry: Option<TelemetrySettings>,
+    /// Rate limit and concurrency cap on agent starts
+    pub limits: LimitSettings,
+    /// Config file the settings were loaded from, if any
+    pub config_file: Option<PathBuf>,
+}
+
+impl Default for ForgeServerConfig {
+    fn default() -> Self {
+        Self {
+            host: DEFAULT_HOST.to_string(),
+            port: PortSpec::default(),
+            auth_required: false,
+            allowed_logins: Vec::new(),
+            open_browser: true,
+            database_url: None,
+            public_base_url: None,
+            cors_origins: Vec::new(),
+            version: None,
+            metrics: false,
+            tls: None,
+            socket: None,
+            log: LogSettings::default(),
+            telemetry: None,
+            limits: LimitSettings::default(),
+            config_file: None,
+        }
+    }
+}
+
+impl ForgeServerConfig {
+    /// Settings from the default config file and the environment
+    pub fn from_env() -> Result<Self> {
+        Self::load(None, ConfigLayer::default())
+    }
+
+    /// Merge the config file (`config_file`, else `FORGE_CONFIG`, else
+    /// `<asset dir>/config.toml` when it exists), the environment and `overrides`
+    pub fn load(config_file: Option<&Path>, overrides: ConfigLayer) -> Result<Self> {
+        let asset_dir = forge_core_utils::assets::asset_dir();
+        let config_file = match config_file {
+            Some(path) => Some(path.to_path_buf()),
+            None => env("FORGE_CONFIG")
+                .map(PathBuf::from)
+                .or_else(|| Some(asset_dir.join(CONFIG_FILE_NAME)).filter(|path| path.exists())),
+        };
+
+        let mut layer = match &config_file {
+            Some(path) => ConfigLayer::from_file(path)?,
+            None => ConfigLayer::default(),
+        };
+        layer.merge(ConfigLayer::from_env()?);
+        layer.merge(overrides);
+
+        let mut config = Self::resolve(layer, &asset_dir)?;
+        config.config_file = config_file;
+        Ok(config)
+    }
+
+    /// Validate a merged layer, filling in defaults
+    pub fn resolve(layer: ConfigLayer, asset_dir: &Path) -> Result<Self> {
+        let defaults = Self::default();
+
+        let public_base_url = layer
+            .public_base_url
+            .map(|raw| {
+                let url = url::Url::parse(&raw)
+                    .with_context(|| format!("Invalid public_base_url '{raw}'"))?;
+                if !matches!(url.scheme(), "http" | "https") {
+                    bail!("public_base_url must be an http or https URL, got '{raw}'");
+                }
+                Ok(raw.trim_end_matches('/').to_string())
+            })
+            .transpose()?;
+
+        if let Some(url) = &layer.database_url
+            && !url.starts_with("sqlite:")
+        {
+            bail!("database_url must be a sqlite: URL, got '{url}'");
+        }
+
+        let config = Self {
+            host: layer.host.unwrap_or(defaults.host),
+            port: layer.port.unwrap_or(defaults.port),
+            auth_required: layer.auth_required.unwrap_or(defaults.auth_required),
+            allowed_logins: layer.allowed_logins.unwrap_or(defaults.allowed_logins),
+            open_browser: layer.open_browser.unwrap_or(defaults.open_browser),
+            database_url: layer.database_url,
+            public_base_url,
+            cors_origins: layer.cors_origins,
+            version: layer.version,
+            metrics: layer.metrics.unwrap_or(defaults.metrics),
+            tls: TlsSettings::resolve(
+                layer.tls.cert,
+                layer.tls.key,
+                layer.tls.self_signed.unwrap_or(false),
+                layer.tls.self_signed_names,
+                layer.tls.redirect_port,
+                asset_dir,
+            )?,
+            socket: SocketSettings::resolve(
+                layer.socket.path,
+                layer.socket.only.unwrap_or(false),
+                layer.socket.mode.as_deref(),
+            )?,
+            log: LogSettings::resolve(
+                layer.log.level,
+                layer.log.format,
+                layer.log.files.unwrap_or(false),
+                layer.log.max_files,
+                asset_dir,
+            )?,
+            telemetry: TelemetrySettings::resolve(
+                layer.telemetry.otlp_endpoint,
+                layer.telemetry.service_name,
+            )?,
+            limits: LimitSettings::resolve(
+                layer.limits.agent_starts_per_minute,
+                layer.limits.agent_start_burst,
+                layer.limits.max_running_agents,
+                layer.limits.queue_timeout_secs,
+            )?,
+            config_file: None,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reject values and combinations that cannot be served
+    pub fn validate(&self) -> Result<()> {
+        if self.host_ip().is_none() {
+            bail!(
+                "Invalid host '{}'; expected an IP address such as 127.0.0.1, 0.0.0.0 or ::1",
+                self.host
+            );
+        }
+        if let Some(origin) = self
+            .cors_origins
+            .iter()
+            .find(|origin| crate::middleware::cors::normalize_origin(origin).is_none())
+        {
+            bail!("Invalid CORS origin '{origin}'; expected e.g. https://app.example.com or *");
+        }
+        if !self.tcp_enabled() && self.tls.is_some() {
+            bail!(
+                "TLS settings apply to the TCP listener and cannot be used with socket-only mode"
+            );
+        }
+        Ok(())
+    }
+
+    /// Make this the configuration of the running server; the first call wins
+    pub fn install(&self) {
+        if let Some(version) = &self.version {
+            crate::version::set_version(version);
+        }
+        let _ = ACTIVE.set(self.clone());
+    }
+
+    /// Configuration of the running server, if one was installed
+    pub fn active() -> Option<&'static Self> {
+        ACTIVE.get()
+    }
+
+    /// Export `database_url` as `DATABASE_URL`, which the upstream database service reads.
+    ///
+    /// The binary calls this before starting the async runtime; later calls are no-ops
+    /// unless an embedder changed the URL.
+    pub fn export_database_url(&self) {
+        if let Some(url) = &self.database_url
+            && std::env::var("DATABASE_URL").ok().as_ref() != Some(url)
+        {
+            unsafe {
+                std::env::set_var("DATABASE_URL", url);
+            }
+        }
+    }
+
+    /// False when serving only on the Unix socket
+    pub fn tcp_enabled(&self) -> bool {
+        !self.socket.as_ref().is_some_and(|socket| socket.exclusive)
+    }
+
+    fn host_ip(&self) -> Option<IpAddr> {
+        if self.host.eq_ignore_ascii_case("localhost") {
+            return Some(Ipv4Addr::LOCALHOST.into());
+        }
+        self.host.parse().ok()
+    }
+
+    /// Address of the TCP listener, or of its first candidate port
+    pub fn bind_addr(&self) -> SocketAddr {
+        let ip = self.host_ip().unwrap_or(Ipv4Addr::LOCALHOST.into());
+        SocketAddr::from((ip, self.port.first()))
+    }
+
+    fn listener_url(&self) -> String {
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        let addr = self.bind_addr();
+        if addr.ip().is_unspecified() || self.host.eq_ignore_ascii_case("localhost") {
+            format!("{scheme}://localhost:{}", addr.port())
+        } else {
+            format!("{scheme}://{addr}")
+        }
+    }
+
+    /// URL to open in a browser; `None` without a TCP listener
+    pub fn browser_url(&self) -> Option<String> {
+        self.tcp_enabled().then(|| self.listener_url())
+    }
+
+    /// `public_base_url`, or the TCP listener's own URL
+    pub fn public_url(&self) -> String {
+        self.public_base_url
+            .clone()
+            .unwrap_or_else(|| self.listener_url())
+    }
+
+    /// `public_base_url` followed by the configured CORS origins
+    pub fn allowed_origins(&self) -> impl Iterator<Item = &str> {
+        self.public_base_url
+            .iter()
+            .chain(&self.cors_origins)
+            .map(String::as_str)
+    }
+
+    /// The effective settings in config file syntax
+    pub fn to_toml(&self) -> Result<String> {
+        let layer = ConfigLayer {
+            host: Some(self.host.clone()),
+            port: Some(self.port),
+            auth_required: Some(self.auth_required),
+            allowed_logins: Some(self.allowed_logins.clone()).filter(|logins| !logins.is_empty()),
+            open_browser: Some(self.open_browser),
+            database_url: self.database_url.clone(),
+            public_base_url: self.public_base_url.clone(),
+            cors_origins: self.cors_origins.clone(),
+            version: self.version.clone(),
+            metrics: Some(self.metrics),
+            tls: self
+                .tls
+                .as_ref()
+                .map(|tls| TlsLayer {
+                    cert: Some(tls.cert_path.clone()),
+                    key: Some(tls.key_path.clone()),
+                    self_signed: Some(tls.self_signed),
+                    self_signed_names: tls.self_signed_names.clone(),
+                    redirect_port: tls.redirect_port,
+                })
+                .unwrap_or_default(),
+            socket: self
+                .socket
+                .as_ref()
+                .map(|socket| SocketLayer {
+                    path: Some(socket.path.clone()),
+                    only: Some(socket.exclusive),
+                    mode: Some(format!("{:o}", socket.mode)),
+                })
+                .unwrap_or_default(),
+            log: LogLayer {
+                level: Some(self.log.level.clone()),
+                format: Some(self.log.format),
+                files: Some(self.log.directory.is_some()),
+                max_files: Some(self.log.max_files),
+            },
+            telemetry: self
+                .telemetry
+                .as_ref()
+                .map(|telemetry| TelemetryLayer {
+                    otlp_endpoint: Some(telemetry.otlp_endpoint.clone()),
+                    service_name: Some(telemetry.service_name.clone()),
+                })
+                .unwrap_or_default(),
+            limits: LimitsLayer {
+                agent_starts_per_minute: Some(self.limits.agent_starts_per_minute),
+                agent_start_burst: Some(self.limits.agent_start_burst),
+                max_running_agents: Some(self.limits.max_running_agents),
+                queue_timeout_secs: Some(self.limits.queue_timeout.as_secs()),
+            },
+        };
+        Ok(toml::to_string_pretty(&layer)?)
+    }
+}
+
+fn overlay<T>(target: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *target = value;
+    }
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+fn parse_env<T>(name: &str, raw: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    raw.trim()
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid {name} '{raw}': {e}"))
+}
+
+fn env_flag(name: &str) -> Result<Option<bool>> {
+    env(name).map(|raw| parse_flag(name, &raw)).transpose()
+}
+
+fn parse_flag(name: &str, raw: &str) -> Result<bool> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" => Ok(true),
+        "0" | "false" | "no" => Ok(false),
+        _ => anyhow::bail!("Invalid {name} '{raw}': expected 1/true/yes or 0/false/no"),
+    }
+}
+
+fn split_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(layer: ConfigLayer) -> Result<ForgeServerConfig> {
+        ForgeServerConfig::resolve(layer, Path::new("/var/forge"))
+    }
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let mut layer: ConfigLayer = toml::from_str(
+            r#"
+            host = "0.0.0.0"
+            port = 9000
+            cors_origins = ["https://a.example"]
+
+            [socket]
+            path = "/run/forge.sock"
+            mode = "660"
+
+            [log]
+            level = "debug"
+            format = "json"
+
+            [limits]
+            max_running_agents = 2
+            agent_starts_per_minute = 60
+            "#,
+        )
+        .unwrap();
+        layer.merge(ConfigLayer {
+            port: Some(PortSpec::Fixed(9100)),
+            open_browser: Some(false),
+            cors_origins: vec!["https://b.example".into()],
+            log: LogLayer {
+                level: Some("forge_app_lib=trace,info".into()),
+                files: Some(true),
+                ..Default::default()
+            },
+            limits: LimitsLayer {
+                agent_starts_per_minute: Some(0),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let config = resolve(layer).unwrap();
+        assert_eq!(config.host, "0.0.0.0");
+        assert_eq!(config.port, PortSpec::Fixed(9100));
+        assert!(!config.open_browser);
+        assert_eq!(
+            config.cors_origins,
+            ["https://a.example", "https://b.example"]
+        );
+        assert_eq!(config.socket.as_ref().unwrap().mode, 0o660);
+        assert_eq!(config.log.level, "forge_app_lib=trace,info");
+        assert_eq!(config.log.format, LogFormat::Json);
+        assert_eq!(
+            config.log.directory.as_deref(),
+            Some(Path::new("/var/forge/logs"))
+        );
+        assert_eq!(config.limits.max_running_agents, 2);
+        assert_eq!(config.limits.agent_starts_per_minute, 0);
+        assert_eq!(
+            config.browser_url().as_deref(),
+            Some("http://localhost:9100")
+        );
+    }
+
+    #[test]
+    fn port_specs_parse_and_probe_upwards() {
+        assert_eq!("9000".parse(), Ok(PortSpec::Fixed(9000)));
+        assert_eq!("9000+".parse(), Ok(PortSpec::FirstFree(9000)));
+        assert_eq!("auto".parse(), Ok(PortSpec::FirstFree(DEFAULT_PORT)));
+        assert!("90000".parse::<PortSpec>().is_err());
+        assert!("+".parse::<PortSpec>().is_err());
+
+        assert_eq!(
+            PortSpec::Fixed(9000).candidates().collect::<Vec<_>>(),
+            [9000]
+        );
+        assert_eq!(
+            PortSpec::FirstFree(9000).candidates().count(),
+            MAX_PORT_PROBES as usize
+        );
+        assert_eq!(
+            PortSpec::FirstFree(u16::MAX - 1)
+                .candidates()
+                .collect::<Vec<_>>(),
+            [u16::MAX - 1, u16::MAX]
+        );
+
+        let layer: ConfigLayer = toml::from_str(r#"port = "8900+""#).unwrap();
+        assert_eq!(layer.port, Some(PortSpec::FirstFree(8900)));
+        assert_eq!(layer.port.unwrap().to_string(), "8900+");
+    }
+
+    #[test]
+    fn bad_values_are_errors() {
+        assert!(toml::from_str::<ConfigLayer>("prot = 9000").is_err());
+        assert!(toml::from_str::<ConfigLayer>("port = 70000").is_err());
+        assert!(toml::from_str::<ConfigLayer>("[log]\nformat = \"xml\"").is_err());
+
+        for layer in [
+            ConfigLayer {
+                host: Some("forge.lan".into()),
+                ..Default::default()
+            },
+            ConfigLayer {
+                public_base_url: Some("javascript:alert(1)".into()),
+                ..Default::default()
+            },
+            ConfigLayer {
+                database_url: Some("postgres://db".into()),
+                ..Default::default()
+            },
+            ConfigLayer {
+                cors_origins: vec!["not an origin".into()],
+                ..Default::default()
+            },
+            ConfigLayer {
+                log: LogLayer {
+                    level: Some("forge_app_lib=loud".into()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ConfigLayer {
+                limits: LimitsLayer {
+                    agent_start_burst: Some(0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ConfigLayer {
+                socket: SocketLayer {
+                    only: Some(true),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ConfigLayer {
+                tls: TlsLayer {
+                    self_signed: Some(true),
+                    ..Default::default()
+                },
+                socket: SocketLayer {
+                    path: Some("/run/forge.sock".into()),
+                    only: Some(true),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ] {
+            assert!(resolve(layer.clone()).is_err(), "{layer:?}");
+        }
+    }
+
+    #[test]
+    fn flags_parse_strictly() {
+        for raw in ["1", "true", "YES", " yes "] {
+            assert!(parse_flag("FORGE_METRICS", raw).unwrap(), "{raw}");
+        }
+        for raw in ["0", "false", "No"] {
+            assert!(!parse_flag("FORGE_METRICS", raw).unwrap(), "{raw}");
+        }
+        for raw in ["on", "enabled", "2"] {
+            assert!(parse_flag("FORGE_METRICS", raw).is_err(), "{raw}");
+        }
+    }
+
+    #[test]
+    fn printed_config_loads_back_unchanged() {
+        let config = resolve(ConfigLayer {
+            host: Some("::1".into()),
+            public_base_url: Some("https://forge.example.com/".into()),
+            telemetry: TelemetryLayer {
+                otlp_endpoint: Some("http://localhost:4318".into()),
+                ..Default::default()
+            },
+            tls: TlsLayer {
+                self_signed: Some(true),
+                ..Default::default()
+            },
+            limits: LimitsLayer {
+                queue_timeout_secs: Some(120),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(config.public_url(), "https://forge.example.com");
+        assert_eq!(config.browser_url().as_deref(), Some("https://[::1]:8887"));
+
+        let printed = config.to_toml().unwrap();
+        let reloaded = resolve(toml::from_str(&printed).unwrap()).unwrap();
+        assert_eq!(reloaded, config);
+    }
+}
@@ -0,0 +1,129 @@
+//! Discovery File
+//!
+//! Once its listeners are up the server writes `<asset dir>/server.json` with its
+//! URL, port and socket, so the npx wrapper and editor plugins can find an instance
+//! started with `--port auto` or `--port 8887+` without scraping its logs. The same
+//! [`ServerInfo`] is sent on the readiness channel. The file is removed on graceful
+//! shutdown; one left behind by a crash names a PID that is no longer running.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::ForgeServerConfig;
+
+pub const DISCOVERY_FILE_NAME: &str = "server.json";
+
+/// How to reach a running instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub pid: u32,
+    /// Base URL of the TCP listener; `None` in socket-only mode
+    pub url: Option<String>,
+    pub port: Option<u16>,
+    pub socket: Option<PathBuf>,
+    pub version: String,
+    pub started_at: DateTime<Utc>,
+}
+
+impl ServerInfo {
+    /// Describe the listeners of `config`, whose port is already the bound one
+    pub fn new(config: &ForgeServerConfig) -> Self {
+        let url = config.browser_url();
+        Self {
+            pid: std::process::id(),
+            port: url.as_ref().map(|_| config.port.first()),
+            url,
+            socket: config.socket.as_ref().map(|socket| socket.path.clone()),
+            version: crate::version::get_version().to_string(),
+            started_at: Utc::now(),
+        }
+    }
+}
+
+/// `server.json` in `asset_dir`
+pub fn file_path(asset_dir: &Path) -> PathBuf {
+    asset_dir.join(DISCOVERY_FILE_NAME)
+}
+
+/// Write the file atomically, so readers never see a partial one
+pub fn write(asset_dir: &Path, info: &ServerInfo) -> Result<()> {
+    let path = file_path(asset_dir);
+    let tmp = path.with_extension(format!("json.{}.tmp", info.pid));
+    std::fs::write(&tmp, serde_json::to_vec_pretty(info)?)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, &path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// The last instance that announced itself, if any
+pub fn read(asset_dir: &Path) -> Result<Option<ServerInfo>> {
+    let path = file_path(asset_dir);
+    let contents = match std::fs::read(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    serde_json::from_slice(&contents)
+        .map(Some)
+        .with_context(|| format!("Invalid discovery file {}", path.display()))
+}
+
+/// Remove the file if it still describes process `pid`; a newer instance may have
+/// replaced it
+pub fn remove(asset_dir: &Path, pid: u32) {
+    if let Ok(Some(info)) = read(asset_dir)
+        && info.pid == pid
+    {
+        let _ = std::fs::remove_file(file_path(asset_dir));
+    }
+}
+
+/// Publish `info` through the discovery file and the readiness channel
+pub(crate) fn announce(
+    info: ServerInfo,
+    ready_tx: Option<tokio::sync::oneshot::Sender<ServerInfo>>,
+) {
+    let asset_dir = forge_core_utils::assets::asset_dir();
+    if let Err(e) = write(&asset_dir, &info) {
+        tracing::warn!("Failed to write discovery file: {:#}", e);
+    }
+    if let Some(tx) = ready_tx {
+        let _ = tx.send(info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(pid: u32) -> ServerInfo {
+        ServerInfo {
+            pid,
+            url: Some("http://localhost:8890".into()),
+            port: Some(8890),
+            socket: None,
+            version: "1.2.3".into(),
+            started_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn written_info_reads_back_and_is_removed_only_by_its_owner() {
+        let dir = std::env::temp_dir().join(format!("forge-discovery-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(read(&dir).unwrap(), None);
+
+        write(&dir, &info(41)).unwrap();
+        write(&dir, &info(42)).unwrap();
+        assert_eq!(read(&dir).unwrap().map(|info| info.pid), Some(42));
+
+        remove(&dir, 41);
+        assert!(file_path(&dir).exists());
+        remove(&dir, 42);
+        assert!(!file_path(&dir).exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
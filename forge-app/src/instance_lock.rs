@@ -0,0 +1,137 @@
+//! Single-Instance Lock
+//!
+//! Two servers on the same asset directory share one SQLite database and would each
+//! run the Omni worker, the PR monitor and the orphaned execution cleanup. The first
+//! one therefore holds an advisory lock on `<asset dir>/forge.lock` for as long as it
+//! runs, and records its PID, port and version in the file. The operating system
+//! releases the lock when the process exits, so a crash never leaves a stale lock.
+
+use std::{
+    fmt,
+    fs::{File, OpenOptions, TryLockError},
+    io::{Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::discovery::{self, ServerInfo};
+
+pub const LOCK_FILE_NAME: &str = "forge.lock";
+
+/// Returned by [`InstanceLock::acquire`] when another server holds the lock
+#[derive(Debug)]
+pub struct AlreadyRunning {
+    pub asset_dir: PathBuf,
+    /// What the running server recorded; `None` while it is still starting up
+    pub info: Option<ServerInfo>,
+}
+
+impl fmt::Display for AlreadyRunning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Forge is already running")?;
+        if let Some(info) = &self.info {
+            write!(f, " (PID {}, version {})", info.pid, info.version)?;
+            match (&info.url, &info.socket) {
+                (Some(url), _) => write!(f, " at {url}")?,
+                (None, Some(socket)) => write!(f, " on {}", socket.display())?,
+                (None, None) => {}
+            }
+        }
+        write!(
+            f,
+            " for {}.\n  Stop it first; a second server on the same data would duplicate \
+             its Omni worker, PR monitor and cleanup runs.",
+            self.asset_dir.display()
+        )
+    }
+}
+
+impl std::error::Error for AlreadyRunning {}
+
+/// Held for the lifetime of the server; dropping it releases the lock
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Take the lock, failing with [`AlreadyRunning`] if another process holds it
+    pub fn acquire(asset_dir: &Path) -> Result<Self> {
+        let path = asset_dir.join(LOCK_FILE_NAME);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(Self { file, path }),
+            Err(TryLockError::WouldBlock) => Err(AlreadyRunning {
+                asset_dir: asset_dir.to_path_buf(),
+                info: read_holder(&path, asset_dir),
+            }
+            .into()),
+            Err(TryLockError::Error(e)) => {
+                Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+            }
+        }
+    }
+
+    /// Record how to reach this instance for later launches
+    pub fn record(&mut self, info: &ServerInfo) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        (&self.file).write_all(&serde_json::to_vec_pretty(info)?)?;
+        self.file
+            .sync_data()
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// Details of the instance holding the lock. Windows locks are mandatory, so the
+/// lock file itself may be unreadable there; the discovery file has the same
+/// details once that instance is up.
+fn read_holder(path: &Path, asset_dir: &Path) -> Option<ServerInfo> {
+    std::fs::read(path)
+        .ok()
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .or_else(|| discovery::read(asset_dir).ok().flatten())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_acquire_reports_the_running_instance() {
+        let dir = std::env::temp_dir().join(format!("forge-lock-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut lock = InstanceLock::acquire(&dir).unwrap();
+        let err = InstanceLock::acquire(&dir).unwrap_err();
+        let running = err.downcast_ref::<AlreadyRunning>().unwrap();
+        assert!(running.info.is_none());
+
+        let info = ServerInfo {
+            pid: 4242,
+            url: Some("http://localhost:8891".into()),
+            port: Some(8891),
+            socket: None,
+            version: "1.2.3".into(),
+            started_at: chrono::Utc::now(),
+        };
+        lock.record(&info).unwrap();
+        let err = InstanceLock::acquire(&dir).unwrap_err();
+        let running = err.downcast_ref::<AlreadyRunning>().unwrap();
+        assert_eq!(running.info.as_ref(), Some(&info));
+        assert!(err.to_string().contains("PID 4242"));
+
+        drop(lock);
+        InstanceLock::acquire(&dir).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -2,19 +2,37 @@
 //!
 //! Provides reusable modules for forge binaries.
 
+pub mod cli;
+pub mod config;
+pub mod discovery;
+pub mod instance_lock;
+pub mod logging;
+pub mod metrics;
+pub mod middleware;
+pub mod openapi;
 pub mod router;
 pub mod services;
+pub mod telemetry;
+pub mod tls;
+pub mod unix_socket;
 pub mod version;
 
-use std::net::{IpAddr, SocketAddr};
+use std::net::SocketAddr;
 
 use tokio::signal;
 
+use crate::{
+    config::{ForgeServerConfig, MAX_PORT_PROBES, PortSpec},
+    discovery::ServerInfo,
+    instance_lock::InstanceLock,
+    metrics::Metrics,
+};
+
 /// Check if a port conflict exists and provide diagnostic information.
 ///
 /// This is a helper function that attempts to identify which process is using a port.
 /// Platform-specific implementations provide varying levels of detail.
-fn check_port_conflict(port: u16, host: &str) -> String {
+pub(crate) fn check_port_conflict(port: u16, host: &str) -> String {
     find_process_using_port(port, host).unwrap_or_else(|| {
         format!("Port {port} may be in use by another process (unable to identify which)")
     })
@@ -108,21 +126,61 @@ fn find_process_using_port(_port: u16, _host: &str) -> Option<String> {
     None
 }
 
+/// Bind the configured port, or with `8887+` / `auto` the first free one from there
+pub(crate) fn bind_tcp_listener(
+    config: &ForgeServerConfig,
+) -> anyhow::Result<std::net::TcpListener> {
+    let host = config.host.as_str();
+    let ip = config.bind_addr().ip();
+    let probing = matches!(config.port, PortSpec::FirstFree(_));
+
+    for port in config.port.candidates() {
+        match std::net::TcpListener::bind((ip, port)) {
+            Ok(listener) => {
+                if port != config.port.first() {
+                    tracing::info!("Port {} is in use, using {}", config.port.first(), port);
+                }
+                return Ok(listener);
+            }
+            Err(e) if probing && e.kind() == std::io::ErrorKind::AddrInUse => continue,
+            Err(e) => {
+                // Enhanced error message with port conflict detection
+                let error_msg = format!(
+                    "Failed to bind to {}:{}\n  Error: {}\n  {}",
+                    host,
+                    port,
+                    e,
+                    check_port_conflict(port, host)
+                );
+                tracing::error!("{}", error_msg);
+                return Err(anyhow::anyhow!(error_msg));
+            }
+        }
+    }
+
+    anyhow::bail!(
+        "No free port on {} among the {} ports from {}",
+        host,
+        MAX_PORT_PROBES,
+        config.port.first()
+    )
+}
+
 /// Run the Forge server with optional readiness notification
 ///
-/// When `ready_tx` is provided, sends a signal after the server successfully binds
-/// to the port and is ready to accept connections. This is useful for Android JNI
-/// to avoid race conditions where the WebView tries to connect before the server starts.
+/// When `ready_tx` is provided, sends the [`ServerInfo`] (including the bound port)
+/// after the server successfully binds and is ready to accept connections. This is
+/// useful for Android JNI to avoid race conditions where the WebView tries to
+/// connect before the server starts. The same information is written to the
+/// discovery file in the asset directory.
 ///
-/// Note: Caller is responsible for initializing tracing subscriber.
+/// Note: Caller is responsible for initializing tracing, e.g. with [`logging::init`].
 pub async fn run_server_with_readiness(
-    ready_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    mut config: ForgeServerConfig,
+    ready_tx: Option<tokio::sync::oneshot::Sender<ServerInfo>>,
 ) -> anyhow::Result<()> {
-    // Parse auth flag from environment
-    let auth_required = std::env::var("AUTH_REQUIRED").is_ok();
-    if auth_required {
-        tracing::info!("GitHub authentication required for frontend access");
-    }
+    // Validate settings before the slower service startup
+    config.validate()?;
 
     // Ensure asset directory exists before initializing services
     // This prevents "unable to open database file" errors when the directory
@@ -141,8 +199,36 @@ pub async fn run_server_with_readiness(
         ));
     }
 
+    // One server per asset directory; a second one would duplicate the background workers
+    let mut instance_lock = InstanceLock::acquire(&asset_path)?;
+
+    // Bind before service startup, so the resolved port is part of the installed configuration
+    let listener = if config.tcp_enabled() {
+        let listener = bind_tcp_listener(&config)?;
+        config.port = PortSpec::Fixed(listener.local_addr()?.port());
+        Some(listener)
+    } else {
+        None
+    };
+    config.install();
+    if let Err(e) = instance_lock.record(&ServerInfo::new(&config)) {
+        tracing::warn!("Failed to record this instance in the lock file: {:#}", e);
+    }
+    config.export_database_url();
+    if let Some(path) = &config.config_file {
+        tracing::info!("Loaded configuration from {}", path.display());
+    }
+    if config.auth_required {
+        tracing::info!("GitHub sign-in required for API access (except the public routes)");
+        if config.allowed_logins.is_empty() {
+            tracing::info!(
+                "Only the GitHub account already signed in may start a session; set allowed_logins to admit others"
+            );
+        }
+    }
+
     // Check if DATABASE_URL is set (may override default path)
-    if let Ok(db_url) = std::env::var("DATABASE_URL") {
+    if let Some(db_url) = &config.database_url {
         tracing::info!("DATABASE_URL is set: {}", db_url);
     } else {
         let default_db_path = asset_path.join("db.sqlite");
@@ -162,6 +248,12 @@ pub async fn run_server_with_readiness(
         }
     }
 
+    // Install the recorder before the services start recording
+    let metrics = config.metrics.then(Metrics::install).transpose()?;
+    if metrics.is_some() {
+        tracing::info!("Serving Prometheus metrics at /metrics");
+    }
+
     // Initialize services
     tracing::info!("Initializing forge services using upstream deployment");
     let services = crate::services::ForgeServices::new().await?;
@@ -170,62 +262,135 @@ pub async fn run_server_with_readiness(
     services.load_genie_profiles_for_all_projects().await?;
 
     // Create router
-    let app = router::create_router(services, auth_required);
-
-    // Resolve bind address
-    let host = std::env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
-    let port: u16 = std::env::var("BACKEND_PORT")
-        .or_else(|_| std::env::var("PORT"))
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(8887);
-
-    // Parse host as IpAddr first to support both IPv4 and IPv6
-    let ip: IpAddr = host.parse().unwrap_or_else(|_| {
-        tracing::warn!("Invalid HOST value '{}', falling back to 127.0.0.1", host);
-        IpAddr::from([127, 0, 0, 1])
-    });
-    let addr = SocketAddr::from((ip, port));
-
-    // Bind and serve
-    let listener = match tokio::net::TcpListener::bind(addr).await {
-        Ok(listener) => listener,
-        Err(e) => {
-            // Enhanced error message with port conflict detection
-            let error_msg = format!(
-                "Failed to bind to {}:{}\n  Error: {}\n  {}",
-                host,
-                port,
-                e,
-                check_port_conflict(port, &host)
-            );
-            tracing::error!("{}", error_msg);
-            return Err(anyhow::anyhow!(error_msg));
-        }
-    };
+    let cors_policy =
+        middleware::cors::CorsPolicy::new(config.allowed_origins()).with_bind_host(&config.host);
+    if !cors_policy.extra_origins().is_empty() {
+        tracing::info!(
+            "Allowing cross-origin API access from: {}",
+            cors_policy.extra_origins().join(", ")
+        );
+    }
+    let app = router::create_router(
+        services,
+        config.auth_required,
+        config.allowed_logins.clone(),
+        config.tls.is_some(),
+        cors_policy,
+        metrics,
+        config.limits.clone(),
+    );
+
+    // Unix socket listener, alongside TCP or instead of it
+    let socket_server = config
+        .socket
+        .as_ref()
+        .map(|settings| unix_socket::start(settings, app.clone(), shutdown_signal()))
+        .transpose()?;
+
+    if let Some(listener) = listener {
+        serve_tcp(app, listener, &config, ready_tx).await?;
+    } else {
+        discovery::announce(ServerInfo::new(&config), ready_tx);
+    }
+
+    if let Some(socket_server) = socket_server {
+        socket_server.await??;
+    }
+    discovery::remove(&asset_path, std::process::id());
+    drop(instance_lock);
+
+    tracing::info!("Forge app shut down gracefully");
+    Ok(())
+}
+
+/// Serve on the bound TCP listener (HTTPS when configured) until shutdown
+async fn serve_tcp(
+    app: axum::Router,
+    listener: std::net::TcpListener,
+    config: &ForgeServerConfig,
+    ready_tx: Option<tokio::sync::oneshot::Sender<ServerInfo>>,
+) -> anyhow::Result<()> {
+    let host = config.host.as_str();
+    let addr = config.bind_addr();
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::TcpListener::from_std(listener)?;
 
     let actual_addr = listener.local_addr()?;
-    tracing::info!("Forge app listening on {}", actual_addr);
 
-    // Signal readiness after successful bind (for Android JNI synchronization)
-    if let Some(tx) = ready_tx {
-        let _ = tx.send(());
+    // Load the certificate before signalling readiness so a bad one fails startup
+    let tls_config = match &config.tls {
+        Some(settings) => {
+            let rustls_config = tls::load_rustls_config(settings, host).await?;
+            tls::watch_certificate_files(rustls_config.clone(), settings)?;
+            Some(rustls_config)
+        }
+        None => None,
+    };
+    let scheme = if tls_config.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    tracing::info!("Forge app listening on {}://{}", scheme, actual_addr);
+
+    if let Some(redirect_port) = config.tls.as_ref().and_then(|s| s.redirect_port) {
+        let redirect_addr = SocketAddr::from((addr.ip(), redirect_port));
+        let redirect_listener =
+            tokio::net::TcpListener::bind(redirect_addr)
+                .await
+                .map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to bind HTTPS redirect listener to {}: {}\n  {}",
+                        redirect_addr,
+                        e,
+                        check_port_conflict(redirect_port, host)
+                    )
+                })?;
+        let https_port = actual_addr.port();
+        tokio::spawn(async move {
+            if let Err(e) =
+                tls::serve_https_redirect(redirect_listener, https_port, shutdown_signal()).await
+            {
+                tracing::error!("HTTPS redirect listener stopped: {}", e);
+            }
+        });
     }
 
-    // Graceful shutdown
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    // Signal readiness after successful bind (for Android JNI synchronization)
+    discovery::announce(ServerInfo::new(config), ready_tx);
+
+    // Graceful shutdown; peer addresses key the agent start limits of anonymous callers
+    match tls_config {
+        Some(tls_config) => {
+            let handle = axum_server::Handle::new();
+            let shutdown_handle = handle.clone();
+            tokio::spawn(async move {
+                shutdown_signal().await;
+                shutdown_handle.graceful_shutdown(None);
+            });
+            axum_server::from_tcp_rustls(listener.into_std()?, tls_config)
+                .handle(handle)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?;
+        }
+        None => {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(shutdown_signal())
+            .await?;
+        }
+    }
 
-    tracing::info!("Forge app shut down gracefully");
     Ok(())
 }
 
-/// Run the Forge server (backwards-compatible wrapper)
+/// Run the Forge server configured from the environment (backwards-compatible wrapper)
 ///
-/// Note: Caller is responsible for initializing tracing subscriber.
+/// Note: Caller is responsible for initializing tracing, e.g. with [`logging::init`].
 pub async fn run_server() -> anyhow::Result<()> {
-    run_server_with_readiness(None).await
+    run_server_with_readiness(ForgeServerConfig::from_env()?, None).await
 }
 
 /// Wait for shutdown signal
@@ -256,3 +421,27 @@ async fn shutdown_signal() {
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigLayer;
+
+    fn config(port: PortSpec) -> ForgeServerConfig {
+        let layer = ConfigLayer {
+            port: Some(port),
+            ..Default::default()
+        };
+        ForgeServerConfig::resolve(layer, std::path::Path::new("/var/forge")).unwrap()
+    }
+
+    #[test]
+    fn first_free_port_skips_ports_in_use() {
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+
+        assert!(bind_tcp_listener(&config(PortSpec::Fixed(port))).is_err());
+        let listener = bind_tcp_listener(&config(PortSpec::FirstFree(port))).unwrap();
+        assert_ne!(listener.local_addr().unwrap().port(), port);
+    }
+}
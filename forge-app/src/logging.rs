@@ -0,0 +1,197 @@
+//! Logging
+//!
+//! Log lines go to stdout as human-readable text (`pretty`, the default) or as one
+//! JSON object per line (`json`), filtered by `EnvFilter` directives such as `info`
+//! or `forge_app_lib=debug,info`. With `log.files` they are also written to daily
+//! rolling files in `<asset dir>/logs`, keeping the newest `log.max_files`.
+//!
+//! Every HTTP request gets an `x-request-id` (a client-supplied one is kept) and a
+//! `request` span carrying it, so all lines logged while serving it can be found
+//! by ID. Notifications a request queues store its ID, and their Omni deliveries are
+//! logged in an `omni_delivery` span carrying it.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{Context, Result, bail};
+use axum::{
+    body::Body,
+    extract::MatchedPath,
+    http::{HeaderMap, Request},
+};
+use serde::{Deserialize, Serialize};
+use tracing::Span;
+use tracing_appender::{non_blocking::WorkerGuard, rolling};
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry, fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt,
+};
+
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+pub const DEFAULT_MAX_LOG_FILES: usize = 7;
+const LOG_DIR_NAME: &str = "logs";
+const LOG_FILE_PREFIX: &str = "forge";
+
+/// Output format of log lines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => Err("expected pretty or json".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pretty => f.write_str("pretty"),
+            Self::Json => f.write_str("json"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogSettings {
+    /// `EnvFilter` directives
+    pub level: String,
+    pub format: LogFormat,
+    /// Folder of the rolling log files; `None` logs to stdout only
+    pub directory: Option<PathBuf>,
+    /// Rotated files kept in `directory`
+    pub max_files: usize,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_LOG_LEVEL.to_string(),
+            format: LogFormat::default(),
+            directory: None,
+            max_files: DEFAULT_MAX_LOG_FILES,
+        }
+    }
+}
+
+impl LogSettings {
+    pub(crate) fn resolve(
+        level: Option<String>,
+        format: Option<LogFormat>,
+        files: bool,
+        max_files: Option<usize>,
+        asset_dir: &Path,
+    ) -> Result<Self> {
+        let defaults = Self::default();
+        let level = level.unwrap_or(defaults.level);
+        EnvFilter::try_new(&level).with_context(|| {
+            format!("Invalid log level '{level}'; expected e.g. info or forge_app_lib=debug,info")
+        })?;
+
+        let max_files = max_files.unwrap_or(defaults.max_files);
+        if max_files == 0 {
+            bail!("log max_files must be at least 1");
+        }
+
+        Ok(Self {
+            level,
+            format: format.unwrap_or(defaults.format),
+            directory: files.then(|| asset_dir.join(LOG_DIR_NAME)),
+            max_files,
+        })
+    }
+}
+
+/// Flushes and closes the log file when dropped; keep it alive while the process logs
+pub struct LogGuard {
+    _file: Option<WorkerGuard>,
+}
+
+/// Install the global subscriber. Fails if one is already installed.
+pub fn init(settings: &LogSettings) -> Result<LogGuard> {
+    let filter = EnvFilter::try_new(&settings.level)?;
+    let mut layers = vec![format_layer(settings.format, std::io::stdout, true)];
+
+    let file = match &settings.directory {
+        Some(directory) => {
+            let appender = rolling::Builder::new()
+                .rotation(rolling::Rotation::DAILY)
+                .filename_prefix(LOG_FILE_PREFIX)
+                .filename_suffix("log")
+                .max_log_files(settings.max_files)
+                .build(directory)
+                .with_context(|| format!("Failed to open log files in {}", directory.display()))?;
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            layers.push(format_layer(settings.format, writer, false));
+            Some(guard)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .try_init()?;
+
+    if let Some(directory) = &settings.directory {
+        tracing::info!("Writing logs to {}", directory.display());
+    }
+    Ok(LogGuard { _file: file })
+}
+
+fn format_layer<W>(
+    format: LogFormat,
+    writer: W,
+    ansi: bool,
+) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match format {
+        LogFormat::Pretty => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
+
+/// `x-request-id` of a request that passed the router's request ID layer
+pub(crate) fn request_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Span of one HTTP request, made by the router's trace layer after the request ID
+/// has been assigned
+pub fn request_span(request: &Request<Body>) -> Span {
+    let request_id = request_id(request.headers()).unwrap_or_default();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("fallback", MatchedPath::as_str);
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        route,
+        path = request.uri().path(),
+        request_id,
+    )
+}
@@ -0,0 +1,229 @@
+//! Prometheus Metrics
+//!
+//! Opt-in with `metrics = true` in the config file, `FORGE_METRICS` or `--metrics`;
+//! `/metrics` then serves the Prometheus text format:
+//!
+//! - `forge_http_requests_total` and `forge_http_request_duration_seconds` per route,
+//!   recorded by the [`track_http`] layer of `create_router`
+//! - `forge_notification_deliveries_total` (by sink and outcome) and
+//!   `forge_notification_delivery_duration_seconds` per sink
+//! - `forge_profile_reloads_total` for `.genie` changes the profile cache reloads
+//! - `forge_omni_queue_depth`, `forge_executions` and `forge_tokens_consumed`, read
+//!   from the database on every scrape
+//!
+//! Without an installed recorder the `record_*` functions are no-ops.
+
+use std::time::{Duration, Instant};
+
+use ::metrics::{
+    Unit, counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram,
+};
+use anyhow::Result;
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use sqlx::SqlitePool;
+
+/// How often histogram samples are folded into their buckets between scrapes
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(10);
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+/// Always reported, so a drained status drops to zero instead of keeping its last value
+const OMNI_QUEUE_STATUSES: &[&str] = &["pending", "processing", "sent", "skipped", "dead_letter"];
+const EXECUTION_STATUSES: &[&str] = &["running", "completed", "failed", "killed"];
+
+/// Handle to the installed recorder, rendered by `/metrics`
+#[derive(Clone)]
+pub struct Metrics {
+    handle: PrometheusHandle,
+}
+
+impl Metrics {
+    /// Install the process-wide recorder. Call once, before the services start
+    /// recording.
+    pub fn install() -> Result<Self> {
+        let handle = PrometheusBuilder::new()
+            .set_buckets_for_metric(
+                Matcher::Suffix("_duration_seconds".to_string()),
+                LATENCY_BUCKETS,
+            )?
+            .install_recorder()?;
+        describe();
+
+        let upkeep = handle.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                upkeep.run_upkeep();
+            }
+        });
+
+        Ok(Self { handle })
+    }
+
+    /// Refresh the gauges read from the database, then render every metric
+    pub async fn render(&self, pool: &SqlitePool) -> Result<String> {
+        record_database_gauges(pool).await?;
+        Ok(self.handle.render())
+    }
+}
+
+fn describe() {
+    describe_counter!(
+        "forge_http_requests_total",
+        "HTTP requests by route and status"
+    );
+    describe_histogram!(
+        "forge_http_request_duration_seconds",
+        Unit::Seconds,
+        "Time until the response headers were ready"
+    );
+    describe_counter!(
+        "forge_notification_deliveries_total",
+        "Notification deliveries by sink and outcome (sent or failed)"
+    );
+    describe_histogram!(
+        "forge_notification_delivery_duration_seconds",
+        Unit::Seconds,
+        "Time a sink took to accept or reject a notification"
+    );
+    describe_counter!(
+        "forge_profile_reloads_total",
+        "Executor profile reloads after .genie changes"
+    );
+    describe_gauge!("forge_omni_queue_depth", "Omni notifications by status");
+    describe_gauge!(
+        "forge_executions",
+        "Coding agent executions by executor and status"
+    );
+    describe_gauge!(
+        "forge_tokens_consumed",
+        "Tokens used by task attempts, by executor and kind"
+    );
+}
+
+/// Middleware counting requests and their latency under the matched route template
+pub async fn track_http(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    // Unmatched paths are served by the frontend fallback
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "fallback".to_string(), |path| path.as_str().to_string());
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    counter!(
+        "forge_http_requests_total",
+        "method" => method.clone(),
+        "route" => route.clone(),
+        "status" => status
+    )
+    .increment(1);
+    histogram!(
+        "forge_http_request_duration_seconds",
+        "method" => method,
+        "route" => route
+    )
+    .record(started.elapsed().as_secs_f64());
+    response
+}
+
+pub fn record_notification_delivery(sink: &str, elapsed: Duration, delivered: bool) {
+    let outcome = if delivered { "sent" } else { "failed" };
+    counter!(
+        "forge_notification_deliveries_total",
+        "sink" => sink.to_string(),
+        "outcome" => outcome
+    )
+    .increment(1);
+    histogram!(
+        "forge_notification_delivery_duration_seconds",
+        "sink" => sink.to_string()
+    )
+    .record(elapsed.as_secs_f64());
+}
+
+pub fn record_profile_reload() {
+    counter!("forge_profile_reloads_total").increment(1);
+}
+
+async fn record_database_gauges(pool: &SqlitePool) -> Result<()> {
+    for status in OMNI_QUEUE_STATUSES {
+        gauge!("forge_omni_queue_depth", "status" => *status).set(0.0);
+    }
+    let queue: Vec<(String, i64)> =
+        sqlx::query_as("SELECT status, COUNT(*) FROM forge_omni_notifications GROUP BY status")
+            .fetch_all(pool)
+            .await?;
+    for (status, count) in queue {
+        gauge!("forge_omni_queue_depth", "status" => status).set(count as f64);
+    }
+
+    let executors: Vec<String> = sqlx::query_scalar("SELECT DISTINCT executor FROM task_attempts")
+        .fetch_all(pool)
+        .await?;
+    for executor in executors {
+        for status in EXECUTION_STATUSES {
+            gauge!("forge_executions", "executor" => executor.clone(), "status" => *status)
+                .set(0.0);
+        }
+    }
+    let executions: Vec<(String, String, i64)> = sqlx::query_as(
+        r#"SELECT ta.executor, ep.status, COUNT(*)
+             FROM execution_processes ep
+             JOIN task_attempts ta ON ta.id = ep.task_attempt_id
+            WHERE ep.run_reason = 'codingagent'
+            GROUP BY ta.executor, ep.status"#,
+    )
+    .fetch_all(pool)
+    .await?;
+    for (executor, status, count) in executions {
+        gauge!("forge_executions", "executor" => executor, "status" => status).set(count as f64);
+    }
+
+    let tokens: Vec<(String, i64, i64, i64, i64)> = sqlx::query_as(
+        r#"SELECT executor,
+                  COALESCE(SUM(input_tokens), 0),
+                  COALESCE(SUM(output_tokens), 0),
+                  COALESCE(SUM(cache_creation_tokens), 0),
+                  COALESCE(SUM(cache_read_tokens), 0)
+             FROM task_attempts
+            GROUP BY executor"#,
+    )
+    .fetch_all(pool)
+    .await?;
+    for (executor, input, output, cache_creation, cache_read) in tokens {
+        for (kind, value) in [
+            ("input", input),
+            ("output", output),
+            ("cache_creation", cache_creation),
+            ("cache_read", cache_read),
+        ] {
+            gauge!("forge_tokens_consumed", "executor" => executor.clone(), "kind" => kind)
+                .set(value as f64);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn database_gauges_read_the_current_schema() {
+        let pool = crate::services::test_support::pool().await;
+
+        // No recorder is installed, so this only checks the queries
+        record_database_gauges(&pool).await.unwrap();
+    }
+}
@@ -0,0 +1,470 @@
+//! Session Authentication
+//!
+//! With `AUTH_REQUIRED` set, every route outside the public allowlist needs a session
+//! or an API token. Sessions are issued as an HttpOnly cookie when the upstream GitHub
+//! device flow reports `SUCCESS` for an allowed account (see [`LoginGuard`]), and only
+//! a hash of each token is stored in
+//! `forge_auth_sessions`. Scripts send `Authorization: Bearer <token>` instead, limited
+//! to the operations their token scopes allow (see `ApiOperation::scope`).
+//! The frontend fallback is not a route, so static assets stay reachable for the login UI.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use forge_core_deployment::Deployment;
+use forge_core_server::DeploymentImpl;
+use forge_core_services::services::config::save_config_to_file;
+use forge_core_utils::assets::config_path;
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+
+use super::lifecycle_events::buffer_json_response;
+use crate::services::api_tokens::{self, TokenScope, hash_token, random_hex};
+
+pub const SESSION_COOKIE: &str = "forge_session";
+/// Nonce binding a GitHub device flow to the browser that started it
+const LOGIN_COOKIE: &str = "forge_login";
+/// Lifetime of the nonce; GitHub device codes expire after 15 minutes
+const LOGIN_TTL_SECS: u64 = 15 * 60;
+
+const SESSION_TTL_DAYS: i64 = 30;
+
+/// Marks requests that arrived on the Unix socket, whose file permissions already
+/// decide who may connect
+#[derive(Debug, Clone, Copy)]
+pub struct LocalSocket;
+
+/// Enforces sessions or API tokens on every route it is layered on, except public ones
+#[derive(Clone)]
+pub struct AuthGuard {
+    pool: SqlitePool,
+    required: bool,
+    routes: Arc<[(Method, String, Option<TokenScope>)]>,
+}
+
+impl AuthGuard {
+    /// `routes` holds method, path template (`/api/items/{id}`) and required token
+    /// scope for each registered operation; `None` marks a public route
+    pub fn new(
+        pool: SqlitePool,
+        required: bool,
+        routes: impl IntoIterator<Item = (Method, String, Option<TokenScope>)>,
+    ) -> Self {
+        Self {
+            pool,
+            required,
+            routes: routes.into_iter().collect(),
+        }
+    }
+
+    /// Access rule for a request; unregistered routes need a session or an admin token
+    fn access(&self, method: &Method, path: &str) -> Option<TokenScope> {
+        // HEAD is answered by GET routes
+        let method = if method == Method::HEAD {
+            &Method::GET
+        } else {
+            method
+        };
+        self.routes
+            .iter()
+            .find(|(route_method, template, _)| {
+                route_method == method && matches_template(template, path)
+            })
+            .map_or(Some(TokenScope::Admin), |(_, _, scope)| *scope)
+    }
+}
+
+fn matches_template(template: &str, path: &str) -> bool {
+    let mut expected = template.trim_end_matches('/').split('/');
+    let mut actual = path.trim_end_matches('/').split('/');
+    loop {
+        match (expected.next(), actual.next()) {
+            (None, None) => return true,
+            (Some(segment), Some(value)) => {
+                let is_param = segment.starts_with('{') && segment.ends_with('}');
+                if !(segment == value || (is_param && !value.is_empty())) {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// Reject requests without a valid session or sufficiently scoped API token when
+/// authentication is required
+pub async fn require_session(
+    State(guard): State<AuthGuard>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !guard.required || request.extensions().get::<LocalSocket>().is_some() {
+        return next.run(request).await;
+    }
+    let Some(required_scope) = guard.access(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    if let Some(token) = bearer_token(request.headers()) {
+        return match api_tokens::authenticate_api_token(&guard.pool, &token).await {
+            Ok(Some(scopes)) if scopes.iter().any(|scope| *scope >= required_scope) => {
+                next.run(request).await
+            }
+            Ok(Some(_)) => error_response(
+                StatusCode::FORBIDDEN,
+                &format!("This API token needs the '{required_scope}' scope for this route"),
+            ),
+            Ok(None) => error_response(
+                StatusCode::UNAUTHORIZED,
+                "Invalid, expired or revoked API token",
+            ),
+            Err(e) => {
+                tracing::error!("Failed to validate API token: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to validate API token",
+                )
+                    .into_response()
+            }
+        };
+    }
+
+    let Some(token) = session_token(request.headers()) else {
+        return unauthorized();
+    };
+
+    match validate_session(&guard.pool, &token).await {
+        Ok(true) => next.run(request).await,
+        Ok(false) => unauthorized(),
+        Err(e) => {
+            tracing::error!("Failed to validate session: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to validate session",
+            )
+                .into_response()
+        }
+    }
+}
+
+fn unauthorized() -> Response {
+    error_response(
+        StatusCode::UNAUTHORIZED,
+        "Authentication required; sign in with GitHub or send an API token",
+    )
+}
+
+/// Same shape as upstream's `ApiResponse`, so the frontend surfaces the message
+pub(super) fn error_response(status: StatusCode, message: &str) -> Response {
+    (
+        status,
+        axum::Json(json!({
+            "success": false,
+            "data": null,
+            "error_data": null,
+            "message": message,
+        })),
+    )
+        .into_response()
+}
+
+/// The GitHub account the upstream device flow signs in, stored in the deployment config
+#[async_trait]
+pub trait GitHubAccount: Send + Sync {
+    async fn current(&self) -> StoredAccount;
+    /// Put back an account saved by `current`
+    async fn restore(&self, account: StoredAccount) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StoredAccount {
+    pub login: Option<String>,
+    /// Upstream's whole GitHub config, credentials included
+    pub saved: Value,
+}
+
+#[async_trait]
+impl GitHubAccount for DeploymentImpl {
+    async fn current(&self) -> StoredAccount {
+        let config = self.config().read().await;
+        StoredAccount {
+            login: config.github.username.clone(),
+            saved: serde_json::to_value(&config.github).unwrap_or_default(),
+        }
+    }
+
+    async fn restore(&self, account: StoredAccount) -> Result<()> {
+        let github = serde_json::from_value(account.saved)?;
+        let mut config = self.config().write().await;
+        config.github = github;
+        save_config_to_file(&config, &config_path()).await?;
+        Ok(())
+    }
+}
+
+/// Decides who the GitHub device flow may sign in when authentication is required.
+///
+/// The flow is bound to the browser that started it by a nonce cookie, so nobody
+/// can poll someone else's flow to completion. A session is only issued for a login
+/// in `allowed_logins` or for the account that was stored before the flow; any
+/// other account is signed out again.
+#[derive(Clone)]
+pub struct LoginGuard {
+    pool: SqlitePool,
+    required: bool,
+    account: Arc<dyn GitHubAccount>,
+    allowed_logins: Arc<[String]>,
+    flow: Arc<Mutex<Option<PendingLogin>>>,
+    secure_cookies: bool,
+}
+
+/// The device flow in progress
+struct PendingLogin {
+    nonce_hash: String,
+    before: StoredAccount,
+}
+
+impl LoginGuard {
+    pub fn new(
+        pool: SqlitePool,
+        required: bool,
+        account: Arc<dyn GitHubAccount>,
+        allowed_logins: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Self {
+            pool,
+            required,
+            account,
+            allowed_logins: allowed_logins.into_iter().collect(),
+            flow: Arc::default(),
+            secure_cookies: false,
+        }
+    }
+
+    /// Mark the session and login cookies `Secure` when the server is served over TLS
+    pub fn with_secure_cookies(mut self, secure: bool) -> Self {
+        self.secure_cookies = secure;
+        self
+    }
+
+    fn may_sign_in(&self, login: &str, before: &StoredAccount) -> bool {
+        self.allowed_logins
+            .iter()
+            .chain(&before.login)
+            .any(|allowed| allowed.eq_ignore_ascii_case(login))
+    }
+}
+
+/// Bind the upstream device flow to the browser that started it, and issue a session
+/// cookie once its poll reports `SUCCESS` for an allowed account
+pub async fn start_session_on_login(
+    State(guard): State<LoginGuard>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let is_start = path.ends_with("/auth/github/device/start");
+    let is_poll = path.ends_with("/auth/github/device/poll");
+    if !guard.required || request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    if is_start {
+        start_login(&guard, request, next).await
+    } else if is_poll {
+        finish_login(&guard, request, next).await
+    } else {
+        next.run(request).await
+    }
+}
+
+async fn start_login(guard: &LoginGuard, request: Request, next: Next) -> Response {
+    let before = guard.account.current().await;
+    let mut response = next.run(request).await;
+    if !response.status().is_success() {
+        return response;
+    }
+
+    let nonce = random_hex(16);
+    *guard.flow.lock().unwrap_or_else(|e| e.into_inner()) = Some(PendingLogin {
+        nonce_hash: hash_token(&nonce),
+        before,
+    });
+    response.headers_mut().append(
+        header::SET_COOKIE,
+        login_cookie(&nonce, LOGIN_TTL_SECS, guard.secure_cookies),
+    );
+    response
+}
+
+async fn finish_login(guard: &LoginGuard, request: Request, next: Next) -> Response {
+    let nonce_hash = cookie(request.headers(), LOGIN_COOKIE).map(|nonce| hash_token(&nonce));
+    let started_here = guard
+        .flow
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .is_some_and(|flow| Some(&flow.nonce_hash) == nonce_hash.as_ref());
+    if !started_here {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            "This sign-in was not started from this browser; start it again here",
+        );
+    }
+
+    let response = next.run(request).await;
+    if !response.status().is_success() {
+        return response;
+    }
+    let (mut response, body) = buffer_json_response(response).await;
+    if body
+        .as_ref()
+        .and_then(|body| body.get("data"))
+        .and_then(Value::as_str)
+        != Some("SUCCESS")
+    {
+        return response;
+    }
+
+    let Some(flow) = guard.flow.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return response;
+    };
+    // Upstream stored the new GitHub identity in the deployment config before responding
+    let login = guard.account.current().await.login;
+    let Some(login) = login.filter(|login| guard.may_sign_in(login, &flow.before)) else {
+        if let Err(e) = guard.account.restore(flow.before).await {
+            tracing::error!(
+                "Failed to restore the GitHub account after a rejected sign-in: {}",
+                e
+            );
+        }
+        return error_response(
+            StatusCode::FORBIDDEN,
+            "This GitHub account may not sign in to this server; add it to allowed_logins",
+        );
+    };
+
+    match create_session(&guard.pool, Some(&login)).await {
+        Ok(token) => {
+            let headers = response.headers_mut();
+            headers.append(
+                header::SET_COOKIE,
+                session_cookie(&token, guard.secure_cookies),
+            );
+            headers.append(
+                header::SET_COOKIE,
+                login_cookie("", 0, guard.secure_cookies),
+            );
+        }
+        Err(e) => tracing::error!("Failed to create session after GitHub login: {}", e),
+    }
+
+    response
+}
+
+/// Create a session and return its token; the token itself is never stored
+pub async fn create_session(pool: &SqlitePool, github_login: Option<&str>) -> Result<String> {
+    sqlx::query("DELETE FROM forge_auth_sessions WHERE expires_at <= datetime('now')")
+        .execute(pool)
+        .await?;
+
+    let token = random_hex(32);
+
+    sqlx::query(
+        "INSERT INTO forge_auth_sessions (token_hash, github_login, expires_at)
+         VALUES (?, ?, datetime('now', ?))",
+    )
+    .bind(hash_token(&token))
+    .bind(github_login)
+    .bind(format!("+{SESSION_TTL_DAYS} days"))
+    .execute(pool)
+    .await?;
+
+    Ok(token)
+}
+
+/// True when the token belongs to an unexpired session; refreshes `last_seen_at`
+pub async fn validate_session(pool: &SqlitePool, token: &str) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE forge_auth_sessions SET last_seen_at = datetime('now')
+         WHERE token_hash = ? AND expires_at > datetime('now')",
+    )
+    .bind(hash_token(token))
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn revoke_session(pool: &SqlitePool, token: &str) -> Result<()> {
+    sqlx::query("DELETE FROM forge_auth_sessions WHERE token_hash = ?")
+        .bind(hash_token(token))
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Session token from the `forge_session` cookie
+pub fn session_token(headers: &HeaderMap) -> Option<String> {
+    cookie(headers, SESSION_COOKIE)
+}
+
+fn cookie(headers: &HeaderMap, wanted: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, value)| *name == wanted && !value.is_empty())
+        .map(|(_, value)| value.to_string())
+}
+
+/// API token from `Authorization: Bearer <token>`
+pub fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+}
+
+/// `secure` adds the `Secure` attribute, so browsers only send the cookie over HTTPS
+pub fn session_cookie(token: &str, secure: bool) -> HeaderValue {
+    let max_age = SESSION_TTL_DAYS * 24 * 60 * 60;
+    HeaderValue::from_str(&format!(
+        "{SESSION_COOKIE}={token}; Path=/; HttpOnly; SameSite=Strict; Max-Age={max_age}{}",
+        secure_attribute(secure)
+    ))
+    .expect("session tokens are hex")
+}
+
+/// Nonce of the device flow this browser started; empty with `max_age` 0 to clear it
+fn login_cookie(nonce: &str, max_age: u64, secure: bool) -> HeaderValue {
+    HeaderValue::from_str(&format!(
+        "{LOGIN_COOKIE}={nonce}; Path=/api/auth/github; HttpOnly; SameSite=Strict; Max-Age={max_age}{}",
+        secure_attribute(secure)
+    ))
+    .expect("login nonces are hex")
+}
+
+pub fn expired_session_cookie(secure: bool) -> HeaderValue {
+    HeaderValue::from_str(&format!(
+        "{SESSION_COOKIE}=; Path=/; HttpOnly; SameSite=Strict; Max-Age=0{}",
+        secure_attribute(secure)
+    ))
+    .expect("cookie is ASCII")
+}
+
+fn secure_attribute(secure: bool) -> &'static str {
+    if secure { "; Secure" } else { "" }
+}
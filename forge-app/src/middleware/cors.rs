@@ -0,0 +1,308 @@
+//! Cross-Origin Policy
+//!
+//! Forge runs agents against local repositories, so a page on another site must not be
+//! able to drive it. Browsers may call the API from the same origin, from localhost on
+//! any port (the Vite dev server, Swagger UI) and from origins allowed explicitly via
+//! `public_base_url` and `cors_origins` in the server config (`PUBLIC_BASE_URL`,
+//! `FORGE_CORS_ORIGINS`, `forge --cors-origin`). Requests from any other origin get no
+//! CORS headers and are rejected before reaching a handler, which also covers "simple"
+//! requests that browsers send without a preflight.
+//!
+//! A page that rebinds its own DNS name to 127.0.0.1 is same-origin with the server, so
+//! the `Host` header is checked too: it must be localhost, a loopback IP, the bind
+//! address, or the host of `public_base_url` or a configured origin. When bound to all
+//! interfaces any IP address is accepted, since IP literals cannot be rebound.
+
+use std::{net::IpAddr, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, Method, StatusCode, header, request::Parts, uri::Authority},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
+
+/// How long browsers may cache a preflight response
+const PREFLIGHT_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Origins allowed to call the API from a browser
+#[derive(Debug, Clone, Default)]
+pub struct CorsPolicy {
+    /// Normalized `scheme://host[:port]` origins, or `*` to allow any origin
+    extra_origins: Arc<[String]>,
+    /// Lowercase names besides localhost that the server answers to
+    hosts: Arc<[String]>,
+    /// Bound to all interfaces, so any IP address names the server
+    any_ip: bool,
+}
+
+impl CorsPolicy {
+    pub fn new(extra_origins: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        let extra_origins: Arc<[String]> = extra_origins
+            .into_iter()
+            .filter_map(|origin| normalize_origin(origin.as_ref()))
+            .collect();
+        let hosts = extra_origins
+            .iter()
+            .filter_map(|origin| origin.split_once("://"))
+            .map(|(_, authority)| hostname(authority).to_string())
+            .collect();
+        Self {
+            extra_origins,
+            hosts,
+            any_ip: false,
+        }
+    }
+
+    /// Also answer to the address the server is bound to (`HOST`)
+    pub fn with_bind_host(mut self, host: &str) -> Self {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        match host.parse::<IpAddr>() {
+            Ok(ip) if ip.is_unspecified() => self.any_ip = true,
+            _ => {
+                self.hosts = self
+                    .hosts
+                    .iter()
+                    .cloned()
+                    .chain([host.to_ascii_lowercase()])
+                    .collect();
+            }
+        }
+        self
+    }
+
+    pub fn extra_origins(&self) -> &[String] {
+        &self.extra_origins
+    }
+
+    /// Whether the request names this server; requests without a host pass
+    pub fn allows_host(&self, parts: &Parts) -> bool {
+        let Some(authority) = parts
+            .headers
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .or_else(|| parts.uri.authority().map(Authority::as_str))
+        else {
+            return true;
+        };
+
+        let host = hostname(authority);
+        is_localhost(host)
+            || (self.any_ip && host.parse::<IpAddr>().is_ok())
+            || self
+                .hosts
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    }
+
+    /// Same-origin, localhost and explicitly allowed origins, on an allowed host
+    pub fn allows(&self, origin: &HeaderValue, parts: &Parts) -> bool {
+        if !self.allows_host(parts) {
+            return false;
+        }
+        let Some(origin) = origin.to_str().ok().and_then(normalize_origin) else {
+            return false;
+        };
+        let Some((_, authority)) = origin.split_once("://") else {
+            return false;
+        };
+
+        // The scheme is ignored so a TLS-terminating proxy still counts as same-origin
+        let same_origin = parts
+            .headers
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .is_some_and(|host| host.eq_ignore_ascii_case(authority));
+
+        same_origin
+            || is_localhost(hostname(authority))
+            || self
+                .extra_origins
+                .iter()
+                .any(|allowed| allowed == "*" || *allowed == origin)
+    }
+
+    pub fn layer(&self) -> CorsLayer {
+        let policy = self.clone();
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::predicate(move |origin, parts| {
+                policy.allows(origin, parts)
+            }))
+            .allow_methods([
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+                Method::OPTIONS,
+            ])
+            .allow_headers(AllowHeaders::mirror_request())
+            .max_age(PREFLIGHT_MAX_AGE)
+    }
+}
+
+/// Reject requests for an unknown `Host` and requests whose `Origin` the policy does
+/// not allow; requests without an `Origin` header (curl, scripts, same-origin
+/// navigations) only need an allowed host
+pub async fn reject_disallowed_origins(
+    State(policy): State<CorsPolicy>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    if !policy.allows_host(&parts) {
+        tracing::warn!(
+            "Rejected {} {} for unknown host {:?}",
+            parts.method,
+            parts.uri.path(),
+            parts.headers.get(header::HOST)
+        );
+        return forbidden(
+            "Unknown Host; add the server's public URL to PUBLIC_BASE_URL or FORGE_CORS_ORIGINS",
+        );
+    }
+
+    let Some(origin) = parts.headers.get(header::ORIGIN).cloned() else {
+        return next.run(Request::from_parts(parts, body)).await;
+    };
+    if policy.allows(&origin, &parts) {
+        return next.run(Request::from_parts(parts, body)).await;
+    }
+
+    tracing::warn!(
+        "Rejected {} {} from disallowed origin {:?}",
+        parts.method,
+        parts.uri.path(),
+        origin
+    );
+    forbidden("Cross-origin request not allowed; add the origin to FORGE_CORS_ORIGINS")
+}
+
+fn forbidden(message: &str) -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        axum::Json(serde_json::json!({
+            "success": false,
+            "data": null,
+            "error_data": null,
+            "message": message,
+        })),
+    )
+        .into_response()
+}
+
+/// `https://Example.com/` -> `https://example.com`; `None` for anything that is not an origin
+pub(crate) fn normalize_origin(origin: &str) -> Option<String> {
+    let origin = origin.trim().trim_end_matches('/');
+    if origin == "*" {
+        return Some(origin.to_string());
+    }
+    let (scheme, authority) = origin.split_once("://")?;
+    if !matches!(scheme, "http" | "https") || authority.is_empty() || authority.contains('/') {
+        return None;
+    }
+    Some(format!("{scheme}://{}", authority.to_ascii_lowercase()))
+}
+
+/// `forge.lan:8887` -> `forge.lan`; `[::1]:3000` -> `::1`
+fn hostname(authority: &str) -> &str {
+    match authority.strip_prefix('[') {
+        // IPv6 literal: [::1]:3000
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    }
+}
+
+fn is_localhost(host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    host == "localhost"
+        || host.ends_with(".localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    fn parts(host: &str) -> Parts {
+        Request::builder()
+            .header(header::HOST, host)
+            .body(())
+            .unwrap()
+            .into_parts()
+            .0
+    }
+
+    fn allows(policy: &CorsPolicy, origin: &str, host: &str) -> bool {
+        policy.allows(&HeaderValue::from_str(origin).unwrap(), &parts(host))
+    }
+
+    #[test]
+    fn same_origin_and_localhost_are_allowed_by_default() {
+        let policy = CorsPolicy::default();
+
+        assert!(allows(&policy, "http://127.0.0.2:8887", "127.0.0.2:8887"));
+        assert!(allows(&policy, "http://localhost:3000", "127.0.0.1:8887"));
+        assert!(allows(&policy, "http://127.0.0.1:5173", "127.0.0.1:8887"));
+        assert!(allows(&policy, "http://[::1]:3000", "[::1]:8887"));
+
+        assert!(!allows(&policy, "https://evil.example", "127.0.0.1:8887"));
+        assert!(!allows(
+            &policy,
+            "http://localhost.evil.example",
+            "127.0.0.1:8887"
+        ));
+        assert!(!allows(&policy, "null", "127.0.0.1:8887"));
+    }
+
+    #[test]
+    fn hosts_are_limited_to_local_bound_and_configured_names() {
+        let policy = CorsPolicy::new(["https://forge.example.com"]).with_bind_host("forge.lan");
+
+        for host in [
+            "localhost:8887",
+            "127.0.0.1:8887",
+            "[::1]:8887",
+            "Forge.lan:8887",
+            "forge.example.com",
+        ] {
+            assert!(policy.allows_host(&parts(host)), "{host}");
+        }
+        assert!(allows(
+            &policy,
+            "https://forge.example.com",
+            "forge.example.com"
+        ));
+        assert!(allows(&policy, "http://forge.lan:8887", "forge.lan:8887"));
+
+        // DNS rebinding: the attacker's page is same-origin with its own name
+        assert!(!policy.allows_host(&parts("evil.example:8887")));
+        assert!(!allows(
+            &policy,
+            "http://evil.example:8887",
+            "evil.example:8887"
+        ));
+        assert!(!policy.allows_host(&parts("192.168.1.20:8887")));
+
+        let all_interfaces = CorsPolicy::default().with_bind_host("0.0.0.0");
+        assert!(all_interfaces.allows_host(&parts("192.168.1.20:8887")));
+        assert!(!all_interfaces.allows_host(&parts("evil.example:8887")));
+    }
+
+    #[test]
+    fn extra_origins_are_normalized() {
+        let policy = CorsPolicy::new([" https://App.Example.com/ ", "not an origin", ""]);
+
+        assert_eq!(policy.extra_origins(), ["https://app.example.com"]);
+        assert!(allows(&policy, "https://app.example.com", "127.0.0.1:8887"));
+        assert!(!allows(&policy, "http://app.example.com", "127.0.0.1:8887"));
+        assert!(allows(
+            &CorsPolicy::new(["*"]),
+            "https://any.example",
+            "127.0.0.1:8887"
+        ));
+    }
+}
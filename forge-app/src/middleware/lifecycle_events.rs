@@ -0,0 +1,381 @@
+//! Lifecycle Event Middleware
+//!
+//! Some notification events never touch the database: approval requests live in the
+//! upstream approvals service and merge/rebase conflicts are only reported in the API
+//! response. These middlewares inspect the upstream responses and queue the matching
+//! notification events.
+
+use axum::{
+    Extension,
+    body::{Body, HttpBody, to_bytes},
+    extract::{Request, State},
+    http::{Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use forge_core_db::models::task_attempt::TaskAttempt;
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::{
+    logging,
+    services::{
+        ForgeServices, OmniWorkerHandle,
+        notification_events::{NotificationEvent, queue_event_notification},
+    },
+};
+
+/// Responses inspected here are small JSON documents; anything larger, or streamed
+/// without a known length, is passed on untouched
+const MAX_INSPECTED_BODY: usize = 1024 * 1024;
+
+/// State of the event middlewares: the queue and the worker delivering it
+#[derive(Clone)]
+pub struct EventQueue {
+    pool: SqlitePool,
+    omni_worker: OmniWorkerHandle,
+}
+
+impl EventQueue {
+    pub fn new(services: &ForgeServices) -> Self {
+        Self {
+            pool: services.pool.clone(),
+            omni_worker: services.omni_worker.clone(),
+        }
+    }
+}
+
+/// Queue `approval_pending` when an executor asks for tool approval
+pub async fn notify_on_approval_request(
+    State(queue): State<EventQueue>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_create =
+        request.method() == Method::POST && request.uri().path().ends_with("/approvals/create");
+    let query_process_id = request.uri().query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "execution_process_id")
+            .map(|(_, value)| value.into_owned())
+    });
+    let request_id = logging::request_id(request.headers()).map(str::to_string);
+
+    let response = next.run(request).await;
+    if !is_create || !response.status().is_success() {
+        return response;
+    }
+
+    let (response, body) = buffer_json_response(response).await;
+    let Some(body) = body else {
+        return response;
+    };
+
+    let Some(approval) = approval_details(&body, query_process_id.as_deref()) else {
+        tracing::debug!("Approval response carried no execution process id; not notifying");
+        return response;
+    };
+
+    let task_attempt_id: Option<Uuid> =
+        match sqlx::query_scalar("SELECT task_attempt_id FROM execution_processes WHERE id = ?")
+            .bind(approval.execution_process_id)
+            .fetch_optional(&queue.pool)
+            .await
+        {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::warn!("Failed to resolve attempt for approval notification: {}", e);
+                None
+            }
+        };
+
+    if let Some(task_attempt_id) = task_attempt_id {
+        queue_event(
+            &queue,
+            NotificationEvent::ApprovalPending,
+            task_attempt_id,
+            "pending",
+            approval.details,
+            request_id.as_deref(),
+        )
+        .await;
+    }
+
+    response
+}
+
+/// Queue `merge_conflict` / `rebase_conflict` when a merge or rebase stops on conflicts.
+/// Layered on the attempt routes, after `load_task_attempt_middleware` has run.
+///
+/// Upstream reports conflicts as `200 OK` with `success: false`, so successful
+/// responses are inspected as well as errors.
+pub async fn notify_on_git_conflicts(
+    State(queue): State<EventQueue>,
+    Extension(task_attempt): Extension<TaskAttempt>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let request_id = logging::request_id(request.headers()).map(str::to_string);
+    let response = next.run(request).await;
+
+    let (response, body) = buffer_json_response(response).await;
+    if let Some((event, details)) = body.as_ref().and_then(conflict_details) {
+        queue_event(
+            &queue,
+            event,
+            task_attempt.id,
+            "conflict",
+            details,
+            request_id.as_deref(),
+        )
+        .await;
+    }
+
+    response
+}
+
+async fn queue_event(
+    queue: &EventQueue,
+    event: NotificationEvent,
+    task_attempt_id: Uuid,
+    status: &str,
+    details: Value,
+    request_id: Option<&str>,
+) {
+    match queue_event_notification(
+        &queue.pool,
+        event,
+        task_attempt_id,
+        status,
+        details,
+        request_id,
+    )
+    .await
+    {
+        Ok(true) => queue.omni_worker.wake(),
+        Ok(false) => {
+            tracing::debug!(%task_attempt_id, "Attempt vanished before '{}' was queued", event)
+        }
+        Err(e) => tracing::warn!("Failed to queue '{}' notification: {}", event, e),
+    }
+}
+
+/// Read a JSON response body so it can be inspected, then rebuild the response
+pub(super) async fn buffer_json_response(response: Response) -> (Response, Option<Value>) {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let fits = response
+        .body()
+        .size_hint()
+        .upper()
+        .is_some_and(|len| len <= MAX_INSPECTED_BODY as u64);
+    if !is_json || !fits {
+        return (response, None);
+    }
+
+    let (parts, body) = response.into_parts();
+    match to_bytes(body, MAX_INSPECTED_BODY).await {
+        Ok(bytes) => {
+            let value = serde_json::from_slice(&bytes).ok();
+            (Response::from_parts(parts, Body::from(bytes)), value)
+        }
+        Err(e) => {
+            tracing::warn!("Failed to buffer response for inspection: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR.into_response(), None)
+        }
+    }
+}
+
+struct ApprovalDetails {
+    execution_process_id: Uuid,
+    details: Value,
+}
+
+/// Fields of the created approval, whether returned bare or wrapped in `ApiResponse`
+fn approval_details(body: &Value, query_process_id: Option<&str>) -> Option<ApprovalDetails> {
+    let approval = body
+        .get("data")
+        .filter(|data| data.is_object())
+        .unwrap_or(body);
+    let execution_process_id = approval
+        .get("execution_process_id")
+        .and_then(Value::as_str)
+        .or(query_process_id)
+        .and_then(|id| Uuid::parse_str(id).ok())?;
+
+    Some(ApprovalDetails {
+        execution_process_id,
+        details: json!({
+            "approval_id": approval.get("id"),
+            "tool_name": approval.get("tool_name"),
+            "execution_process_id": execution_process_id,
+        }),
+    })
+}
+
+/// `GitOperationError::MergeConflicts` carried in the `error_data` of a failed `ApiResponse`
+fn conflict_details(body: &Value) -> Option<(NotificationEvent, Value)> {
+    if body.get("success").and_then(Value::as_bool) != Some(false) {
+        return None;
+    }
+    let error = body.get("error_data")?;
+    if error.get("type").and_then(Value::as_str) != Some("merge_conflicts") {
+        return None;
+    }
+
+    let op = error.get("op").and_then(Value::as_str).unwrap_or("merge");
+    let event = if op == "rebase" {
+        NotificationEvent::RebaseConflict
+    } else {
+        NotificationEvent::MergeConflict
+    };
+
+    Some((
+        event,
+        json!({
+            "op": op,
+            "message": error.get("message").or_else(|| body.get("message")),
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{Json, Router, middleware::from_fn_with_state, routing::post};
+    use forge_core_services::services::forge_config::ForgeConfigService;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::services::test_support;
+
+    #[test]
+    fn approval_details_accept_bare_and_wrapped_responses() {
+        let process_id = Uuid::new_v4();
+        let bare = json!({
+            "id": "approval-1",
+            "tool_name": "Bash",
+            "execution_process_id": process_id,
+        });
+        let wrapped = json!({ "success": true, "data": bare.clone() });
+
+        for body in [bare, wrapped] {
+            let approval = approval_details(&body, None).expect("approval should be detected");
+            assert_eq!(approval.execution_process_id, process_id);
+            assert_eq!(approval.details["tool_name"], "Bash");
+            assert_eq!(approval.details["approval_id"], "approval-1");
+        }
+    }
+
+    #[test]
+    fn approval_details_fall_back_to_query_process_id() {
+        let process_id = Uuid::new_v4().to_string();
+        let body = json!({ "id": "approval-1", "tool_name": "Edit" });
+
+        let approval = approval_details(&body, Some(&process_id)).unwrap();
+        assert_eq!(approval.execution_process_id.to_string(), process_id);
+        assert!(approval_details(&body, None).is_none());
+    }
+
+    #[test]
+    fn conflict_details_distinguish_merge_and_rebase() {
+        let rebase = json!({
+            "success": false,
+            "error_data": { "type": "merge_conflicts", "message": "conflict in src/lib.rs", "op": "rebase" },
+        });
+        let (event, details) = conflict_details(&rebase).unwrap();
+        assert_eq!(event, NotificationEvent::RebaseConflict);
+        assert_eq!(details["message"], "conflict in src/lib.rs");
+
+        let merge = json!({
+            "success": false,
+            "error_data": { "type": "merge_conflicts", "message": "conflict", "op": "merge" },
+        });
+        assert_eq!(
+            conflict_details(&merge).unwrap().0,
+            NotificationEvent::MergeConflict
+        );
+
+        let other = json!({ "success": false, "error_data": { "type": "rebase_in_progress" } });
+        assert!(conflict_details(&other).is_none());
+
+        let succeeded = json!({ "success": true, "error_data": { "type": "merge_conflicts" } });
+        assert!(conflict_details(&succeeded).is_none());
+    }
+
+    #[tokio::test]
+    async fn oversized_responses_pass_through_untouched() {
+        let blob = "x".repeat(MAX_INSPECTED_BODY);
+        let (response, body) =
+            buffer_json_response(Json(json!({ "blob": blob })).into_response()).await;
+        assert!(body.is_none());
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(
+            body["blob"].as_str().map(str::len),
+            Some(MAX_INSPECTED_BODY)
+        );
+    }
+
+    #[tokio::test]
+    async fn conflicts_reported_with_200_are_queued() {
+        let pool = test_support::pool().await;
+        let attempt_id = test_support::seed_attempt(&pool).await.attempt_id;
+        let task_attempt = TaskAttempt::find_by_id(&pool, attempt_id)
+            .await
+            .unwrap()
+            .expect("seeded attempt should load");
+        let queue = EventQueue {
+            pool: pool.clone(),
+            omni_worker: OmniWorkerHandle::spawn(
+                pool.clone(),
+                Arc::new(ForgeConfigService::new(pool.clone())),
+            ),
+        };
+
+        // What upstream's rebase handler answers when the rebase stops on conflicts
+        let rebase = || async {
+            Json(json!({
+                "success": false,
+                "data": null,
+                "error_data": { "type": "merge_conflicts", "message": "conflict in src/lib.rs", "op": "rebase" },
+                "message": null,
+            }))
+        };
+        let response = Router::new()
+            .route("/rebase", post(rebase))
+            .layer(from_fn_with_state(queue, notify_on_git_conflicts))
+            .layer(Extension(task_attempt))
+            .oneshot(
+                Request::post("/rebase")
+                    .header("x-request-id", "req-rebase")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(body["error_data"]["type"], "merge_conflicts");
+
+        let queued: Vec<(String, String)> = sqlx::query_as(
+            "SELECT notification_type, json_extract(metadata, '$.request_id') FROM forge_omni_notifications",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            queued,
+            vec![("rebase_conflict".to_string(), "req-rebase".to_string())]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! Lifecycle Span Middleware
+//!
+//! Records the API calls that create or act on a task attempt as phases of the
+//! attempt's trace (see [`crate::telemetry`]). Pass-through unless trace export is on.
+
+use axum::{
+    Extension,
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use forge_core_db::models::task_attempt::TaskAttempt;
+use serde_json::Value;
+use sqlx::{Row, SqlitePool};
+use uuid::Uuid;
+
+use super::lifecycle_events::buffer_json_response;
+use crate::{services::ForgeServices, telemetry::Phase};
+
+/// Root of the trace for attempts started by `POST /api/tasks/create-and-start`
+pub async fn trace_create_and_start(
+    State(services): State<ForgeServices>,
+    request: Request,
+    next: Next,
+) -> Response {
+    trace_attempt_start(&services.pool, "create_and_start", request, next).await
+}
+
+/// Root of the trace for attempts created by `POST /api/task-attempts`
+pub async fn trace_create_attempt(
+    State(services): State<ForgeServices>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    trace_attempt_start(&services.pool, "create_attempt", request, next).await
+}
+
+async fn trace_attempt_start(
+    pool: &SqlitePool,
+    phase: &'static str,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !crate::telemetry::enabled() {
+        return next.run(request).await;
+    }
+    let started_at = Utc::now();
+    let request_id = request_id(&request);
+
+    let response = next.run(request).await;
+    if !response.status().is_success() {
+        return response;
+    }
+    let (response, body) = buffer_json_response(response).await;
+    let Some(id) = body.as_ref().and_then(response_id) else {
+        return response;
+    };
+
+    // create-and-start answers with the task, create with the attempt
+    let query = if phase == "create_and_start" {
+        "SELECT id, task_id, executor FROM task_attempts WHERE task_id = ? ORDER BY created_at DESC LIMIT 1"
+    } else {
+        "SELECT id, task_id, executor FROM task_attempts WHERE id = ?"
+    };
+    let attempt = match sqlx::query(query).bind(id).fetch_optional(pool).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return response,
+        Err(e) => {
+            tracing::warn!("Failed to look up the attempt for its trace: {}", e);
+            return response;
+        }
+    };
+    let (Ok(attempt_id), Ok(task_id), Ok(executor)) = (
+        attempt.try_get::<Uuid, _>("id"),
+        attempt.try_get::<Uuid, _>("task_id"),
+        attempt.try_get::<String, _>("executor"),
+    ) else {
+        return response;
+    };
+
+    Phase::new(phase, started_at, Utc::now())
+        .attribute("forge.task_id", task_id.to_string())
+        .attribute("forge.executor", executor)
+        .attribute("forge.request_id", request_id)
+        .record_as_root(attempt_id);
+    response
+}
+
+/// Record a call on an existing attempt, named by the layer's state. Layered on the
+/// attempt routes, after `load_task_attempt_middleware` has run.
+///
+/// Upstream reports some failures (merge conflicts, for one) as `200 OK` with
+/// `success: false`, so successful JSON responses are inspected too.
+pub async fn trace_attempt_action(
+    State(phase): State<&'static str>,
+    Extension(task_attempt): Extension<TaskAttempt>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !crate::telemetry::enabled() {
+        return next.run(request).await;
+    }
+    let started_at = Utc::now();
+    let request_id = request_id(&request);
+
+    let response = next.run(request).await;
+    let status = response.status();
+    let (response, body) = if status.is_success() {
+        buffer_json_response(response).await
+    } else {
+        (response, None)
+    };
+
+    let phase = Phase::new(phase, started_at, Utc::now())
+        .attribute("forge.request_id", request_id)
+        .attribute("http.response.status_code", i64::from(status.as_u16()));
+    match failure(status, body.as_ref()) {
+        Some(reason) => phase.failed(reason).record(task_attempt.id),
+        None => phase.record(task_attempt.id),
+    }
+    response
+}
+
+/// Why the call failed: an error status, or an `ApiResponse` with `success: false`
+fn failure(status: StatusCode, body: Option<&Value>) -> Option<String> {
+    if !status.is_success() {
+        return Some(format!("HTTP {status}"));
+    }
+    let body = body?;
+    if body.get("success").and_then(Value::as_bool) != Some(false) {
+        return None;
+    }
+    let reason = body
+        .get("message")
+        .and_then(Value::as_str)
+        .or_else(|| body.get("error_data")?.get("type")?.as_str())
+        .unwrap_or("success: false");
+    Some(reason.to_string())
+}
+
+fn request_id(request: &Request) -> String {
+    request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// `data.id` of an enveloped API response
+fn response_id(body: &Value) -> Option<Uuid> {
+    body.get("data")?.get("id")?.as_str()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn failures_include_unsuccessful_200_responses() {
+        assert_eq!(
+            failure(StatusCode::NOT_FOUND, None).as_deref(),
+            Some("HTTP 404 Not Found")
+        );
+        assert_eq!(failure(StatusCode::OK, None), None);
+        assert_eq!(
+            failure(
+                StatusCode::OK,
+                Some(&json!({ "success": true, "data": {} }))
+            ),
+            None
+        );
+
+        let conflict = json!({
+            "success": false,
+            "error_data": { "type": "merge_conflicts", "op": "rebase" },
+            "message": null,
+        });
+        assert_eq!(
+            failure(StatusCode::OK, Some(&conflict)).as_deref(),
+            Some("merge_conflicts")
+        );
+        let with_message = json!({ "success": false, "message": "Branch is behind" });
+        assert_eq!(
+            failure(StatusCode::OK, Some(&with_message)).as_deref(),
+            Some("Branch is behind")
+        );
+    }
+}
@@ -0,0 +1,9 @@
+//! Forge Middleware
+//!
+//! Axum middleware that forge-app layers onto upstream routers.
+
+pub mod auth;
+pub mod cors;
+pub mod lifecycle_events;
+pub mod lifecycle_spans;
+pub mod rate_limit;
@@ -0,0 +1,382 @@
+//! Agent Start Limits
+//!
+//! Creating and starting a task, creating an attempt and sending a follow-up each
+//! spawn a coding agent. Two limits keep a runaway script from starting dozens:
+//!
+//! - a token bucket per client (API token, session, or else peer IP address)
+//!   allowing `limits.agent_start_burst` starts back to back, refilled at
+//!   `limits.agent_starts_per_minute`. Starts over it get 429 with `Retry-After`.
+//! - a global cap of `limits.max_running_agents` attempts with a running setup
+//!   script, coding agent or cleanup script. Each admitted start reserves a slot
+//!   until its handler has recorded the agent. Starts over the cap wait until an
+//!   agent finishes, and get 429 after `limits.queue_timeout_secs`.
+//!
+//! A limit of 0 turns it off.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+};
+
+use anyhow::{Result, bail};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::Response,
+};
+use sqlx::SqlitePool;
+use tokio::time::{Duration, Instant, sleep, timeout_at};
+
+use super::auth::{bearer_token, error_response, session_token};
+use crate::services::api_tokens::hash_token;
+
+pub const DEFAULT_AGENT_STARTS_PER_MINUTE: u32 = 30;
+pub const DEFAULT_AGENT_START_BURST: u32 = 10;
+pub const DEFAULT_MAX_RUNNING_AGENTS: u32 = 8;
+pub const DEFAULT_QUEUE_TIMEOUT_SECS: u64 = 30;
+/// How often a queued start checks whether an agent has finished
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Buckets kept before refilled ones are dropped
+const MAX_TRACKED_CLIENTS: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitSettings {
+    /// Agent starts each client may make per minute; 0 for no rate limit
+    pub agent_starts_per_minute: u32,
+    /// Starts a client may make back to back before the rate applies
+    pub agent_start_burst: u32,
+    /// Attempts running an agent at once, across all clients; 0 for no cap
+    pub max_running_agents: u32,
+    /// How long a start waits in line for a free slot; 0 rejects it right away
+    pub queue_timeout: Duration,
+}
+
+impl Default for LimitSettings {
+    fn default() -> Self {
+        Self {
+            agent_starts_per_minute: DEFAULT_AGENT_STARTS_PER_MINUTE,
+            agent_start_burst: DEFAULT_AGENT_START_BURST,
+            max_running_agents: DEFAULT_MAX_RUNNING_AGENTS,
+            queue_timeout: Duration::from_secs(DEFAULT_QUEUE_TIMEOUT_SECS),
+        }
+    }
+}
+
+impl LimitSettings {
+    pub(crate) fn resolve(
+        agent_starts_per_minute: Option<u32>,
+        agent_start_burst: Option<u32>,
+        max_running_agents: Option<u32>,
+        queue_timeout_secs: Option<u64>,
+    ) -> Result<Self> {
+        let defaults = Self::default();
+        let agent_start_burst = agent_start_burst.unwrap_or(defaults.agent_start_burst);
+        if agent_start_burst == 0 {
+            bail!("limits agent_start_burst must be at least 1");
+        }
+
+        Ok(Self {
+            agent_starts_per_minute: agent_starts_per_minute
+                .unwrap_or(defaults.agent_starts_per_minute),
+            agent_start_burst,
+            max_running_agents: max_running_agents.unwrap_or(defaults.max_running_agents),
+            queue_timeout: queue_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.queue_timeout),
+        })
+    }
+}
+
+/// Shared state of the limit layers; clones share the buckets and the queue
+#[derive(Clone)]
+pub struct AgentLimiter {
+    pool: SqlitePool,
+    settings: LimitSettings,
+    buckets: Arc<Mutex<HashMap<String, TokenBucket>>>,
+    /// Admitted starts whose handler has not returned yet
+    reserved: Arc<AtomicU32>,
+    /// Held while counting and reserving, so concurrent starts see each other's slots
+    admission: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl AgentLimiter {
+    pub fn new(pool: SqlitePool, settings: LimitSettings) -> Self {
+        Self {
+            pool,
+            settings,
+            buckets: Arc::default(),
+            reserved: Arc::default(),
+            admission: Arc::default(),
+        }
+    }
+
+    /// Take a start from the client's bucket, or say how long until one is available
+    fn take_start(&self, client: &str) -> Result<(), Duration> {
+        if self.settings.agent_starts_per_minute == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+        buckets
+            .entry(client.to_string())
+            .or_insert_with(|| TokenBucket::new(&self.settings, now))
+            .take(now)
+    }
+
+    /// Reserve one of the `max_running_agents` slots, waiting while running agents and
+    /// other starts' reservations take them all; `None` when `deadline` passes first
+    async fn reserve_slot(&self, deadline: Instant) -> Option<SlotReservation> {
+        let limit = i64::from(self.settings.max_running_agents);
+        let mut queued = false;
+        loop {
+            {
+                let _admission = timeout_at(deadline, self.admission.lock()).await.ok()?;
+                let running = match running_agents(&self.pool).await {
+                    Ok(running) => running,
+                    Err(e) => {
+                        // Do not block agent starts on a failed count
+                        tracing::warn!("Failed to count running agents: {}", e);
+                        return Some(self.reserve());
+                    }
+                };
+                // A start whose agent was just recorded counts twice until its handler
+                // returns, which only errs on the side of the cap
+                let starting = i64::from(self.reserved.load(Ordering::SeqCst));
+                if running + starting < limit {
+                    return Some(self.reserve());
+                }
+                if Instant::now() + QUEUE_POLL_INTERVAL > deadline {
+                    return None;
+                }
+                if !queued {
+                    tracing::info!(
+                        "Agent start queued: {running} running and {starting} starting of {limit} agents"
+                    );
+                    queued = true;
+                }
+            }
+            sleep(QUEUE_POLL_INTERVAL).await;
+        }
+    }
+
+    fn reserve(&self) -> SlotReservation {
+        self.reserved.fetch_add(1, Ordering::SeqCst);
+        SlotReservation(self.reserved.clone())
+    }
+}
+
+/// A running-agent slot held by an admitted start until its handler returns, by which
+/// time the agent's execution process is recorded or the start has failed
+struct SlotReservation(Arc<AtomicU32>);
+
+impl Drop for SlotReservation {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    capacity: f64,
+    per_second: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(settings: &LimitSettings, now: Instant) -> Self {
+        let capacity = f64::from(settings.agent_start_burst);
+        Self {
+            tokens: capacity,
+            capacity,
+            per_second: f64::from(settings.agent_starts_per_minute) / 60.0,
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.refilled_at = now;
+    }
+
+    fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.per_second,
+            ))
+        }
+    }
+
+    fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+}
+
+/// Apply both limits to agent-starting `POST`s; other methods pass through
+pub async fn limit_agent_starts(
+    State(limiter): State<AgentLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+
+    if let Err(retry_after) = limiter.take_start(&client_key(&request)) {
+        let mut response = error_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many agent starts, try again later",
+        );
+        // Whole seconds, rounded up so the retry finds a token
+        let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        return response;
+    }
+
+    if limiter.settings.max_running_agents == 0 {
+        return next.run(request).await;
+    }
+    let deadline = Instant::now() + limiter.settings.queue_timeout;
+    let Some(_reservation) = limiter.reserve_slot(deadline).await else {
+        return too_many_running(&limiter.settings);
+    };
+    next.run(request).await
+}
+
+fn too_many_running(settings: &LimitSettings) -> Response {
+    error_response(
+        StatusCode::TOO_MANY_REQUESTS,
+        &format!(
+            "{} agents are already running, try again when one finishes",
+            settings.max_running_agents
+        ),
+    )
+}
+
+/// Bucket key of the caller; secrets are hashed before they are kept
+fn client_key(request: &Request) -> String {
+    let headers = request.headers();
+    if let Some(token) = bearer_token(headers) {
+        format!("token:{}", hash_token(&token))
+    } else if let Some(token) = session_token(headers) {
+        format!("session:{}", hash_token(&token))
+    } else if let Some(ConnectInfo(peer)) = request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        format!("peer:{}", peer.ip())
+    } else {
+        // Unix socket callers are local processes and share one bucket
+        "local".to_string()
+    }
+}
+
+/// Attempts with a running setup script, coding agent or cleanup script
+async fn running_agents(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(DISTINCT task_attempt_id)
+           FROM execution_processes
+          WHERE status = 'running' AND run_reason != 'devserver'",
+    )
+    .fetch_one(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::services::test_support;
+
+    #[test]
+    fn buckets_allow_a_burst_then_refill_at_the_rate() {
+        let settings = LimitSettings::resolve(Some(6), Some(2), None, None).unwrap();
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(&settings, start);
+
+        assert!(bucket.take(start).is_ok());
+        assert!(bucket.take(start).is_ok());
+        assert_eq!(bucket.take(start), Err(Duration::from_secs(10)));
+
+        // 6 per minute: one token every 10 seconds
+        let later = start + Duration::from_secs(10);
+        assert!(bucket.take(later).is_ok());
+        assert!(bucket.take(later).is_err());
+        assert!(bucket.is_full(later + Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn anonymous_callers_are_keyed_by_peer_address() {
+        let from = |peer: &str| {
+            let mut request = Request::new(axum::body::Body::empty());
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+            client_key(&request)
+        };
+
+        assert_eq!(from("192.168.1.20:50001"), from("192.168.1.20:50002"));
+        assert_ne!(from("192.168.1.20:50001"), from("192.168.1.21:50001"));
+
+        let mut with_token = Request::new(axum::body::Body::empty());
+        with_token.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer forge_pat_ci"),
+        );
+        with_token.extensions_mut().insert(ConnectInfo(
+            "192.168.1.20:50001".parse::<SocketAddr>().unwrap(),
+        ));
+        assert!(client_key(&with_token).starts_with("token:"));
+    }
+
+    #[tokio::test]
+    async fn starts_queue_while_the_cap_is_reached() {
+        let pool = test_support::pool().await;
+        let attempt_id = test_support::seed_attempt(&pool).await.attempt_id;
+        let process_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO execution_processes (id, task_attempt_id, run_reason, executor_action, status)
+             VALUES (?, ?, 'codingagent', '{}', 'running')",
+        )
+        .bind(process_id)
+        .bind(attempt_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let limiter = AgentLimiter::new(
+            pool.clone(),
+            LimitSettings::resolve(None, None, Some(1), None).unwrap(),
+        );
+        let soon = || Instant::now() + Duration::from_millis(100);
+        assert!(limiter.reserve_slot(soon()).await.is_none());
+
+        sqlx::query("UPDATE execution_processes SET status = 'completed' WHERE id = ?")
+            .bind(process_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let reservation = limiter.reserve_slot(soon()).await;
+        assert!(reservation.is_some());
+
+        // The admitted start holds the slot until its handler returns
+        assert!(limiter.reserve_slot(soon()).await.is_none());
+        drop(reservation);
+        assert!(limiter.reserve_slot(soon()).await.is_some());
+    }
+}
@@ -0,0 +1,711 @@
+//! OpenAPI Generation
+//!
+//! The spec served at `/api/openapi.json` is built from the same route declarations
+//! `create_router` mounts. Forge-owned routes go through [`ApiRouter`], which records
+//! every method and path as it is registered. Upstream routers are opaque to axum, so
+//! their operations are declared in [`upstream`] and attached where each router is
+//! merged. Schemas come from `schemars` for forge types and from `ts-rs` declarations
+//! for upstream types. The same operation list, with each route's auth requirement and
+//! body limit, is the route registry served at `/api/routes`.
+
+pub mod upstream;
+
+use std::{collections::BTreeMap, convert::Infallible};
+
+use axum::{
+    Router,
+    extract::{DefaultBodyLimit, Request},
+    handler::Handler,
+    http::Method,
+    response::IntoResponse,
+    routing::{MethodRouter, Route},
+};
+use schemars::{JsonSchema, Schema, SchemaGenerator, generate::SchemaSettings, json_schema};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use tower::{Layer, Service};
+use ts_rs_forge::TS;
+
+use crate::services::api_tokens::TokenScope;
+
+const SCHEMA_REF_PREFIX: &str = "#/components/schemas/";
+
+/// Produces a schema for one request/response type, registering any components it needs
+#[derive(Clone, Copy)]
+pub struct SchemaRef(fn(&mut SchemaGenerator) -> Schema);
+
+impl SchemaRef {
+    /// Forge types, which derive `JsonSchema`
+    pub fn json<T: JsonSchema>() -> Self {
+        Self(|generator| generator.subschema_for::<T>())
+    }
+
+    /// Upstream types, which only derive `TS`; the declaration is kept as `x-typescript`
+    pub fn ts<T: TS>() -> Self {
+        Self(ts_component::<T>)
+    }
+
+    pub fn ts_array<T: TS>() -> Self {
+        Self(|generator| {
+            let items = ts_component::<T>(generator);
+            json_schema!({ "type": "array", "items": items })
+        })
+    }
+
+    fn generate(&self, generator: &mut SchemaGenerator) -> Schema {
+        (self.0)(generator)
+    }
+}
+
+fn ts_component<T: TS>(generator: &mut SchemaGenerator) -> Schema {
+    let name = T::name();
+    generator
+        .definitions_mut()
+        .entry(name.clone())
+        .or_insert_with(|| {
+            json!({
+                "description": format!("`{name}` from shared/types.ts"),
+                "x-typescript": T::decl(),
+            })
+        });
+    json_schema!({ "$ref": format!("{SCHEMA_REF_PREFIX}{name}") })
+}
+
+/// Documentation for one operation, before it is bound to a method and path
+#[derive(Clone)]
+pub struct ApiDoc {
+    tag: &'static str,
+    summary: &'static str,
+    query: Option<SchemaRef>,
+    request: Option<SchemaRef>,
+    response: Option<SchemaRef>,
+    /// Upstream handlers wrap their payload in `ApiResponse { success, data, message }`
+    envelope: bool,
+    auth: RouteAuth,
+}
+
+pub fn doc(tag: &'static str, summary: &'static str) -> ApiDoc {
+    ApiDoc {
+        tag,
+        summary,
+        query: None,
+        request: None,
+        response: None,
+        envelope: false,
+        auth: RouteAuth::Session,
+    }
+}
+
+impl ApiDoc {
+    pub fn query<T: JsonSchema>(mut self) -> Self {
+        self.query = Some(SchemaRef::json::<T>());
+        self
+    }
+
+    pub fn request<T: JsonSchema>(mut self) -> Self {
+        self.request = Some(SchemaRef::json::<T>());
+        self
+    }
+
+    pub fn response<T: JsonSchema>(mut self) -> Self {
+        self.response = Some(SchemaRef::json::<T>());
+        self
+    }
+
+    pub fn request_schema(mut self, schema: SchemaRef) -> Self {
+        self.request = Some(schema);
+        self
+    }
+
+    pub fn response_schema(mut self, schema: SchemaRef) -> Self {
+        self.response = Some(schema);
+        self
+    }
+
+    pub fn enveloped(mut self) -> Self {
+        self.envelope = true;
+        self
+    }
+
+    /// Reachable without a session even when `AUTH_REQUIRED` is set
+    pub fn public(mut self) -> Self {
+        self.auth = RouteAuth::Public;
+        self
+    }
+}
+
+/// Whether a route needs a GitHub session when `AUTH_REQUIRED` is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteAuth {
+    Session,
+    Public,
+}
+
+/// axum's `DefaultBodyLimit` when no route-specific limit is layered on
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+#[derive(Clone)]
+pub struct ApiOperation {
+    pub method: Method,
+    pub path: String,
+    pub doc: ApiDoc,
+    /// Largest request body accepted, in bytes
+    pub body_limit: usize,
+}
+
+impl ApiOperation {
+    pub fn new(method: Method, path: &str, doc: ApiDoc) -> Self {
+        Self {
+            method,
+            path: path.to_string(),
+            doc,
+            body_limit: DEFAULT_BODY_LIMIT,
+        }
+    }
+
+    pub fn auth(&self) -> RouteAuth {
+        self.doc.auth
+    }
+
+    /// Least API token scope that may call this operation; `None` for public routes.
+    /// Reads need `read` except where they expose host configuration, the forge settings
+    /// (which carry the Omni API key) or the filesystem, task work needs `tasks:write`,
+    /// and everything else is `admin`.
+    pub fn scope(&self) -> Option<TokenScope> {
+        if self.doc.auth == RouteAuth::Public {
+            return None;
+        }
+        let scope = match self.doc.tag {
+            "Config" | "Forge" | "Filesystem" | "Tokens" => TokenScope::Admin,
+            _ if self.method == Method::GET || self.method == Method::HEAD => TokenScope::Read,
+            "Tasks" | "Task Attempts" | "Images" | "Processes" => TokenScope::TasksWrite,
+            _ => TokenScope::Admin,
+        };
+        Some(scope)
+    }
+
+    pub fn route_entry(&self) -> RouteEntry {
+        RouteEntry {
+            method: self.method.to_string(),
+            path: self.path.clone(),
+            tag: self.doc.tag,
+            summary: self.doc.summary,
+            auth: self.doc.auth,
+            scope: self.scope(),
+            body_limit: self.body_limit,
+        }
+    }
+
+    fn nested_under(mut self, prefix: &str) -> Self {
+        self.path = join_path(prefix, &self.path);
+        self
+    }
+}
+
+/// One mounted operation as listed by `/api/routes`
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RouteEntry {
+    pub method: String,
+    pub path: String,
+    pub tag: &'static str,
+    pub summary: &'static str,
+    pub auth: RouteAuth,
+    /// API token scope required when calling with `Authorization: Bearer`
+    pub scope: Option<TokenScope>,
+    pub body_limit: usize,
+}
+
+/// `"/api"` + `"/"` is `"/api"`, matching how axum mounts a nested root route
+fn join_path(prefix: &str, path: &str) -> String {
+    match path {
+        "" | "/" if !prefix.is_empty() => prefix.to_string(),
+        _ => format!("{prefix}{path}"),
+    }
+}
+
+/// `MethodRouter` plus the operations documented for it
+pub struct ApiMethodRouter<S> {
+    inner: MethodRouter<S>,
+    operations: Vec<ApiOperation>,
+}
+
+macro_rules! api_method {
+    ($name:ident, $method:ident) => {
+        pub fn $name<H, T, S>(handler: H, doc: ApiDoc) -> ApiMethodRouter<S>
+        where
+            H: Handler<T, S>,
+            T: 'static,
+            S: Clone + Send + Sync + 'static,
+        {
+            ApiMethodRouter {
+                inner: axum::routing::$name(handler),
+                operations: vec![ApiOperation::new(Method::$method, "", doc)],
+            }
+        }
+
+        impl<S: Clone + Send + Sync + 'static> ApiMethodRouter<S> {
+            pub fn $name<H, T>(mut self, handler: H, doc: ApiDoc) -> Self
+            where
+                H: Handler<T, S>,
+                T: 'static,
+            {
+                self.inner = self.inner.$name(handler);
+                self.operations
+                    .push(ApiOperation::new(Method::$method, "", doc));
+                self
+            }
+        }
+    };
+}
+
+api_method!(get, GET);
+api_method!(post, POST);
+api_method!(put, PUT);
+api_method!(delete, DELETE);
+
+impl<S: Clone + Send + Sync + 'static> ApiMethodRouter<S> {
+    pub fn layer<L>(self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + Sync + 'static,
+        L::Service: Service<Request> + Clone + Send + Sync + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        Self {
+            inner: self.inner.layer(layer),
+            operations: self.operations,
+        }
+    }
+
+    /// Apply changes that need live dependencies, such as state-bound middleware
+    pub fn map(self, f: impl FnOnce(MethodRouter<S>) -> MethodRouter<S>) -> Self {
+        Self {
+            inner: f(self.inner),
+            operations: self.operations,
+        }
+    }
+}
+
+/// `Router` that keeps the list of operations it serves
+pub struct ApiRouter<S = ()> {
+    router: Router<S>,
+    operations: Vec<ApiOperation>,
+}
+
+impl<S: Clone + Send + Sync + 'static> Default for ApiRouter<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Clone + Send + Sync + 'static> ApiRouter<S> {
+    pub fn new() -> Self {
+        Self {
+            router: Router::new(),
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn route(mut self, path: &str, route: ApiMethodRouter<S>) -> Self {
+        self.router = self.router.route(path, route.inner);
+        self.operations.extend(
+            route
+                .operations
+                .into_iter()
+                .map(|operation| operation.nested_under(path)),
+        );
+        self
+    }
+
+    pub fn nest(mut self, prefix: &str, other: ApiRouter<S>) -> Self {
+        self.router = self.router.nest(prefix, other.router);
+        self.operations.extend(
+            other
+                .operations
+                .into_iter()
+                .map(|operation| operation.nested_under(prefix)),
+        );
+        self
+    }
+
+    pub fn merge(mut self, other: ApiRouter<S>) -> Self {
+        self.router = self.router.merge(other.router);
+        self.operations.extend(other.operations);
+        self
+    }
+
+    /// Merge a router whose routes axum cannot enumerate, documented by `operations`.
+    /// `router` is `None` when only the operation list is being built.
+    pub fn merge_upstream(
+        mut self,
+        router: Option<Router<S>>,
+        operations: Vec<ApiOperation>,
+    ) -> Self {
+        if let Some(router) = router {
+            self.router = self.router.merge(router);
+        }
+        self.operations.extend(operations);
+        self
+    }
+
+    pub fn layer<L>(self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + Sync + 'static,
+        L::Service: Service<Request> + Clone + Send + Sync + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        Self {
+            router: self.router.layer(layer),
+            operations: self.operations,
+        }
+    }
+
+    /// Raise or lower the request body limit of every route added so far
+    pub fn body_limit(self, limit: usize) -> Self {
+        let mut limited = self.layer(DefaultBodyLimit::max(limit));
+        for operation in &mut limited.operations {
+            operation.body_limit = limit;
+        }
+        limited
+    }
+
+    /// Apply changes that need live dependencies, such as state-bound middleware
+    pub fn map(self, f: impl FnOnce(Router<S>) -> Router<S>) -> Self {
+        Self {
+            router: f(self.router),
+            operations: self.operations,
+        }
+    }
+
+    pub fn operations(&self) -> &[ApiOperation] {
+        &self.operations
+    }
+
+    pub fn into_parts(self) -> (Router<S>, Vec<ApiOperation>) {
+        (self.router, self.operations)
+    }
+}
+
+/// Build the OpenAPI 3.1 document for `operations`
+pub fn openapi_spec(operations: &[ApiOperation], version: &str) -> Value {
+    let mut generator = SchemaSettings::draft2020_12()
+        .with(|settings| {
+            settings.definitions_path = "/components/schemas".into();
+            settings.meta_schema = None;
+        })
+        .into_generator();
+
+    let mut paths: BTreeMap<&str, Map<String, Value>> = BTreeMap::new();
+    let mut tags: Vec<&str> = Vec::new();
+    for operation in operations {
+        if !tags.contains(&operation.doc.tag) {
+            tags.push(operation.doc.tag);
+        }
+        paths.entry(&operation.path).or_default().insert(
+            operation.method.as_str().to_ascii_lowercase(),
+            operation_object(operation, &mut generator),
+        );
+    }
+    tags.sort_unstable();
+
+    let mut schemas: Vec<(String, Value)> = generator.take_definitions(true).into_iter().collect();
+    schemas.sort_by(|a, b| a.0.cmp(&b.0));
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Automagik Forge API",
+            "version": version,
+            "description": "Automagik Forge API - AI-powered task orchestration and execution platform.\n\nGenerated from the routes mounted by `create_router`.",
+            "license": {
+                "name": "MIT",
+                "url": "https://github.com/namastexlabs/automagik-forge/blob/main/LICENSE"
+            }
+        },
+        "servers": [{ "url": "", "description": "Same origin (use current server)" }],
+        "tags": tags.into_iter().map(|name| json!({ "name": name })).collect::<Vec<_>>(),
+        "paths": paths,
+        "components": {
+            "schemas": schemas.into_iter().collect::<Map<_, _>>(),
+        }
+    })
+}
+
+fn operation_object(operation: &ApiOperation, generator: &mut SchemaGenerator) -> Value {
+    let doc = &operation.doc;
+    let mut object = Map::new();
+    object.insert("tags".into(), json!([doc.tag]));
+    object.insert("summary".into(), json!(doc.summary));
+    object.insert(
+        "operationId".into(),
+        json!(operation_id(&operation.method, &operation.path)),
+    );
+
+    let mut parameters: Vec<Value> = path_parameters(&operation.path)
+        .map(|name| {
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string" }
+            })
+        })
+        .collect();
+    if let Some(query) = &doc.query {
+        let schema = query.generate(generator);
+        parameters.extend(query_parameters(schema.as_value(), generator));
+    }
+    if !parameters.is_empty() {
+        object.insert("parameters".into(), Value::Array(parameters));
+    }
+
+    if let Some(request) = &doc.request {
+        object.insert(
+            "requestBody".into(),
+            json!({
+                "required": true,
+                "content": { "application/json": { "schema": request.generate(generator) } }
+            }),
+        );
+    }
+
+    let body = match &doc.response {
+        Some(response) => {
+            let data = response.generate(generator);
+            if doc.envelope {
+                envelope_schema(data.to_value())
+            } else {
+                data.to_value()
+            }
+        }
+        None if doc.envelope => envelope_schema(json!({})),
+        None => json!({}),
+    };
+    object.insert(
+        "responses".into(),
+        json!({
+            "200": {
+                "description": "Success",
+                "content": { "application/json": { "schema": body } }
+            }
+        }),
+    );
+
+    Value::Object(object)
+}
+
+fn envelope_schema(data: Value) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "success": { "type": "boolean" },
+            "data": data,
+            "error_data": {},
+            "message": { "type": ["string", "null"] }
+        },
+        "required": ["success"]
+    })
+}
+
+fn path_parameters(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+}
+
+/// Flatten a query struct's properties into `in: query` parameters
+fn query_parameters(schema: &Value, generator: &SchemaGenerator) -> Vec<Value> {
+    let resolved = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix(SCHEMA_REF_PREFIX))
+        .and_then(|name| generator.definitions().get(name))
+        .unwrap_or(schema);
+
+    let required: Vec<&str> = resolved
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    resolved
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, schema)| {
+                    json!({
+                        "name": name,
+                        "in": "query",
+                        "required": required.contains(&name.as_str()),
+                        "schema": schema
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `GET /api/tasks/{task_id}` -> `get_api_tasks_task_id`
+fn operation_id(method: &Method, path: &str) -> String {
+    let mut id = method.as_str().to_ascii_lowercase();
+    for segment in path
+        .split(['/', '-', '.'])
+        .filter(|segment| !segment.is_empty())
+    {
+        id.push('_');
+        id.push_str(segment.trim_matches(['{', '}']));
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::Query;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Filter {
+        status: Option<String>,
+        limit: u32,
+    }
+
+    async fn handler() {}
+
+    async fn filtered(Query(_): Query<Filter>) {}
+
+    #[test]
+    fn nested_routes_are_recorded_with_full_paths() {
+        let items = ApiRouter::<()>::new()
+            .route(
+                "/",
+                get(handler, doc("Items", "List items")).post(handler, doc("Items", "Create item")),
+            )
+            .route(
+                "/{item_id}/archive",
+                post(handler, doc("Items", "Archive item")),
+            );
+        let api = ApiRouter::new().nest("/api", ApiRouter::new().nest("/items", items));
+
+        let routes: Vec<_> = api
+            .operations()
+            .iter()
+            .map(|op| format!("{} {}", op.method, op.path))
+            .collect();
+        assert_eq!(
+            routes,
+            vec![
+                "GET /api/items",
+                "POST /api/items",
+                "POST /api/items/{item_id}/archive"
+            ]
+        );
+    }
+
+    #[test]
+    fn spec_describes_parameters_and_schemas() {
+        let api = ApiRouter::<()>::new().route(
+            "/api/items/{item_id}",
+            get(
+                filtered,
+                doc("Items", "Get item")
+                    .query::<Filter>()
+                    .response::<Vec<String>>(),
+            ),
+        );
+        let spec = openapi_spec(api.operations(), "1.2.3");
+        let operation = &spec["paths"]["/api/items/{item_id}"]["get"];
+
+        assert_eq!(spec["info"]["version"], "1.2.3");
+        assert_eq!(operation["operationId"], "get_api_items_item_id");
+        let parameters: Vec<_> = operation["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| {
+                (
+                    p["name"].as_str().unwrap(),
+                    p["in"].as_str().unwrap(),
+                    p["required"].as_bool().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            parameters,
+            vec![
+                ("item_id", "path", true),
+                ("status", "query", false),
+                ("limit", "query", true)
+            ]
+        );
+        assert_eq!(
+            operation["responses"]["200"]["content"]["application/json"]["schema"]["type"],
+            "array"
+        );
+    }
+
+    #[test]
+    fn registry_records_auth_and_body_limits() {
+        let uploads = ApiRouter::<()>::new()
+            .route("/upload", post(handler, doc("Items", "Upload")))
+            .body_limit(100 * 1024 * 1024);
+        let api = ApiRouter::new()
+            .route("/health", get(handler, doc("Core", "Health").public()))
+            .nest("/api", uploads)
+            .route("/api/items", get(handler, doc("Items", "List items")));
+
+        let entries: Vec<_> = api
+            .operations()
+            .iter()
+            .map(|op| {
+                let entry = op.route_entry();
+                (entry.path, entry.auth, entry.body_limit)
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("/health".to_string(), RouteAuth::Public, DEFAULT_BODY_LIMIT),
+                (
+                    "/api/upload".to_string(),
+                    RouteAuth::Session,
+                    100 * 1024 * 1024
+                ),
+                (
+                    "/api/items".to_string(),
+                    RouteAuth::Session,
+                    DEFAULT_BODY_LIMIT
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn token_scopes_follow_method_and_tag() {
+        let scope = |method: Method, tag: &'static str| {
+            ApiOperation::new(method, "/api/x", doc(tag, "X")).scope()
+        };
+
+        assert_eq!(scope(Method::GET, "Tasks"), Some(TokenScope::Read));
+        assert_eq!(scope(Method::POST, "Tasks"), Some(TokenScope::TasksWrite));
+        assert_eq!(
+            scope(Method::DELETE, "Task Attempts"),
+            Some(TokenScope::TasksWrite)
+        );
+        assert_eq!(scope(Method::GET, "Config"), Some(TokenScope::Admin));
+        assert_eq!(scope(Method::GET, "Forge"), Some(TokenScope::Admin));
+        assert_eq!(scope(Method::GET, "Filesystem"), Some(TokenScope::Admin));
+        assert_eq!(scope(Method::POST, "Projects"), Some(TokenScope::Admin));
+        assert_eq!(
+            ApiOperation::new(Method::GET, "/health", doc("Core", "Health").public()).scope(),
+            None
+        );
+    }
+}
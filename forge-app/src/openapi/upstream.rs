@@ -0,0 +1,495 @@
+//! Operations served by forge-core routers
+//!
+//! axum cannot enumerate the routes inside a `Router`, so each upstream router merged
+//! by `create_router` is paired with one of these lists. Paths are relative to the
+//! `/api` nest. Keep them in step when bumping forge-core.
+
+use axum::http::Method;
+use forge_core_db::models::{
+    execution_process::ExecutionProcess,
+    project::{CreateProject, Project, UpdateProject},
+    tag::{CreateTag, Tag, UpdateTag},
+};
+
+use super::{ApiOperation, SchemaRef, doc};
+
+fn op(method: Method, path: &str, tag: &'static str, summary: &'static str) -> ApiOperation {
+    ApiOperation::new(method, path, doc(tag, summary).enveloped())
+}
+
+/// Public: the login flow has to work before a session exists
+pub fn auth() -> Vec<ApiOperation> {
+    let public = |method, path, summary| {
+        ApiOperation::new(method, path, doc("Auth", summary).enveloped().public())
+    };
+    vec![
+        public(Method::GET, "/auth/github/check", "Check the GitHub token"),
+        public(
+            Method::POST,
+            "/auth/github/device/start",
+            "Start the GitHub device flow",
+        ),
+        public(
+            Method::POST,
+            "/auth/github/device/poll",
+            "Poll the GitHub device flow for a token",
+        ),
+    ]
+}
+
+pub fn config() -> Vec<ApiOperation> {
+    vec![
+        op(
+            Method::GET,
+            "/info",
+            "Config",
+            "Get system info and configuration",
+        ),
+        op(
+            Method::PUT,
+            "/config",
+            "Config",
+            "Update application configuration",
+        ),
+        op(
+            Method::GET,
+            "/sounds/{sound}",
+            "Config",
+            "Get a notification sound",
+        ),
+        op(
+            Method::GET,
+            "/mcp-config",
+            "Config",
+            "Get MCP server configuration",
+        ),
+        op(
+            Method::POST,
+            "/mcp-config",
+            "Config",
+            "Update MCP server configuration",
+        ),
+        op(Method::GET, "/profiles", "Config", "Get executor profiles"),
+        op(
+            Method::PUT,
+            "/profiles",
+            "Config",
+            "Update executor profiles",
+        ),
+    ]
+}
+
+pub fn containers() -> Vec<ApiOperation> {
+    vec![op(
+        Method::GET,
+        "/containers/info",
+        "Containers",
+        "Resolve a container reference",
+    )]
+}
+
+pub fn projects() -> Vec<ApiOperation> {
+    let project = || SchemaRef::ts::<Project>();
+    vec![
+        ApiOperation::new(
+            Method::GET,
+            "/projects",
+            doc("Projects", "List projects")
+                .response_schema(SchemaRef::ts_array::<Project>())
+                .enveloped(),
+        ),
+        ApiOperation::new(
+            Method::POST,
+            "/projects",
+            doc("Projects", "Create a project")
+                .request_schema(SchemaRef::ts::<CreateProject>())
+                .response_schema(project())
+                .enveloped(),
+        ),
+        ApiOperation::new(
+            Method::GET,
+            "/projects/{id}",
+            doc("Projects", "Get a project")
+                .response_schema(project())
+                .enveloped(),
+        ),
+        ApiOperation::new(
+            Method::PUT,
+            "/projects/{id}",
+            doc("Projects", "Update a project")
+                .request_schema(SchemaRef::ts::<UpdateProject>())
+                .response_schema(project())
+                .enveloped(),
+        ),
+        op(
+            Method::DELETE,
+            "/projects/{id}",
+            "Projects",
+            "Delete a project",
+        ),
+    ]
+}
+
+pub fn drafts() -> Vec<ApiOperation> {
+    vec![op(
+        Method::GET,
+        "/drafts/stream/ws",
+        "Drafts",
+        "Stream project drafts (WebSocket)",
+    )]
+}
+
+pub fn execution_processes() -> Vec<ApiOperation> {
+    vec![
+        op(
+            Method::GET,
+            "/execution-processes/stream/ws",
+            "Processes",
+            "Stream execution processes (WebSocket)",
+        ),
+        ApiOperation::new(
+            Method::GET,
+            "/execution-processes",
+            doc("Processes", "List execution processes")
+                .response_schema(SchemaRef::ts_array::<ExecutionProcess>())
+                .enveloped(),
+        ),
+        ApiOperation::new(
+            Method::GET,
+            "/execution-processes/{id}",
+            doc("Processes", "Get an execution process")
+                .response_schema(SchemaRef::ts::<ExecutionProcess>())
+                .enveloped(),
+        ),
+        op(
+            Method::GET,
+            "/execution-processes/{id}/raw-logs/ws",
+            "Processes",
+            "Stream raw logs (WebSocket)",
+        ),
+        op(
+            Method::GET,
+            "/execution-processes/{id}/normalized-logs/ws",
+            "Processes",
+            "Stream normalized logs (WebSocket)",
+        ),
+        op(
+            Method::POST,
+            "/execution-processes/{id}/stop",
+            "Processes",
+            "Stop an execution process",
+        ),
+    ]
+}
+
+pub fn tags() -> Vec<ApiOperation> {
+    vec![
+        ApiOperation::new(
+            Method::GET,
+            "/tags",
+            doc("Tags", "List tags")
+                .response_schema(SchemaRef::ts_array::<Tag>())
+                .enveloped(),
+        ),
+        ApiOperation::new(
+            Method::POST,
+            "/tags",
+            doc("Tags", "Create a tag")
+                .request_schema(SchemaRef::ts::<CreateTag>())
+                .response_schema(SchemaRef::ts::<Tag>())
+                .enveloped(),
+        ),
+        ApiOperation::new(
+            Method::GET,
+            "/tags/{tag_id}",
+            doc("Tags", "Get a tag")
+                .response_schema(SchemaRef::ts::<Tag>())
+                .enveloped(),
+        ),
+        ApiOperation::new(
+            Method::PUT,
+            "/tags/{tag_id}",
+            doc("Tags", "Update a tag")
+                .request_schema(SchemaRef::ts::<UpdateTag>())
+                .response_schema(SchemaRef::ts::<Tag>())
+                .enveloped(),
+        ),
+        op(Method::DELETE, "/tags/{tag_id}", "Tags", "Delete a tag"),
+    ]
+}
+
+pub fn filesystem() -> Vec<ApiOperation> {
+    vec![
+        op(
+            Method::GET,
+            "/filesystem/directory",
+            "Filesystem",
+            "List a directory",
+        ),
+        op(
+            Method::GET,
+            "/filesystem/git-repos",
+            "Filesystem",
+            "Find git repositories",
+        ),
+    ]
+}
+
+pub fn events() -> Vec<ApiOperation> {
+    vec![op(Method::GET, "/events", "Events", "Stream events (SSE)")]
+}
+
+pub fn approvals() -> Vec<ApiOperation> {
+    vec![
+        op(
+            Method::POST,
+            "/approvals/create",
+            "Approvals",
+            "Request approval for a tool call",
+        ),
+        op(
+            Method::GET,
+            "/approvals/{id}/status",
+            "Approvals",
+            "Get approval status",
+        ),
+        op(
+            Method::POST,
+            "/approvals/{id}/respond",
+            "Approvals",
+            "Respond to an approval request",
+        ),
+        op(
+            Method::GET,
+            "/approvals/pending",
+            "Approvals",
+            "List pending approvals",
+        ),
+    ]
+}
+
+pub fn forge() -> Vec<ApiOperation> {
+    vec![
+        op(Method::GET, "/forge/config", "Forge", "Get forge settings"),
+        op(
+            Method::PUT,
+            "/forge/config",
+            "Forge",
+            "Update forge settings",
+        ),
+        op(
+            Method::GET,
+            "/forge/projects/{id}/settings",
+            "Forge",
+            "Get project forge settings",
+        ),
+        op(
+            Method::PUT,
+            "/forge/projects/{id}/settings",
+            "Forge",
+            "Update project forge settings",
+        ),
+        op(
+            Method::GET,
+            "/forge/projects/{id}/branch-status",
+            "Forge",
+            "Get project branch status",
+        ),
+        op(
+            Method::POST,
+            "/forge/projects/{id}/pull",
+            "Forge",
+            "Pull the project's base branch",
+        ),
+        op(
+            Method::GET,
+            "/forge/projects/{id}/profiles",
+            "Forge",
+            "Get project executor profiles",
+        ),
+        op(Method::GET, "/forge/omni/status", "Omni", "Get Omni status"),
+        op(
+            Method::GET,
+            "/forge/omni/instances",
+            "Omni",
+            "List Omni instances",
+        ),
+        op(
+            Method::POST,
+            "/forge/omni/validate",
+            "Omni",
+            "Validate an Omni configuration",
+        ),
+        op(
+            Method::GET,
+            "/forge/omni/notifications",
+            "Omni",
+            "List recent Omni notifications",
+        ),
+        op(Method::GET, "/forge/releases", "Forge", "List releases"),
+    ]
+}
+
+/// Relative to the `/images` nest
+pub fn images() -> Vec<ApiOperation> {
+    vec![
+        op(Method::POST, "/upload", "Images", "Upload an image"),
+        op(
+            Method::POST,
+            "/task/{task_id}/upload",
+            "Images",
+            "Upload an image for a task",
+        ),
+        op(
+            Method::GET,
+            "/task/{task_id}",
+            "Images",
+            "List a task's images",
+        ),
+        op(Method::GET, "/{id}/file", "Images", "Get an image file"),
+        op(Method::DELETE, "/{id}", "Images", "Delete an image"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{StatusCode, header},
+        middleware::{Next, from_fn},
+    };
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        middleware::{cors::CorsPolicy, rate_limit::LimitSettings},
+        router,
+        services::{
+            ForgeServices,
+            api_tokens::{self, CreateApiToken, TokenScope},
+            test_support,
+        },
+    };
+
+    /// Every operation listed here must reach a route of the built router. A route layer
+    /// answers matched requests itself, so no upstream handler runs; unmatched paths
+    /// fall through to the frontend and wrong methods get 405.
+    #[tokio::test]
+    async fn documented_operations_are_routed() {
+        // Points DATABASE_URL at an in-memory database before the deployment opens it
+        test_support::pool().await;
+        let services = ForgeServices::new()
+            .await
+            .expect("forge services should start");
+        let app = router::create_router(
+            services,
+            false,
+            Vec::new(),
+            false,
+            CorsPolicy::new(Vec::<String>::new()),
+            None,
+            LimitSettings::default(),
+        )
+        .route_layer(from_fn(|_: Request, _: Next| async {
+            StatusCode::NO_CONTENT
+        }));
+
+        let operations = [
+            auth(),
+            config(),
+            containers(),
+            projects(),
+            drafts(),
+            execution_processes(),
+            tags(),
+            filesystem(),
+            events(),
+            approvals(),
+            forge(),
+            images(),
+        ];
+        for operation in operations.into_iter().flatten() {
+            let path: Vec<String> = operation
+                .path
+                .split('/')
+                .map(|segment| {
+                    if segment.starts_with('{') {
+                        Uuid::new_v4().to_string()
+                    } else {
+                        segment.to_string()
+                    }
+                })
+                .collect();
+            let uri = format!("/api{}", path.join("/"));
+
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method(operation.method.clone())
+                        .uri(&uri)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(
+                response.status(),
+                StatusCode::NO_CONTENT,
+                "{} {} ({uri}) is documented but not routed",
+                operation.method,
+                operation.path
+            );
+        }
+    }
+
+    /// The forge settings include the Omni API key, so read-only tokens must not see them
+    #[tokio::test]
+    async fn read_tokens_cannot_read_forge_settings() {
+        // Points DATABASE_URL at an in-memory database before the deployment opens it
+        test_support::pool().await;
+        let services = ForgeServices::new()
+            .await
+            .expect("forge services should start");
+        let pool = services.pool.clone();
+        let app = router::create_router(
+            services,
+            true,
+            Vec::new(),
+            false,
+            CorsPolicy::new(Vec::<String>::new()),
+            None,
+            LimitSettings::default(),
+        );
+
+        let reader = api_tokens::create_api_token(
+            &pool,
+            &CreateApiToken {
+                name: "ci".into(),
+                scopes: vec![TokenScope::Read],
+                expires_in_days: None,
+            },
+        )
+        .await
+        .unwrap()
+        .token;
+        for uri in [
+            "/api/forge/config".to_string(),
+            format!("/api/forge/projects/{}/settings", Uuid::new_v4()),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::get(&uri)
+                        .header(header::AUTHORIZATION, format!("Bearer {reader}"))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{uri}");
+        }
+    }
+}
@@ -9,26 +9,49 @@
 //! - Executor:variant storage for filtering
 //! - Branch prefix "forge/" (configurable)
 
+use std::sync::Arc;
+
 use axum::{
     Json, Router,
     extract::{FromRef, Path, State},
-    http::{HeaderValue, Method, StatusCode, header},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{Html, IntoResponse, Response},
-    routing::{get, post},
 };
 use forge_core_db::models::task::TaskWithAttemptStatus;
 use forge_core_server::{
     DeploymentImpl,
     routes::{
-        self as upstream, approvals, auth, config as upstream_config, containers, drafts, events,
-        execution_processes, filesystem, forge, images, projects, tags, task_attempts, tasks,
+        self as upstream, approvals, auth as upstream_auth, config as upstream_config, containers,
+        drafts, events, execution_processes, filesystem, forge, images, projects, tags,
     },
 };
 use rust_embed::RustEmbed;
+use schemars::JsonSchema;
 use serde_json::{Value, json};
-use tower_http::cors::{Any, CorsLayer};
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
 
-use crate::services::ForgeServices;
+use crate::{
+    logging,
+    metrics::{self, Metrics},
+    middleware::{
+        auth::{self, AuthGuard, LoginGuard},
+        cors::{self, CorsPolicy},
+        lifecycle_events,
+        rate_limit::{AgentLimiter, LimitSettings},
+    },
+    openapi::{self, ApiOperation, ApiRouter, RouteEntry, doc, upstream as api_docs},
+    services::{ForgeServices, OmniWorkerStatus},
+};
+
+mod health;
+mod notifications;
+mod prometheus;
+mod task_overrides;
+mod tokens;
 
 #[derive(RustEmbed)]
 #[folder = "../frontend/dist"]
@@ -42,14 +65,30 @@ struct ForgeAppState {
     services: ForgeServices,
     deployment: DeploymentImpl,
     auth_required: bool,
+    /// Session cookies carry `Secure`; the server is served over TLS
+    secure_cookies: bool,
+    /// Route registry filled by `create_router`; backs `/api/routes` and the OpenAPI spec
+    operations: Arc<[ApiOperation]>,
+    /// Prometheus recorder when metrics are enabled
+    metrics: Option<Metrics>,
 }
 
 impl ForgeAppState {
-    fn new(services: ForgeServices, deployment: DeploymentImpl, auth_required: bool) -> Self {
+    fn new(
+        services: ForgeServices,
+        deployment: DeploymentImpl,
+        auth_required: bool,
+        secure_cookies: bool,
+        operations: Vec<ApiOperation>,
+        metrics: Option<Metrics>,
+    ) -> Self {
         Self {
             services,
             deployment,
             auth_required,
+            secure_cookies,
+            operations: operations.into(),
+            metrics,
         }
     }
 }
@@ -66,199 +105,262 @@ impl FromRef<ForgeAppState> for DeploymentImpl {
     }
 }
 
-pub fn create_router(services: ForgeServices, auth_required: bool) -> Router {
+/// Live dependencies for route middleware; `None` when only the route table is built
+#[derive(Clone, Copy)]
+struct RouteDeps<'a> {
+    deployment: &'a DeploymentImpl,
+    services: &'a ForgeServices,
+    limiter: &'a AgentLimiter,
+    login: &'a LoginGuard,
+}
+
+pub fn create_router(
+    services: ForgeServices,
+    auth_required: bool,
+    allowed_logins: Vec<String>,
+    secure_cookies: bool,
+    cors_policy: CorsPolicy,
+    metrics: Option<Metrics>,
+    limits: LimitSettings,
+) -> Router {
     let deployment = services.deployment.as_ref().clone();
-    let state = ForgeAppState::new(services, deployment.clone(), auth_required);
-
-    let upstream_api = upstream_api_router(&deployment);
-
-    // Configure CORS for Swagger UI and external API access
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::OPTIONS,
-        ])
-        .allow_headers(Any);
-
-    Router::new()
-        .route("/health", get(health_check))
-        .route("/docs", get(serve_swagger_ui))
-        .route("/api/openapi.json", get(serve_openapi_spec))
-        .route("/api/routes", get(list_routes))
-        // Public PWA manifest - must be accessible without authentication
-        .route("/site.webmanifest", get(serve_assets_public))
-        .merge(forge_api_routes())
-        // Upstream API at /api
-        .nest("/api", upstream_api)
+    let limiter = AgentLimiter::new(services.pool.clone(), limits);
+    let login = LoginGuard::new(
+        services.pool.clone(),
+        auth_required,
+        services.deployment.clone(),
+        allowed_logins,
+    )
+    .with_secure_cookies(secure_cookies);
+    let (routes, operations) = api_routes(Some(RouteDeps {
+        deployment: &deployment,
+        services: &services,
+        limiter: &limiter,
+        login: &login,
+    }))
+    .into_parts();
+    // Enforce AUTH_REQUIRED on every mounted route except the public ones
+    let auth_guard = AuthGuard::new(
+        services.pool.clone(),
+        auth_required,
+        operations.iter().map(|operation| {
+            (
+                operation.method.clone(),
+                operation.path.clone(),
+                operation.scope(),
+            )
+        }),
+    );
+    let track_http = metrics.is_some();
+    let state = ForgeAppState::new(
+        services,
+        deployment.clone(),
+        auth_required,
+        secure_cookies,
+        operations,
+        metrics,
+    );
+
+    // Browsers may only call the API from same-origin, localhost or configured origins
+    let cors_layer = cors_policy.layer();
+
+    let router = routes
+        .layer(axum::middleware::from_fn_with_state(
+            auth_guard,
+            auth::require_session,
+        ))
         // Single frontend with overlay architecture
         .fallback(frontend_handler)
-        .layer(cors)
+        .layer(axum::middleware::from_fn_with_state(
+            cors_policy,
+            cors::reject_disallowed_origins,
+        ))
+        .layer(cors_layer);
+
+    // Outside the app's own layers, so rejected requests are counted too
+    let router = if track_http {
+        router.layer(axum::middleware::from_fn(metrics::track_http))
+    } else {
+        router
+    };
+
+    // Outermost: every request gets an `x-request-id` and a span carrying it
+    router
+        .layer(
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(TraceLayer::new_for_http().make_span_with(logging::request_span))
+                .layer(PropagateRequestIdLayer::x_request_id()),
+        )
         .with_state(state)
 }
 
-/// Forge-app specific routes that extend forge-core's routes
-/// - auth-required: Check if authentication is required (forge-app only)
-fn forge_api_routes() -> Router<ForgeAppState> {
-    Router::new().route("/api/forge/auth-required", get(get_auth_required))
+/// Every operation `create_router` mounts, used for the OpenAPI spec
+pub fn api_operations() -> Vec<ApiOperation> {
+    api_routes(None).into_parts().1
 }
 
-fn upstream_api_router(deployment: &DeploymentImpl) -> Router<ForgeAppState> {
-    let mut router = Router::new().route("/health", get(upstream::health::health_check));
-
-    let dep_clone = deployment.clone();
-
-    // Forge override: config router with increased body limit for /profiles
-    router = router.merge(forge_config_router().with_state::<ForgeAppState>(dep_clone.clone()));
-    router =
-        router.merge(containers::router(deployment).with_state::<ForgeAppState>(dep_clone.clone()));
-    router =
-        router.merge(projects::router(deployment).with_state::<ForgeAppState>(dep_clone.clone()));
-    router =
-        router.merge(drafts::router(deployment).with_state::<ForgeAppState>(dep_clone.clone()));
-
-    // Build custom tasks router with forge override (already typed as ForgeAppState)
-    let tasks_router_with_override = build_tasks_router_with_forge_override(deployment);
-    router = router.merge(tasks_router_with_override);
-
-    // Build custom task_attempts router with forge override (already typed as ForgeAppState)
-    let task_attempts_router_with_override =
-        build_task_attempts_router_with_forge_override(deployment);
-    router = router.merge(task_attempts_router_with_override);
-    router = router.merge(
-        execution_processes::router(deployment).with_state::<ForgeAppState>(dep_clone.clone()),
-    );
-    router = router.merge(auth::router(deployment).with_state::<ForgeAppState>(dep_clone.clone()));
-    router = router.merge(tags::router(deployment).with_state::<ForgeAppState>(dep_clone.clone()));
-    router = router.merge(filesystem::router().with_state::<ForgeAppState>(dep_clone.clone()));
-    router =
-        router.merge(events::router(deployment).with_state::<ForgeAppState>(dep_clone.clone()));
-    router = router.merge(approvals::router().with_state::<ForgeAppState>(dep_clone.clone()));
-
-    // Forge-core routes: /forge/* (config, settings, omni, releases, agents)
-    router = router.merge(forge::router(deployment).with_state::<ForgeAppState>(dep_clone.clone()));
-
-    router.nest(
-        "/images",
-        forge_images_router().with_state::<ForgeAppState>(dep_clone),
-    )
-}
-
-/// Build tasks router - uses forge-core's handlers that exclude agent tasks
-/// via the forge_agents table (kanban vs agent task separation)
-fn build_tasks_router_with_forge_override(deployment: &DeploymentImpl) -> Router<ForgeAppState> {
-    use axum::middleware::from_fn_with_state;
-    use forge_core_server::middleware::load_task_middleware;
-
-    let task_id_router = Router::new()
+fn api_routes(deps: Option<RouteDeps<'_>>) -> ApiRouter<ForgeAppState> {
+    ApiRouter::new()
         .route(
-            "/",
-            get(tasks::get_task)
-                .put(tasks::update_task)
-                .delete(tasks::delete_task),
+            "/health",
+            openapi::get(health_check, doc("Core", "Health check").public()),
         )
-        .layer(from_fn_with_state(deployment.clone(), load_task_middleware));
-
-    let inner = Router::new()
-        // Use forge-core handlers - agent tasks filtered via forge_agents table
-        .route("/", get(tasks::get_tasks).post(tasks::create_task))
-        .route("/stream/ws", get(tasks::stream_tasks_ws))
-        // forge-core now handles everything: profile injection + agent tracking + executor:variant
-        .route("/create-and-start", post(tasks::create_task_and_start))
-        .nest("/{task_id}", task_id_router);
-
-    Router::new().nest("/tasks", inner)
-}
-
-/// Build task_attempts router with forge override for create endpoint
-fn build_task_attempts_router_with_forge_override(
-    deployment: &DeploymentImpl,
-) -> Router<ForgeAppState> {
-    use axum::middleware::from_fn_with_state;
-    use forge_core_server::middleware::load_task_attempt_middleware;
-
-    let task_attempt_id_router = Router::new()
-        .route("/", get(task_attempts::get_task_attempt))
-        // forge-core's follow_up now handles profile injection automatically
-        .route("/follow-up", post(task_attempts::follow_up))
+        .merge(health::routes())
+        .merge(prometheus::routes())
         .route(
-            "/draft",
-            get(task_attempts::drafts::get_draft)
-                .put(task_attempts::drafts::save_draft)
-                .delete(task_attempts::drafts::delete_draft),
+            "/docs",
+            openapi::get(serve_swagger_ui, doc("Core", "Swagger UI")),
         )
-        .route("/draft/queue", post(task_attempts::drafts::set_draft_queue))
-        .route("/replace-process", post(task_attempts::replace_process))
-        .route("/commit-info", get(task_attempts::get_commit_info))
         .route(
-            "/commit-compare",
-            get(task_attempts::compare_commit_to_head),
+            "/api/openapi.json",
+            openapi::get(serve_openapi_spec, doc("Core", "OpenAPI specification")),
         )
-        .route("/start-dev-server", post(task_attempts::start_dev_server))
-        // Use forge-core's branch-status - already has remote_commits_behind/ahead
         .route(
-            "/branch-status",
-            get(task_attempts::get_task_attempt_branch_status),
+            "/api/routes",
+            openapi::get(
+                list_routes,
+                doc("Core", "List available routes").response::<RouteList>(),
+            ),
         )
-        .route("/diff/ws", get(task_attempts::stream_task_attempt_diff_ws))
-        .route("/merge", post(task_attempts::merge_task_attempt))
-        .route("/push", post(task_attempts::push_task_attempt_branch))
-        .route("/rebase", post(task_attempts::rebase_task_attempt))
+        // Public PWA manifest - must be accessible without authentication
         .route(
-            "/conflicts/abort",
-            post(task_attempts::abort_conflicts_task_attempt),
+            "/site.webmanifest",
+            openapi::get(serve_assets_public, doc("Core", "PWA manifest").public()),
         )
-        .route("/pr", post(task_attempts::create_github_pr))
-        .route("/pr/attach", post(task_attempts::attach_existing_pr))
+        .merge(forge_api_routes())
+        // Upstream API at /api
+        .nest("/api", upstream_api_router(deps))
+}
+
+/// Forge-app specific routes that extend forge-core's routes
+/// - auth-required: Check if authentication is required (forge-app only)
+/// - auth/logout: Revoke the browser session issued after GitHub login
+/// - omni/worker: Omni worker circuit breaker status and reset
+/// - notifications, notification-sinks, notification-templates: see [`notifications`]
+fn forge_api_routes() -> ApiRouter<ForgeAppState> {
+    ApiRouter::new()
         .route(
-            "/open-editor",
-            post(task_attempts::open_task_attempt_in_editor),
+            "/api/forge/auth-required",
+            openapi::get(
+                get_auth_required,
+                // The frontend asks before it knows whether to show the login flow
+                doc("Forge", "Check whether authentication is required").public(),
+            ),
         )
         .route(
-            "/delete-file",
-            post(task_attempts::delete_task_attempt_file),
+            "/api/forge/auth/logout",
+            openapi::post(logout, doc("Forge", "End the current session").public()),
         )
-        .route("/children", get(task_attempts::get_task_attempt_children))
-        .route("/stop", post(task_attempts::stop_task_attempt_execution))
         .route(
-            "/change-target-branch",
-            post(task_attempts::change_target_branch),
+            "/api/forge/omni/worker",
+            openapi::get(
+                get_omni_worker_status,
+                doc("Omni", "Get Omni worker status").response::<OmniWorkerStatus>(),
+            ),
         )
-        .layer(from_fn_with_state(
-            deployment.clone(),
-            load_task_attempt_middleware,
-        ));
-
-    let task_attempts_router = Router::new()
         .route(
-            "/",
-            // forge-core now handles everything: profile injection + executor:variant
-            get(task_attempts::get_task_attempts).post(task_attempts::create_task_attempt),
+            "/api/forge/omni/worker/reset",
+            openapi::post(
+                reset_omni_worker,
+                doc("Omni", "Reset the Omni worker circuit breaker").response::<OmniWorkerStatus>(),
+            ),
         )
-        .nest("/{id}", task_attempt_id_router);
+        .merge(notifications::routes())
+        .merge(tokens::routes())
+}
 
-    Router::new().nest("/task-attempts", task_attempts_router)
+/// Bind an upstream router to the deployment; `None` when only the route table is built
+fn mount_upstream(
+    deps: Option<RouteDeps<'_>>,
+    build: impl FnOnce(&DeploymentImpl) -> Router<DeploymentImpl>,
+) -> Option<Router<ForgeAppState>> {
+    deps.map(|deps| build(deps.deployment).with_state(deps.deployment.clone()))
 }
 
-/// Build config router with forge override for increased body limit on /profiles
-fn forge_config_router() -> Router<DeploymentImpl> {
-    use axum::extract::DefaultBodyLimit;
+fn upstream_api_router(deps: Option<RouteDeps<'_>>) -> ApiRouter<ForgeAppState> {
+    use axum::middleware::from_fn_with_state;
+
+    ApiRouter::new()
+        .route(
+            "/health",
+            openapi::get(
+                upstream::health::health_check,
+                doc("Core", "API health check").public(),
+            ),
+        )
+        // Forge override: config router with increased body limit for /profiles
+        .merge(forge_config_router(deps))
+        .merge_upstream(
+            mount_upstream(deps, containers::router),
+            api_docs::containers(),
+        )
+        .merge_upstream(mount_upstream(deps, projects::router), api_docs::projects())
+        .merge_upstream(mount_upstream(deps, drafts::router), api_docs::drafts())
+        // Custom tasks and task_attempts routers with forge overrides
+        .merge(task_overrides::build_tasks_router_with_forge_override(deps))
+        .merge(task_overrides::build_task_attempts_router_with_forge_override(deps))
+        .merge_upstream(
+            mount_upstream(deps, execution_processes::router),
+            api_docs::execution_processes(),
+        )
+        // Forge override: issue a session cookie when the GitHub device flow succeeds
+        .merge_upstream(
+            deps.map(|deps| {
+                upstream_auth::router(deps.deployment)
+                    .layer(from_fn_with_state(
+                        deps.login.clone(),
+                        auth::start_session_on_login,
+                    ))
+                    .with_state(deps.deployment.clone())
+            }),
+            api_docs::auth(),
+        )
+        .merge_upstream(mount_upstream(deps, tags::router), api_docs::tags())
+        .merge_upstream(
+            mount_upstream(deps, |_| filesystem::router()),
+            api_docs::filesystem(),
+        )
+        .merge_upstream(mount_upstream(deps, events::router), api_docs::events())
+        // Forge override: queue approval_pending notifications for new approval requests
+        .merge_upstream(
+            deps.map(|deps| {
+                approvals::router()
+                    .layer(from_fn_with_state(
+                        lifecycle_events::EventQueue::new(deps.services),
+                        lifecycle_events::notify_on_approval_request,
+                    ))
+                    .with_state(deps.deployment.clone())
+            }),
+            api_docs::approvals(),
+        )
+        // Forge-core routes: /forge/* (config, settings, omni, releases, agents)
+        .merge_upstream(mount_upstream(deps, forge::router), api_docs::forge())
+        .nest("/images", forge_images_router(deps))
+}
 
+/// Build config router with forge override for increased body limit on /profiles
+fn forge_config_router(deps: Option<RouteDeps<'_>>) -> ApiRouter<ForgeAppState> {
     // Use upstream router and layer on increased body limit globally for config routes
     // This affects all config routes, but /profiles is the only one with large payloads
-    upstream_config::router().layer(DefaultBodyLimit::max(20 * 1024 * 1024)) // 20MB limit for large profile payloads
+    ApiRouter::new()
+        .merge_upstream(
+            mount_upstream(deps, |_| upstream_config::router()),
+            api_docs::config(),
+        )
+        .body_limit(20 * 1024 * 1024) // 20MB limit for large profile payloads
 }
 
 /// Build images router with forge override for increased body limit on uploads
-fn forge_images_router() -> Router<DeploymentImpl> {
-    use axum::extract::DefaultBodyLimit;
-
+fn forge_images_router(deps: Option<RouteDeps<'_>>) -> ApiRouter<ForgeAppState> {
     // Use upstream router and layer on increased body limit globally for image routes
     // Upstream has 20MB limits on upload routes, we increase to 100MB for large images
-    images::routes().layer(DefaultBodyLimit::max(100 * 1024 * 1024)) // 100MB limit for image uploads
+    ApiRouter::new()
+        .merge_upstream(
+            mount_upstream(deps, |_| images::routes()),
+            api_docs::images(),
+        )
+        .body_limit(100 * 1024 * 1024) // 100MB limit for image uploads
 }
 
 async fn frontend_handler(uri: axum::http::Uri) -> Response {
@@ -311,28 +413,31 @@ async fn serve_static_file<T: RustEmbed>(path: &str) -> Response {
     }
 }
 
-async fn health_check() -> Json<Value> {
+async fn health_check(State(state): State<ForgeAppState>) -> Json<Value> {
+    // `/health` is public: only the breaker state and counters, since `last_error` can
+    // quote sink URLs; the full status is on the authenticated `/api/forge/omni/worker`
+    let omni_worker = state.services.omni_worker.status().await;
     Json(json!({
         "status": "ok",
         "service": "forge-app",
         "version": crate::version::get_version(),
-        "message": "Forge application ready"
+        "message": "Forge application ready",
+        "omni_worker": {
+            "running": omni_worker.running,
+            "circuit": omni_worker.circuit,
+            "consecutive_failures": omni_worker.consecutive_failures,
+            "open_until": omni_worker.open_until,
+            "restarts": omni_worker.restarts,
+        }
     }))
 }
 
-/// Serve OpenAPI specification as JSON
-async fn serve_openapi_spec() -> Result<Json<Value>, (StatusCode, String)> {
-    const OPENAPI_YAML: &str = include_str!("../openapi.yaml");
-
-    serde_yaml::from_str::<Value>(OPENAPI_YAML)
-        .map(Json)
-        .map_err(|e| {
-            tracing::error!("Failed to parse openapi.yaml: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to parse OpenAPI spec: {e}"),
-            )
-        })
+/// Serve the OpenAPI specification generated from the mounted routes
+async fn serve_openapi_spec(State(state): State<ForgeAppState>) -> Json<Value> {
+    Json(openapi::openapi_spec(
+        &state.operations,
+        crate::version::get_version(),
+    ))
 }
 
 /// Serve Swagger UI HTML
@@ -374,106 +479,28 @@ async fn serve_swagger_ui() -> Html<String> {
     )
 }
 
-/// Simple route listing - practical solution instead of broken OpenAPI
-async fn list_routes() -> Json<Value> {
-    Json(json!({
-        "version": crate::version::get_version(),
-        "routes": {
-            "core": [
-                "GET /health",
-                "GET /api/health",
-                "GET /api/routes (this endpoint)"
-            ],
-            "auth": [
-                "POST /api/auth/github/device",
-                "POST /api/auth/github/device/poll",
-                "POST /api/auth/logout"
-            ],
-            "projects": [
-                "GET /api/projects",
-                "POST /api/projects",
-                "GET /api/projects/{id}",
-                "PUT /api/projects/{id}",
-                "DELETE /api/projects/{id}"
-            ],
-            "tasks": [
-                "GET /api/tasks",
-                "POST /api/tasks",
-                "POST /api/tasks/create-and-start",
-                "GET /api/tasks/{id}",
-                "PUT /api/tasks/{id}",
-                "DELETE /api/tasks/{id}",
-                "GET /api/tasks/stream/ws"
-            ],
-            "task_attempts": [
-                "GET /api/task-attempts",
-                "POST /api/task-attempts",
-                "GET /api/task-attempts/{id}",
-                "POST /api/task-attempts/{id}/follow-up",
-                "POST /api/task-attempts/{id}/stop",
-                "POST /api/task-attempts/{id}/merge",
-                "POST /api/task-attempts/{id}/push",
-                "POST /api/task-attempts/{id}/rebase",
-                "POST /api/task-attempts/{id}/pr",
-                "POST /api/task-attempts/{id}/pr/attach",
-                "GET /api/task-attempts/{id}/branch-status",
-                "GET /api/task-attempts/{id}/diff/ws",
-                "GET /api/task-attempts/{id}/draft",
-                "PUT /api/task-attempts/{id}/draft",
-                "DELETE /api/task-attempts/{id}/draft"
-            ],
-            "processes": [
-                "GET /api/execution-processes",
-                "GET /api/execution-processes/{id}",
-                "POST /api/execution-processes/{id}/stop"
-            ],
-            "events": [
-                "GET /api/events/processes/{id}/logs",
-                "GET /api/events/task-attempts/{id}/diff"
-            ],
-            "images": [
-                "POST /api/images",
-                "GET /api/images/{id}"
-            ],
-            "forge": [
-                "GET /api/forge/config",
-                "PUT /api/forge/config",
-                "GET /api/forge/projects/{id}/settings",
-                "PUT /api/forge/projects/{id}/settings",
-                "GET /api/forge/omni/status",
-                "GET /api/forge/omni/instances",
-                "POST /api/forge/omni/validate",
-                "GET /api/forge/omni/notifications",
-                "GET /api/forge/releases"
-            ],
-            "filesystem": [
-                "GET /api/filesystem/tree",
-                "GET /api/filesystem/file"
-            ],
-            "config": [
-                "GET /api/config",
-                "PUT /api/config"
-            ],
-            "drafts": [
-                "GET /api/drafts",
-                "POST /api/drafts",
-                "GET /api/drafts/{id}",
-                "PUT /api/drafts/{id}",
-                "DELETE /api/drafts/{id}"
-            ],
-            "containers": [
-                "GET /api/containers",
-                "GET /api/containers/{id}"
-            ],
-            "approvals": [
-                "POST /api/approvals/create",
-                "GET /api/approvals/{id}/status",
-                "POST /api/approvals/{id}/respond",
-                "GET /api/approvals/pending"
-            ]
-        },
-        "note": "This is a simple route listing. Most endpoints require GitHub OAuth authentication via /api/auth/github/device"
-    }))
+/// `/api/routes` response: every mounted operation with its auth requirement and body limit
+#[derive(Debug, serde::Serialize, JsonSchema)]
+struct RouteList {
+    version: &'static str,
+    auth_required: bool,
+    routes: Vec<RouteEntry>,
+}
+
+/// Route listing from the registry `create_router` filled while mounting
+async fn list_routes(State(state): State<ForgeAppState>) -> Json<RouteList> {
+    let mut routes: Vec<RouteEntry> = state
+        .operations
+        .iter()
+        .map(ApiOperation::route_entry)
+        .collect();
+    routes.sort_by(|a, b| a.path.cmp(&b.path).then_with(|| a.method.cmp(&b.method)));
+
+    Json(RouteList {
+        version: crate::version::get_version(),
+        auth_required: state.auth_required,
+        routes,
+    })
 }
 
 async fn get_auth_required(State(state): State<ForgeAppState>) -> Json<Value> {
@@ -482,11 +509,150 @@ async fn get_auth_required(State(state): State<ForgeAppState>) -> Json<Value> {
     }))
 }
 
+/// Revoke the caller's session and clear its cookie
+async fn logout(
+    State(state): State<ForgeAppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if let Some(token) = auth::session_token(&headers) {
+        auth::revoke_session(&state.services.pool, &token)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to revoke session: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to revoke session: {e}"),
+                )
+            })?;
+    }
+
+    Ok((
+        [(
+            header::SET_COOKIE,
+            auth::expired_session_cookie(state.secure_cookies),
+        )],
+        Json(json!({ "logged_out": true })),
+    ))
+}
+
+async fn get_omni_worker_status(State(state): State<ForgeAppState>) -> Json<OmniWorkerStatus> {
+    Json(state.services.omni_worker.status().await)
+}
+
+async fn reset_omni_worker(State(state): State<ForgeAppState>) -> Json<OmniWorkerStatus> {
+    state.services.omni_worker.reset().await;
+    Json(state.services.omni_worker.status().await)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use forge_core_utils::text::{git_branch_id, short_uuid};
     use uuid::Uuid;
 
+    use super::api_operations;
+    use crate::{
+        openapi::{DEFAULT_BODY_LIMIT, RouteAuth},
+        services::api_tokens::TokenScope,
+    };
+
+    fn registry_entry(method: &str, path: &str) -> crate::openapi::RouteEntry {
+        api_operations()
+            .iter()
+            .map(|operation| operation.route_entry())
+            .find(|entry| entry.method == method && entry.path == path)
+            .unwrap_or_else(|| panic!("{method} {path} is not registered"))
+    }
+
+    #[test]
+    fn route_registry_uses_mounted_paths() {
+        for (method, path) in [
+            ("GET", "/api/tasks/{task_id}"),
+            ("POST", "/api/task-attempts/{id}/draft/queue"),
+            ("POST", "/api/task-attempts/{id}/replace-process"),
+            ("GET", "/api/task-attempts/{id}/commit-compare"),
+            ("POST", "/api/task-attempts/{id}/conflicts/abort"),
+            ("GET", "/api/task-attempts/{id}/children"),
+            ("GET", "/api/tags"),
+            ("GET", "/api/forge/notifications"),
+        ] {
+            registry_entry(method, path);
+        }
+
+        let mut seen = HashSet::new();
+        for operation in api_operations() {
+            assert!(
+                seen.insert((operation.method.clone(), operation.path.clone())),
+                "{} {} registered twice",
+                operation.method,
+                operation.path
+            );
+        }
+    }
+
+    #[test]
+    fn route_registry_records_auth_and_body_limits() {
+        assert_eq!(
+            registry_entry("PUT", "/api/profiles").body_limit,
+            20 * 1024 * 1024
+        );
+        assert_eq!(
+            registry_entry("POST", "/api/images/upload").body_limit,
+            100 * 1024 * 1024
+        );
+        assert_eq!(
+            registry_entry("POST", "/api/tasks").body_limit,
+            DEFAULT_BODY_LIMIT
+        );
+
+        let public: HashSet<String> = api_operations()
+            .iter()
+            .filter(|operation| operation.auth() == RouteAuth::Public)
+            .map(|operation| format!("{} {}", operation.method, operation.path))
+            .collect();
+        let expected: HashSet<String> = [
+            "GET /health",
+            "GET /health/live",
+            "GET /health/ready",
+            "GET /site.webmanifest",
+            "GET /api/health",
+            "GET /api/forge/auth-required",
+            "POST /api/forge/auth/logout",
+            "GET /api/auth/github/check",
+            "POST /api/auth/github/device/start",
+            "POST /api/auth/github/device/poll",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        assert_eq!(public, expected);
+    }
+
+    #[test]
+    fn route_registry_records_token_scopes() {
+        for (method, path, scope) in [
+            ("GET", "/api/projects", TokenScope::Read),
+            (
+                "POST",
+                "/api/tasks/create-and-start",
+                TokenScope::TasksWrite,
+            ),
+            ("POST", "/api/projects", TokenScope::Admin),
+            ("PUT", "/api/config", TokenScope::Admin),
+            ("GET", "/api/forge/config", TokenScope::Admin),
+            ("GET", "/api/forge/tokens", TokenScope::Admin),
+            ("POST", "/api/forge/tokens", TokenScope::Admin),
+        ] {
+            assert_eq!(
+                registry_entry(method, path).scope,
+                Some(scope),
+                "{method} {path}"
+            );
+        }
+        assert_eq!(registry_entry("GET", "/health").scope, None);
+    }
+
     #[test]
     fn test_forge_branch_prefix_format() {
         // Test that branch names use "forge" prefix instead of "vk"
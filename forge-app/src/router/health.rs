@@ -0,0 +1,327 @@
+//! Liveness and readiness probes
+//!
+//! `/health/live` only shows that the HTTP stack answers. `/health/ready` checks
+//! what a working server depends on and answers 503 with per-component details
+//! when any of it is unhealthy, so Docker and pm2 can restart a wedged server.
+//! An open Omni circuit breaker is reported but stays healthy: the outage is on
+//! Omni's side and a restart would not fix it.
+
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{FromRef, State},
+    http::StatusCode,
+};
+use rust_embed::RustEmbed;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use tokio::{
+    task::JoinHandle,
+    time::{Duration, timeout},
+};
+
+use super::{ForgeAppState, Frontend};
+use crate::{
+    openapi::{self, ApiRouter, doc},
+    services::{CircuitState, OmniWorkerHandle, ProfileWatches},
+};
+
+/// How long a single check may take before it counts as unhealthy
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+/// Free space below which the asset dir (database, worktrees, logs) is unhealthy
+const MIN_FREE_DISK_BYTES: u64 = 256 * 1024 * 1024;
+
+/// - health/live: The process serves HTTP
+/// - health/ready: Database, background workers, frontend assets and disk space
+pub(super) fn routes() -> ApiRouter<ForgeAppState> {
+    ApiRouter::new()
+        .route(
+            "/health/live",
+            openapi::get(live, doc("Core", "Liveness probe").public()),
+        )
+        .route(
+            "/health/ready",
+            openapi::get(
+                ready,
+                doc("Core", "Readiness probe")
+                    .public()
+                    .response::<Readiness>(),
+            ),
+        )
+}
+
+/// What the readiness probe inspects
+#[derive(Clone)]
+struct ReadinessChecks {
+    pool: SqlitePool,
+    omni_worker: OmniWorkerHandle,
+    pr_monitor: Arc<JoinHandle<()>>,
+    profile_watches: ProfileWatches,
+}
+
+impl FromRef<ForgeAppState> for ReadinessChecks {
+    fn from_ref(state: &ForgeAppState) -> Self {
+        Self {
+            pool: state.services.pool.clone(),
+            omni_worker: state.services.omni_worker.clone(),
+            pr_monitor: state.services.pr_monitor.clone(),
+            profile_watches: state.services.profile_watches().clone(),
+        }
+    }
+}
+
+/// `/health/ready` response; 503 when any component is unhealthy
+#[derive(Debug, Serialize, JsonSchema)]
+struct Readiness {
+    ready: bool,
+    version: &'static str,
+    components: Components,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct Components {
+    database: ComponentHealth,
+    omni_worker: ComponentHealth,
+    pr_monitor: ComponentHealth,
+    profile_watchers: ComponentHealth,
+    frontend_assets: ComponentHealth,
+    disk: ComponentHealth,
+}
+
+impl Components {
+    fn all_healthy(&self) -> bool {
+        [
+            &self.database,
+            &self.omni_worker,
+            &self.pr_monitor,
+            &self.profile_watchers,
+            &self.frontend_assets,
+            &self.disk,
+        ]
+        .iter()
+        .all(|component| component.healthy)
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct ComponentHealth {
+    healthy: bool,
+    /// Current state, or why the component is unhealthy
+    detail: String,
+    /// Measurement behind the state: watcher count, free bytes, ...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<u64>,
+}
+
+impl ComponentHealth {
+    fn healthy(detail: impl Into<String>) -> Self {
+        Self {
+            healthy: true,
+            detail: detail.into(),
+            value: None,
+        }
+    }
+
+    fn unhealthy(detail: impl Into<String>) -> Self {
+        Self {
+            healthy: false,
+            ..Self::healthy(detail)
+        }
+    }
+
+    fn with_value(self, value: u64) -> Self {
+        Self {
+            value: Some(value),
+            ..self
+        }
+    }
+}
+
+async fn live() -> Json<Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+async fn ready(State(checks): State<ReadinessChecks>) -> (StatusCode, Json<Readiness>) {
+    let components = Components {
+        database: check_database(&checks.pool).await,
+        omni_worker: check_omni_worker(&checks.omni_worker).await,
+        pr_monitor: check_pr_monitor(&checks.pr_monitor),
+        profile_watchers: check_profile_watchers(&checks.profile_watches),
+        frontend_assets: check_frontend_assets(),
+        disk: check_disk(),
+    };
+    let ready = components.all_healthy();
+    if !ready {
+        tracing::warn!("Readiness check failed: {:?}", components);
+    }
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(Readiness {
+            ready,
+            version: crate::version::get_version(),
+            components,
+        }),
+    )
+}
+
+async fn check_database(pool: &SqlitePool) -> ComponentHealth {
+    let query = sqlx::query_scalar::<_, i64>("SELECT 1").fetch_one(pool);
+    match timeout(CHECK_TIMEOUT, query).await {
+        Ok(Ok(_)) => ComponentHealth::healthy("connected"),
+        Ok(Err(e)) => ComponentHealth::unhealthy(format!("query failed: {e}")),
+        Err(_) => ComponentHealth::unhealthy("query timed out"),
+    }
+}
+
+async fn check_omni_worker(omni_worker: &OmniWorkerHandle) -> ComponentHealth {
+    let Ok(status) = timeout(CHECK_TIMEOUT, omni_worker.status()).await else {
+        return ComponentHealth::unhealthy("status timed out");
+    };
+    let health = if !status.running {
+        ComponentHealth::unhealthy("not running")
+    } else {
+        match status.circuit {
+            CircuitState::Closed => ComponentHealth::healthy("running"),
+            CircuitState::Open => ComponentHealth::healthy("running; circuit breaker open"),
+            CircuitState::HalfOpen => {
+                ComponentHealth::healthy("running; circuit breaker half-open")
+            }
+        }
+    };
+    // Supervisor restarts so far
+    health.with_value(status.restarts.into())
+}
+
+fn check_pr_monitor(pr_monitor: &JoinHandle<()>) -> ComponentHealth {
+    if pr_monitor.is_finished() {
+        ComponentHealth::unhealthy("stopped")
+    } else {
+        ComponentHealth::healthy("running")
+    }
+}
+
+/// Unhealthy when a `.genie` watch could not be set up or has reported an error:
+/// profile edits in that workspace would silently stop reloading
+fn check_profile_watchers(profile_watches: &ProfileWatches) -> ComponentHealth {
+    let status = profile_watches.status();
+    let health = if status.failed.is_empty() {
+        ComponentHealth::healthy(format!("{} workspace(s) watched", status.watched))
+    } else {
+        ComponentHealth::unhealthy(format!(
+            "{} watch(es) broken: {}",
+            status.failed.len(),
+            status.failed.join("; ")
+        ))
+    };
+    health.with_value(status.watched as u64)
+}
+
+fn check_frontend_assets() -> ComponentHealth {
+    if Frontend::get("index.html").is_some() {
+        ComponentHealth::healthy("embedded")
+    } else {
+        ComponentHealth::unhealthy("index.html is missing; build the frontend first")
+    }
+}
+
+fn check_disk() -> ComponentHealth {
+    let asset_dir = forge_core_utils::assets::asset_dir();
+    match fs4::available_space(&asset_dir) {
+        Ok(available) if available < MIN_FREE_DISK_BYTES => ComponentHealth::unhealthy(format!(
+            "only {} MiB free in {}",
+            available / (1024 * 1024),
+            asset_dir.display()
+        ))
+        .with_value(available),
+        Ok(available) => ComponentHealth::healthy(format!(
+            "{} MiB free in {}",
+            available / (1024 * 1024),
+            asset_dir.display()
+        ))
+        .with_value(available),
+        Err(e) => ComponentHealth::unhealthy(format!(
+            "cannot read free space of {}: {e}",
+            asset_dir.display()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::to_bytes, http::Request, routing::get};
+    use forge_core_services::services::forge_config::ForgeConfigService;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::services::test_support;
+
+    async fn checks(pool: &SqlitePool) -> ReadinessChecks {
+        let omni_worker = OmniWorkerHandle::spawn(
+            pool.clone(),
+            Arc::new(ForgeConfigService::new(pool.clone())),
+        );
+        timeout(Duration::from_secs(5), async {
+            while !omni_worker.status().await.running {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("omni worker should start");
+
+        ReadinessChecks {
+            pool: pool.clone(),
+            omni_worker,
+            pr_monitor: Arc::new(tokio::spawn(std::future::pending())),
+            profile_watches: ProfileWatches::default(),
+        }
+    }
+
+    async fn probe(checks: ReadinessChecks) -> (StatusCode, Value) {
+        let response = Router::new()
+            .route("/health/ready", get(ready))
+            .with_state(checks)
+            .oneshot(
+                Request::get("/health/ready")
+                    .body(Default::default())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn ready_when_every_component_is_healthy() {
+        let pool = test_support::pool().await;
+
+        let (status, body) = probe(checks(&pool).await).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["ready"], true);
+        assert_eq!(body["components"]["database"]["detail"], "connected");
+        assert_eq!(body["components"]["omni_worker"]["detail"], "running");
+    }
+
+    #[tokio::test]
+    async fn closed_database_is_unavailable() {
+        let pool = test_support::pool().await;
+        let checks = checks(&pool).await;
+        pool.close().await;
+
+        let (status, body) = probe(checks).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["ready"], false);
+        assert_eq!(body["components"]["database"]["healthy"], false);
+        assert_eq!(body["components"]["pr_monitor"]["healthy"], true);
+    }
+}
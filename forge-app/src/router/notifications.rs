@@ -0,0 +1,314 @@
+//! Notification history, sink configuration and template preview routes
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::ForgeAppState;
+use crate::{
+    logging,
+    openapi::{self, ApiRouter, doc},
+    services::{
+        notification_events::NotificationEvent,
+        notification_history::{
+            self, NotificationFilter, NotificationPurgeResult, NotificationRecord, RetryOutcome,
+        },
+        notification_sinks::{
+            self, NotificationDelivery, NotificationSettings, save_notification_settings,
+        },
+        notification_templates::{
+            self, NotificationContext, NotificationTemplates, RenderedNotification,
+        },
+    },
+};
+
+/// - notifications: Notification history with filters, bulk purge of finished rows
+/// - notifications/{id}/retry: Re-queue a dead-lettered or skipped notification
+/// - notifications/{id}/deliveries: Per-sink delivery status of a notification
+/// - notification-sinks: Webhook/Slack/ntfy/email sink configuration (global or per project)
+/// - notification-templates/preview: Render notification templates against a real attempt
+pub(super) fn routes() -> ApiRouter<ForgeAppState> {
+    ApiRouter::new()
+        .route(
+            "/api/forge/notifications",
+            openapi::get(
+                list_notifications,
+                doc("Notifications", "List notification history")
+                    .query::<NotificationFilter>()
+                    .response::<Vec<NotificationRecord>>(),
+            )
+            .delete(
+                purge_notifications,
+                doc("Notifications", "Purge finished notifications")
+                    .query::<NotificationFilter>()
+                    .response::<NotificationPurgeResult>(),
+            ),
+        )
+        .route(
+            "/api/forge/notifications/{id}/retry",
+            openapi::post(
+                retry_notification,
+                doc(
+                    "Notifications",
+                    "Retry a dead-lettered or skipped notification",
+                ),
+            ),
+        )
+        .route(
+            "/api/forge/notifications/{id}/deliveries",
+            openapi::get(
+                get_notification_deliveries,
+                doc("Notifications", "Per-sink delivery status")
+                    .response::<Vec<NotificationDelivery>>(),
+            ),
+        )
+        .route(
+            "/api/forge/notification-sinks",
+            openapi::get(
+                get_notification_sinks,
+                doc("Notifications", "Get notification sink settings")
+                    .query::<NotificationSinksQuery>()
+                    .response::<NotificationSettings>(),
+            )
+            .put(
+                update_notification_sinks,
+                doc("Notifications", "Update notification sink settings")
+                    .query::<NotificationSinksQuery>()
+                    .request::<NotificationSettings>()
+                    .response::<NotificationSettings>(),
+            ),
+        )
+        .route(
+            "/api/forge/notification-templates/preview",
+            openapi::post(
+                preview_notification_template,
+                doc("Notifications", "Preview notification templates")
+                    .request::<NotificationPreviewRequest>()
+                    .response::<NotificationPreviewResponse>(),
+            ),
+        )
+}
+
+async fn list_notifications(
+    State(state): State<ForgeAppState>,
+    Query(filter): Query<NotificationFilter>,
+) -> Result<Json<Vec<NotificationRecord>>, (StatusCode, String)> {
+    notification_history::list_notifications(&state.services.pool, &filter)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("Failed to list notifications: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to list notifications: {e}"),
+            )
+        })
+}
+
+/// Delete finished notifications matching the filter; queued rows are never purged
+async fn purge_notifications(
+    State(state): State<ForgeAppState>,
+    Query(filter): Query<NotificationFilter>,
+) -> Result<Json<NotificationPurgeResult>, (StatusCode, String)> {
+    if let Some(status) = filter.status.as_deref()
+        && matches!(status, "pending" | "processing")
+    {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Cannot purge '{status}' notifications; only finished rows can be purged"),
+        ));
+    }
+
+    let deleted = notification_history::purge_notifications(&state.services.pool, &filter)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to purge notifications: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to purge notifications: {e}"),
+            )
+        })?;
+
+    Ok(Json(NotificationPurgeResult {
+        deleted: deleted as u32,
+    }))
+}
+
+async fn retry_notification(
+    State(state): State<ForgeAppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let request_id = logging::request_id(&headers);
+    match notification_history::retry_notification(&state.services.pool, &id, request_id).await {
+        Ok(RetryOutcome::Requeued) => {
+            state.services.omni_worker.wake();
+            Ok(Json(json!({
+                "id": id,
+                "status": "pending"
+            })))
+        }
+        Ok(RetryOutcome::NotRetryable { status }) => Err((
+            StatusCode::CONFLICT,
+            format!(
+                "Notification {id} is '{status}'; only dead-lettered or skipped notifications can be retried"
+            ),
+        )),
+        Ok(RetryOutcome::NotFound) => Err((
+            StatusCode::NOT_FOUND,
+            format!("No notification with id {id}"),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to re-queue notification {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to re-queue notification: {e}"),
+            ))
+        }
+    }
+}
+
+async fn get_notification_deliveries(
+    State(state): State<ForgeAppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<NotificationDelivery>>, (StatusCode, String)> {
+    notification_sinks::list_notification_deliveries(&state.services.pool, &id)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("Failed to load deliveries for notification {}: {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to load notification deliveries: {e}"),
+            )
+        })
+}
+
+/// Scope for sink configuration; omit `project_id` for the global settings
+#[derive(Debug, Deserialize, JsonSchema)]
+struct NotificationSinksQuery {
+    project_id: Option<Uuid>,
+}
+
+/// Sink settings with secrets redacted; sinks that have one report `has_secret`
+async fn get_notification_sinks(
+    State(state): State<ForgeAppState>,
+    Query(query): Query<NotificationSinksQuery>,
+) -> Result<Json<NotificationSettings>, (StatusCode, String)> {
+    notification_sinks::load_notification_settings(&state.services.pool, query.project_id)
+        .await
+        .map(|settings| Json(settings.redacted()))
+        .map_err(|e| {
+            tracing::error!("Failed to load notification sinks: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to load notification sinks: {e}"),
+            )
+        })
+}
+
+/// Replace sink settings; secrets omitted or sent back redacted keep their stored value
+async fn update_notification_sinks(
+    State(state): State<ForgeAppState>,
+    Query(query): Query<NotificationSinksQuery>,
+    Json(mut settings): Json<NotificationSettings>,
+) -> Result<Json<NotificationSettings>, (StatusCode, String)> {
+    let stored =
+        notification_sinks::load_notification_settings(&state.services.pool, query.project_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to load notification sinks: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to load notification sinks: {e}"),
+                )
+            })?;
+    settings.restore_secrets(&stored);
+    settings
+        .validate()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    save_notification_settings(&state.services.pool, query.project_id, &settings)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to save notification sinks: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to save notification sinks: {e}"),
+            )
+        })?;
+
+    Ok(Json(settings.redacted()))
+}
+
+/// Preview request; without `templates` the attempt's effective project templates are used
+#[derive(Debug, Deserialize, JsonSchema)]
+struct NotificationPreviewRequest {
+    task_attempt_id: Uuid,
+    event: Option<NotificationEvent>,
+    status: Option<String>,
+    templates: Option<NotificationTemplates>,
+}
+
+#[derive(Debug, serde::Serialize, JsonSchema)]
+struct NotificationPreviewResponse {
+    #[serde(flatten)]
+    rendered: RenderedNotification,
+    context: NotificationContext,
+}
+
+/// Render notification templates against a real attempt without sending anything
+async fn preview_notification_template(
+    State(state): State<ForgeAppState>,
+    Json(request): Json<NotificationPreviewRequest>,
+) -> Result<Json<NotificationPreviewResponse>, (StatusCode, String)> {
+    let pool = &state.services.pool;
+
+    let project_id: Uuid = sqlx::query_scalar(
+        "SELECT t.project_id FROM task_attempts ta JOIN tasks t ON t.id = ta.task_id WHERE ta.id = ?",
+    )
+    .bind(request.task_attempt_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Task attempt {} not found", request.task_attempt_id),
+        )
+    })?;
+
+    let templates = match request.templates {
+        Some(templates) => templates,
+        None => {
+            notification_sinks::effective_notification_settings(pool, project_id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .templates
+        }
+    };
+
+    templates
+        .validate()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    let (rendered, context) = notification_templates::preview_notification(
+        pool,
+        request.task_attempt_id,
+        request
+            .event
+            .unwrap_or(NotificationEvent::ExecutionCompleted),
+        request.status.as_deref(),
+        &templates,
+    )
+    .await
+    .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, format!("{e:#}")))?;
+
+    Ok(Json(NotificationPreviewResponse { rendered, context }))
+}
@@ -0,0 +1,40 @@
+//! Prometheus scrape endpoint, mounted always but only served when metrics are enabled
+
+use axum::{
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+
+use super::ForgeAppState;
+use crate::openapi::{self, ApiRouter, doc};
+
+/// - metrics: Prometheus text format; 404 unless enabled in the config
+pub(super) fn routes() -> ApiRouter<ForgeAppState> {
+    ApiRouter::new().route(
+        "/metrics",
+        openapi::get(scrape, doc("Core", "Prometheus metrics")),
+    )
+}
+
+async fn scrape(State(state): State<ForgeAppState>) -> Response {
+    let Some(metrics) = &state.metrics else {
+        return (
+            StatusCode::NOT_FOUND,
+            "Metrics are disabled; enable them with `metrics = true`, FORGE_METRICS or --metrics",
+        )
+            .into_response();
+    };
+
+    match metrics.render(&state.services.pool).await {
+        Ok(body) => ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to render metrics: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to render metrics: {e}"),
+            )
+                .into_response()
+        }
+    }
+}
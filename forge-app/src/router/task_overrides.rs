@@ -0,0 +1,337 @@
+//! Tasks and task attempts routers
+//!
+//! Upstream's handlers, mounted with forge's layers: lifecycle notifications and
+//! traces, and the agent start limits of [`rate_limit`] on the routes that spawn a
+//! coding agent.
+
+use axum::routing::MethodRouter;
+use forge_core_db::models::{
+    execution_process::ExecutionProcess,
+    task::{CreateTask, Task, TaskWithAttemptStatus, UpdateTask},
+    task_attempt::TaskAttempt,
+};
+use forge_core_server::routes::{task_attempts, tasks};
+
+use super::{ForgeAppState, RouteDeps};
+use crate::{
+    middleware::{lifecycle_events, lifecycle_spans, rate_limit},
+    openapi::{self, ApiRouter, SchemaRef, doc},
+};
+
+/// Forge override: rate-limit the agent starts a route makes and queue them over the
+/// running cap
+fn limit_agent_starts(
+    deps: Option<RouteDeps<'_>>,
+    route: MethodRouter<ForgeAppState>,
+) -> MethodRouter<ForgeAppState> {
+    match deps {
+        Some(deps) => route.layer(axum::middleware::from_fn_with_state(
+            deps.limiter.clone(),
+            rate_limit::limit_agent_starts,
+        )),
+        None => route,
+    }
+}
+
+/// Build tasks router - uses forge-core's handlers that exclude agent tasks
+/// via the forge_agents table (kanban vs agent task separation)
+pub(super) fn build_tasks_router_with_forge_override(
+    deps: Option<RouteDeps<'_>>,
+) -> ApiRouter<ForgeAppState> {
+    use axum::middleware::from_fn_with_state;
+    use forge_core_server::middleware::load_task_middleware;
+
+    let task_id_router = ApiRouter::new()
+        .route(
+            "/",
+            openapi::get(
+                tasks::get_task,
+                doc("Tasks", "Get a task")
+                    .response_schema(SchemaRef::ts::<Task>())
+                    .enveloped(),
+            )
+            .put(
+                tasks::update_task,
+                doc("Tasks", "Update a task")
+                    .request_schema(SchemaRef::ts::<UpdateTask>())
+                    .response_schema(SchemaRef::ts::<Task>())
+                    .enveloped(),
+            )
+            .delete(
+                tasks::delete_task,
+                doc("Tasks", "Delete a task").enveloped(),
+            ),
+        )
+        .map(|router| match deps {
+            Some(deps) => router.layer(from_fn_with_state(
+                deps.deployment.clone(),
+                load_task_middleware,
+            )),
+            None => router,
+        });
+
+    let inner = ApiRouter::new()
+        // Use forge-core handlers - agent tasks filtered via forge_agents table
+        .route(
+            "/",
+            openapi::get(
+                tasks::get_tasks,
+                doc("Tasks", "List tasks for a project")
+                    .response_schema(SchemaRef::ts_array::<TaskWithAttemptStatus>())
+                    .enveloped(),
+            )
+            .post(
+                tasks::create_task,
+                doc("Tasks", "Create a task")
+                    .request_schema(SchemaRef::ts::<CreateTask>())
+                    .response_schema(SchemaRef::ts::<Task>())
+                    .enveloped(),
+            ),
+        )
+        .route(
+            "/stream/ws",
+            openapi::get(
+                tasks::stream_tasks_ws,
+                doc("Tasks", "Stream task updates (WebSocket)"),
+            ),
+        )
+        // forge-core now handles everything: profile injection + agent tracking + executor:variant
+        .route(
+            "/create-and-start",
+            openapi::post(
+                tasks::create_task_and_start,
+                doc("Tasks", "Create a task and start an attempt").enveloped(),
+            )
+            // Forge override: the started attempt's lifecycle trace begins here
+            .map(|route| match deps {
+                Some(deps) => route.layer(from_fn_with_state(
+                    deps.services.clone(),
+                    lifecycle_spans::trace_create_and_start,
+                )),
+                None => route,
+            })
+            .map(|route| limit_agent_starts(deps, route)),
+        )
+        .nest("/{task_id}", task_id_router);
+
+    ApiRouter::new().nest("/tasks", inner)
+}
+
+/// Build task_attempts router with forge override for create endpoint
+pub(super) fn build_task_attempts_router_with_forge_override(
+    deps: Option<RouteDeps<'_>>,
+) -> ApiRouter<ForgeAppState> {
+    use axum::middleware::from_fn_with_state;
+    use forge_core_server::middleware::load_task_attempt_middleware;
+
+    // Forge override: queue merge/rebase conflict notifications
+    let conflict_notifications = |route: MethodRouter<ForgeAppState>| match deps {
+        Some(deps) => route.layer(from_fn_with_state(
+            lifecycle_events::EventQueue::new(deps.services),
+            lifecycle_events::notify_on_git_conflicts,
+        )),
+        None => route,
+    };
+    // Forge override: record the call as a phase of the attempt's lifecycle trace
+    let lifecycle_span = |phase: &'static str| {
+        move |route: MethodRouter<ForgeAppState>| match deps {
+            Some(_) => route.layer(from_fn_with_state(
+                phase,
+                lifecycle_spans::trace_attempt_action,
+            )),
+            None => route,
+        }
+    };
+    let attempt = |summary| doc("Task Attempts", summary).enveloped();
+
+    let task_attempt_id_router = ApiRouter::new()
+        .route(
+            "/",
+            openapi::get(
+                task_attempts::get_task_attempt,
+                attempt("Get a task attempt").response_schema(SchemaRef::ts::<TaskAttempt>()),
+            ),
+        )
+        // forge-core's follow_up now handles profile injection automatically
+        .route(
+            "/follow-up",
+            openapi::post(
+                task_attempts::follow_up,
+                attempt("Send a follow-up").response_schema(SchemaRef::ts::<ExecutionProcess>()),
+            )
+            .map(lifecycle_span("follow_up_request"))
+            .map(|route| limit_agent_starts(deps, route)),
+        )
+        .route(
+            "/draft",
+            openapi::get(task_attempts::drafts::get_draft, attempt("Get the draft"))
+                .put(task_attempts::drafts::save_draft, attempt("Save the draft"))
+                .delete(
+                    task_attempts::drafts::delete_draft,
+                    attempt("Delete the draft"),
+                ),
+        )
+        .route(
+            "/draft/queue",
+            openapi::post(
+                task_attempts::drafts::set_draft_queue,
+                attempt("Queue or unqueue the draft"),
+            ),
+        )
+        .route(
+            "/replace-process",
+            openapi::post(
+                task_attempts::replace_process,
+                attempt("Replace an execution process"),
+            ),
+        )
+        .route(
+            "/commit-info",
+            openapi::get(task_attempts::get_commit_info, attempt("Get commit info")),
+        )
+        .route(
+            "/commit-compare",
+            openapi::get(
+                task_attempts::compare_commit_to_head,
+                attempt("Compare a commit to HEAD"),
+            ),
+        )
+        .route(
+            "/start-dev-server",
+            openapi::post(
+                task_attempts::start_dev_server,
+                attempt("Start the dev server"),
+            ),
+        )
+        // Use forge-core's branch-status - already has remote_commits_behind/ahead
+        .route(
+            "/branch-status",
+            openapi::get(
+                task_attempts::get_task_attempt_branch_status,
+                attempt("Get branch status"),
+            ),
+        )
+        .route(
+            "/diff/ws",
+            openapi::get(
+                task_attempts::stream_task_attempt_diff_ws,
+                doc("Task Attempts", "Stream the diff (WebSocket)"),
+            ),
+        )
+        .route(
+            "/merge",
+            openapi::post(
+                task_attempts::merge_task_attempt,
+                attempt("Merge into the target branch"),
+            )
+            .map(conflict_notifications)
+            .map(lifecycle_span("merge")),
+        )
+        .route(
+            "/push",
+            openapi::post(
+                task_attempts::push_task_attempt_branch,
+                attempt("Push the branch"),
+            )
+            .map(lifecycle_span("push")),
+        )
+        .route(
+            "/rebase",
+            openapi::post(
+                task_attempts::rebase_task_attempt,
+                attempt("Rebase onto the target branch"),
+            )
+            .map(conflict_notifications),
+        )
+        .route(
+            "/conflicts/abort",
+            openapi::post(
+                task_attempts::abort_conflicts_task_attempt,
+                attempt("Abort a conflicted merge or rebase"),
+            ),
+        )
+        .route(
+            "/pr",
+            openapi::post(
+                task_attempts::create_github_pr,
+                attempt("Create a pull request"),
+            )
+            .map(lifecycle_span("create_pr")),
+        )
+        .route(
+            "/pr/attach",
+            openapi::post(
+                task_attempts::attach_existing_pr,
+                attempt("Attach an existing pull request"),
+            )
+            .map(lifecycle_span("attach_pr")),
+        )
+        .route(
+            "/open-editor",
+            openapi::post(
+                task_attempts::open_task_attempt_in_editor,
+                attempt("Open the worktree in an editor"),
+            ),
+        )
+        .route(
+            "/delete-file",
+            openapi::post(
+                task_attempts::delete_task_attempt_file,
+                attempt("Delete a file from the worktree"),
+            ),
+        )
+        .route(
+            "/children",
+            openapi::get(
+                task_attempts::get_task_attempt_children,
+                attempt("List child tasks"),
+            ),
+        )
+        .route(
+            "/stop",
+            openapi::post(
+                task_attempts::stop_task_attempt_execution,
+                attempt("Stop running processes"),
+            ),
+        )
+        .route(
+            "/change-target-branch",
+            openapi::post(
+                task_attempts::change_target_branch,
+                attempt("Change the target branch"),
+            ),
+        )
+        .map(|router| match deps {
+            Some(deps) => router.layer(from_fn_with_state(
+                deps.deployment.clone(),
+                load_task_attempt_middleware,
+            )),
+            None => router,
+        });
+
+    let task_attempts_router = ApiRouter::new()
+        .route(
+            "/",
+            // forge-core now handles everything: profile injection + executor:variant
+            openapi::get(
+                task_attempts::get_task_attempts,
+                attempt("List task attempts").response_schema(SchemaRef::ts_array::<TaskAttempt>()),
+            )
+            .post(
+                task_attempts::create_task_attempt,
+                attempt("Create a task attempt").response_schema(SchemaRef::ts::<TaskAttempt>()),
+            )
+            // Forge override: the new attempt's lifecycle trace begins here
+            .map(|route| match deps {
+                Some(deps) => route.layer(from_fn_with_state(
+                    deps.services.clone(),
+                    lifecycle_spans::trace_create_attempt,
+                )),
+                None => route,
+            })
+            .map(|route| limit_agent_starts(deps, route)),
+        )
+        .nest("/{id}", task_attempt_id_router);
+
+    ApiRouter::new().nest("/task-attempts", task_attempts_router)
+}
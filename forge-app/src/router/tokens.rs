@@ -0,0 +1,100 @@
+//! API token routes for scripts and CI
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+use super::ForgeAppState;
+use crate::{
+    openapi::{self, ApiRouter, doc},
+    services::api_tokens::{self, ApiTokenRecord, CreateApiToken, CreatedApiToken},
+};
+
+/// - tokens: List tokens (without secrets) and mint new ones
+/// - tokens/{id}: Revoke a token
+pub(super) fn routes() -> ApiRouter<ForgeAppState> {
+    ApiRouter::new()
+        .route(
+            "/api/forge/tokens",
+            openapi::get(
+                list_tokens,
+                doc("Tokens", "List API tokens").response::<Vec<ApiTokenRecord>>(),
+            )
+            .post(
+                create_token,
+                doc("Tokens", "Create an API token")
+                    .request::<CreateApiToken>()
+                    .response::<CreatedApiToken>(),
+            ),
+        )
+        .route(
+            "/api/forge/tokens/{id}",
+            openapi::delete(revoke_token, doc("Tokens", "Revoke an API token")),
+        )
+}
+
+async fn list_tokens(
+    State(state): State<ForgeAppState>,
+) -> Result<Json<Vec<ApiTokenRecord>>, (StatusCode, String)> {
+    api_tokens::list_api_tokens(&state.services.pool)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("Failed to list API tokens: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to list API tokens: {e}"),
+            )
+        })
+}
+
+/// The plaintext token is only ever part of this response
+async fn create_token(
+    State(state): State<ForgeAppState>,
+    Json(request): Json<CreateApiToken>,
+) -> Result<(StatusCode, Json<CreatedApiToken>), (StatusCode, String)> {
+    request
+        .validate()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    let created = api_tokens::create_api_token(&state.services.pool, &request)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create API token: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to create API token: {e}"),
+            )
+        })?;
+
+    tracing::info!(
+        "Created API token '{}' ({}) with scopes {:?}",
+        created.record.name,
+        created.record.token_prefix,
+        created.record.scopes
+    );
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+async fn revoke_token(
+    State(state): State<ForgeAppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match api_tokens::revoke_api_token(&state.services.pool, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            format!("No active API token with id {id}"),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to revoke API token {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to revoke API token: {e}"),
+            ))
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! API Tokens
+//!
+//! Personal access tokens for scripts and CI, sent as `Authorization: Bearer`. Only a
+//! SHA-256 hash of each token is stored; the plaintext is returned once, at creation.
+//! Revoked tokens keep their row so listings show when they were revoked.
+
+use std::fmt;
+
+use anyhow::{Result, bail};
+use rand::Rng;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqlitePool, sqlite::SqliteRow};
+use ts_rs_forge::TS;
+use uuid::Uuid;
+
+/// Tokens start with this so secret scanners and humans can recognise them
+pub const TOKEN_PREFIX: &str = "forge_pat_";
+
+/// What a token may do. Each scope includes the ones before it:
+/// `read` < `tasks:write` < `admin`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, TS, JsonSchema,
+)]
+#[ts(crate = "ts_rs_forge")]
+pub enum TokenScope {
+    #[serde(rename = "read")]
+    Read,
+    #[serde(rename = "tasks:write")]
+    TasksWrite,
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl TokenScope {
+    fn as_str(self) -> &'static str {
+        match self {
+            TokenScope::Read => "read",
+            TokenScope::TasksWrite => "tasks:write",
+            TokenScope::Admin => "admin",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "read" => Some(TokenScope::Read),
+            "tasks:write" => Some(TokenScope::TasksWrite),
+            "admin" => Some(TokenScope::Admin),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Request body for minting a token
+#[derive(Debug, Clone, Deserialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct CreateApiToken {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    /// Days until the token stops working; omit for a token that never expires
+    pub expires_in_days: Option<u32>,
+}
+
+/// A stored token, without its secret
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct ApiTokenRecord {
+    pub id: Uuid,
+    pub name: String,
+    /// First characters of the token, enough to tell tokens apart
+    pub token_prefix: String,
+    pub scopes: Vec<TokenScope>,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub expires_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+/// Returned once by the create endpoint; `token` cannot be retrieved again
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct CreatedApiToken {
+    pub token: String,
+    #[serde(flatten)]
+    #[ts(flatten)]
+    pub record: ApiTokenRecord,
+}
+
+impl CreateApiToken {
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            bail!("Token name must not be empty");
+        }
+        if self.scopes.is_empty() {
+            bail!("At least one scope is required");
+        }
+        if self.expires_in_days == Some(0) {
+            bail!("expires_in_days must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+pub async fn create_api_token(
+    pool: &SqlitePool,
+    request: &CreateApiToken,
+) -> Result<CreatedApiToken> {
+    request.validate()?;
+
+    let id = Uuid::new_v4();
+    let token = format!("{TOKEN_PREFIX}{}", random_hex(32));
+    let token_prefix = token[..TOKEN_PREFIX.len() + 8].to_string();
+    let mut scopes = request.scopes.clone();
+    scopes.sort();
+    scopes.dedup();
+    let scopes_csv = scopes
+        .iter()
+        .map(|scope| scope.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    sqlx::query(
+        "INSERT INTO forge_api_tokens (id, name, token_hash, token_prefix, scopes, expires_at)
+         VALUES (?, ?, ?, ?, ?, CASE WHEN ? IS NULL THEN NULL ELSE datetime('now', ?) END)",
+    )
+    .bind(id)
+    .bind(request.name.trim())
+    .bind(hash_token(&token))
+    .bind(&token_prefix)
+    .bind(scopes_csv)
+    .bind(request.expires_in_days)
+    .bind(request.expires_in_days.map(|days| format!("+{days} days")))
+    .execute(pool)
+    .await?;
+
+    let record = sqlx::query(&format!(
+        "SELECT {RECORD_COLUMNS} FROM forge_api_tokens WHERE id = ?"
+    ))
+    .bind(id)
+    .fetch_one(pool)
+    .await
+    .and_then(|row| record_from_row(&row))?;
+
+    Ok(CreatedApiToken { token, record })
+}
+
+const RECORD_COLUMNS: &str =
+    "id, name, token_prefix, scopes, created_at, last_used_at, expires_at, revoked_at";
+
+pub async fn list_api_tokens(pool: &SqlitePool) -> Result<Vec<ApiTokenRecord>> {
+    let rows = sqlx::query(&format!(
+        "SELECT {RECORD_COLUMNS} FROM forge_api_tokens ORDER BY created_at DESC, rowid DESC"
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(record_from_row).collect::<Result<_, _>>()?)
+}
+
+/// Revoke a token; false when no active token has this id
+pub async fn revoke_api_token(pool: &SqlitePool, id: Uuid) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE forge_api_tokens SET revoked_at = datetime('now')
+         WHERE id = ? AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Scopes of a live token, recording the use; `None` for unknown, revoked or expired tokens
+pub async fn authenticate_api_token(
+    pool: &SqlitePool,
+    token: &str,
+) -> Result<Option<Vec<TokenScope>>> {
+    if !token.starts_with(TOKEN_PREFIX) {
+        return Ok(None);
+    }
+
+    let scopes: Option<String> = sqlx::query_scalar(
+        "UPDATE forge_api_tokens SET last_used_at = datetime('now')
+         WHERE token_hash = ?
+           AND revoked_at IS NULL
+           AND (expires_at IS NULL OR expires_at > datetime('now'))
+         RETURNING scopes",
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(scopes.map(|scopes| parse_scopes(&scopes)))
+}
+
+fn record_from_row(row: &SqliteRow) -> Result<ApiTokenRecord, sqlx::Error> {
+    let scopes: String = row.try_get("scopes")?;
+    Ok(ApiTokenRecord {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        token_prefix: row.try_get("token_prefix")?,
+        scopes: parse_scopes(&scopes),
+        created_at: row.try_get("created_at")?,
+        last_used_at: row.try_get("last_used_at")?,
+        expires_at: row.try_get("expires_at")?,
+        revoked_at: row.try_get("revoked_at")?,
+    })
+}
+
+fn parse_scopes(csv: &str) -> Vec<TokenScope> {
+    csv.split(',').filter_map(TokenScope::parse).collect()
+}
+
+/// Hex-encoded random secret of `bytes` bytes
+pub(crate) fn random_hex(bytes: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..bytes)
+        .map(|_| format!("{:02x}", rng.r#gen::<u8>()))
+        .collect()
+}
+
+/// Tokens and sessions are stored as this digest, never in plaintext
+pub(crate) fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support;
+
+    fn request(scopes: Vec<TokenScope>, expires_in_days: Option<u32>) -> CreateApiToken {
+        CreateApiToken {
+            name: "ci".into(),
+            scopes,
+            expires_in_days,
+        }
+    }
+
+    #[tokio::test]
+    async fn tokens_authenticate_until_revoked() {
+        let pool = test_support::pool().await;
+        let created = create_api_token(
+            &pool,
+            &request(vec![TokenScope::TasksWrite, TokenScope::Read], None),
+        )
+        .await
+        .unwrap();
+
+        assert!(created.token.starts_with(TOKEN_PREFIX));
+        assert!(created.token.starts_with(&created.record.token_prefix));
+        assert_eq!(
+            created.record.scopes,
+            vec![TokenScope::Read, TokenScope::TasksWrite]
+        );
+        assert!(created.record.last_used_at.is_none());
+
+        let scopes = authenticate_api_token(&pool, &created.token).await.unwrap();
+        assert_eq!(scopes, Some(vec![TokenScope::Read, TokenScope::TasksWrite]));
+        let listed = list_api_tokens(&pool).await.unwrap();
+        assert!(listed[0].last_used_at.is_some());
+
+        assert!(revoke_api_token(&pool, created.record.id).await.unwrap());
+        assert!(!revoke_api_token(&pool, created.record.id).await.unwrap());
+        assert_eq!(
+            authenticate_api_token(&pool, &created.token).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_and_unknown_tokens_are_rejected() {
+        let pool = test_support::pool().await;
+        let created = create_api_token(&pool, &request(vec![TokenScope::Read], Some(1)))
+            .await
+            .unwrap();
+        assert!(created.record.expires_at.is_some());
+
+        sqlx::query("UPDATE forge_api_tokens SET expires_at = datetime('now', '-1 minute')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            authenticate_api_token(&pool, &created.token).await.unwrap(),
+            None
+        );
+        assert_eq!(
+            authenticate_api_token(&pool, "forge_pat_unknown")
+                .await
+                .unwrap(),
+            None
+        );
+
+        let stored: String = sqlx::query_scalar("SELECT token_hash FROM forge_api_tokens")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_ne!(stored, created.token);
+    }
+
+    #[test]
+    fn requests_need_a_name_and_scopes() {
+        assert!(request(vec![], None).validate().is_err());
+        assert!(request(vec![TokenScope::Read], Some(0)).validate().is_err());
+        assert!(
+            request(vec![TokenScope::Admin], Some(90))
+                .validate()
+                .is_ok()
+        );
+    }
+}
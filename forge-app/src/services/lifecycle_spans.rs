@@ -0,0 +1,189 @@
+//! Lifecycle Spans from Background Work
+//!
+//! Execution processes run inside the upstream deployment, so their phases of the
+//! attempt's trace (see [`crate::telemetry`]) are recorded from the database once
+//! they finish: the deployment's event stream says when to look. Omni deliveries
+//! are recorded by the worker pass that made them.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use forge_core_server::DeploymentImpl;
+use forge_core_utils::log_msg::LogMsg;
+use sqlx::{Row, SqlitePool};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use super::{OmniQueueStep, PendingNotification, touches_execution_processes};
+use crate::telemetry::{self, Phase};
+
+/// Record every execution process that finishes from now on
+pub(super) fn spawn_execution_span_export(deployment: &DeploymentImpl, pool: SqlitePool) {
+    let mut events = deployment.events().msg_store().get_receiver();
+
+    tokio::spawn(async move {
+        let mut watermark: f64 = match sqlx::query_scalar("SELECT julianday('now')")
+            .fetch_one(&pool)
+            .await
+        {
+            Ok(now) => now,
+            Err(e) => {
+                tracing::warn!("Execution spans disabled, cannot read the clock: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            match events.recv().await {
+                Ok(LogMsg::JsonPatch(patch)) if touches_execution_processes(&patch) => {}
+                Ok(_) => continue,
+                // Missed events may include completions; look anyway
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+            match record_finished_processes(&pool, watermark).await {
+                Ok(latest) => watermark = latest,
+                Err(e) => tracing::warn!("Failed to record execution spans: {}", e),
+            }
+        }
+    });
+}
+
+/// Record processes that completed after `watermark` (a julian day), returning the
+/// new watermark
+async fn record_finished_processes(pool: &SqlitePool, watermark: f64) -> Result<f64> {
+    let rows = sqlx::query(
+        r#"SELECT ep.id,
+                  ep.task_attempt_id,
+                  ep.run_reason,
+                  ep.status,
+                  ep.exit_code,
+                  ep.started_at,
+                  ep.completed_at,
+                  julianday(ep.completed_at) AS completed_day,
+                  (SELECT COUNT(*)
+                     FROM execution_processes prev
+                    WHERE prev.task_attempt_id = ep.task_attempt_id
+                      AND prev.run_reason = 'codingagent'
+                      AND prev.created_at < ep.created_at) AS earlier_agent_runs
+             FROM execution_processes ep
+            WHERE julianday(ep.completed_at) > ?
+              AND ep.run_reason != 'devserver'
+            ORDER BY ep.completed_at"#,
+    )
+    .bind(watermark)
+    .fetch_all(pool)
+    .await?;
+
+    let mut latest = watermark;
+    for row in rows {
+        latest = latest.max(row.try_get("completed_day")?);
+
+        let run_reason: String = row.try_get("run_reason")?;
+        let name = match run_reason.as_str() {
+            "setupscript" => "setup_script",
+            "cleanupscript" => "cleanup_script",
+            "codingagent" if row.try_get::<i64, _>("earlier_agent_runs")? > 0 => "follow_up",
+            "codingagent" => "coding_agent",
+            _ => continue,
+        };
+        let status: String = row.try_get("status")?;
+        let exit_code: Option<i64> = row.try_get("exit_code")?;
+        let process_id: Uuid = row.try_get("id")?;
+
+        let mut phase = Phase::new(
+            name,
+            row.try_get::<DateTime<Utc>, _>("started_at")?,
+            row.try_get::<DateTime<Utc>, _>("completed_at")?,
+        )
+        .attribute("forge.execution_process_id", process_id.to_string())
+        .attribute("forge.status", status.clone());
+        if let Some(exit_code) = exit_code {
+            phase = phase.attribute("process.exit_code", exit_code);
+        }
+        if status != "completed" {
+            phase = phase.failed(format!("{run_reason} {status}"));
+        }
+        phase.record(row.try_get("task_attempt_id")?);
+    }
+
+    Ok(latest)
+}
+
+/// Record one Omni delivery pass as the attempt's `notification` phase
+pub(super) fn record_notification(
+    row: &PendingNotification,
+    started_at: DateTime<Utc>,
+    step: &OmniQueueStep,
+) {
+    if !telemetry::enabled() {
+        return;
+    }
+    let Some(task_attempt_id) = row
+        .metadata
+        .as_deref()
+        .and_then(|metadata| serde_json::from_str::<serde_json::Value>(metadata).ok())
+        .and_then(|metadata| metadata["task_attempt_id"].as_str()?.parse::<Uuid>().ok())
+    else {
+        return;
+    };
+
+    let phase = Phase::new("notification", started_at, Utc::now())
+        .attribute("forge.notification_type", row.notification_type.clone())
+        .attribute("forge.delivery_attempt", row.attempts + 1);
+    let phase = match step {
+        OmniQueueStep::Idle => return,
+        OmniQueueStep::Sent => phase.attribute("forge.outcome", "sent"),
+        OmniQueueStep::Skipped => phase.attribute("forge.outcome", "skipped"),
+        OmniQueueStep::PartiallySent { error } => phase
+            .attribute("forge.outcome", "partially_sent")
+            .failed(error.clone()),
+        OmniQueueStep::Failed { error } => phase
+            .attribute("forge.outcome", "failed")
+            .failed(error.clone()),
+    };
+    phase.record(task_attempt_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support;
+
+    #[tokio::test]
+    async fn finished_processes_advance_the_watermark_once() {
+        let pool = test_support::pool().await;
+        let attempt_id = test_support::seed_attempt(&pool).await.attempt_id;
+
+        let watermark: f64 = sqlx::query_scalar("SELECT julianday('now', '-1 minute')")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        for (run_reason, status, finished) in [
+            ("setupscript", "completed", "-5 minutes"),
+            ("codingagent", "completed", "-20 seconds"),
+            ("codingagent", "failed", "-10 seconds"),
+            ("devserver", "killed", "-5 seconds"),
+        ] {
+            sqlx::query(
+                "INSERT INTO execution_processes
+                     (id, task_attempt_id, run_reason, executor_action, status, started_at, completed_at)
+                 VALUES (?, ?, ?, '{}', ?, datetime('now', '-10 minutes'), datetime('now', ?))",
+            )
+            .bind(Uuid::new_v4())
+            .bind(attempt_id)
+            .bind(run_reason)
+            .bind(status)
+            .bind(finished)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let latest = record_finished_processes(&pool, watermark).await.unwrap();
+        assert!(latest > watermark);
+        assert_eq!(
+            record_finished_processes(&pool, latest).await.unwrap(),
+            latest
+        );
+    }
+}
@@ -3,7 +3,16 @@
 //! Service composition layer that wraps upstream services with forge extensions.
 //! Provides unified access to both upstream functionality and forge-specific features.
 
+pub mod api_tokens;
+mod lifecycle_spans;
+pub mod notification_events;
+pub mod notification_history;
 mod notification_hook;
+pub mod notification_sinks;
+pub mod notification_templates;
+mod omni_worker;
+mod profile_watch;
+pub mod schema;
 
 use std::{path::Path, sync::Arc};
 
@@ -14,15 +23,33 @@ use forge_core_server::DeploymentImpl;
 // Import forge extension services from forge-core-services
 use forge_core_services::services::forge_config::ForgeConfigService;
 use forge_core_services::services::omni::{OmniConfig, OmniService};
+use forge_core_utils::log_msg::LogMsg;
+use rand::Rng;
 use serde::Deserialize;
 use serde_json::json;
 use sqlx::{Row, SqlitePool};
 use tokio::{
-    sync::RwLock,
+    sync::{RwLock, broadcast},
     time::{Duration, sleep},
 };
+use tracing::Instrument;
 use uuid::Uuid;
 
+use self::{
+    notification_events::NotificationEvent,
+    notification_sinks::{
+        NotificationMessage, NotificationSink, OmniSink, build_sink,
+        effective_notification_settings,
+    },
+    notification_templates::{
+        NotificationTemplates, load_notification_context, render_notification,
+    },
+};
+pub use self::{
+    omni_worker::{CircuitState, OmniWorkerHandle, OmniWorkerStatus},
+    profile_watch::{ProfileWatchStatus, ProfileWatches},
+};
+
 /// Main forge services container
 #[derive(Clone)]
 pub struct ForgeServices {
@@ -30,6 +57,10 @@ pub struct ForgeServices {
     pub omni: Arc<RwLock<OmniService>>,
     pub config: Arc<ForgeConfigService>,
     pub pool: SqlitePool,
+    pub omni_worker: OmniWorkerHandle,
+    /// Upstream PR monitor loop; finished means it stopped
+    pub pr_monitor: Arc<tokio::task::JoinHandle<()>>,
+    profile_watches: ProfileWatches,
 }
 
 impl ForgeServices {
@@ -42,7 +73,7 @@ impl ForgeServices {
         deployment.update_sentry_scope().await?;
         deployment.cleanup_orphan_executions().await?;
         deployment.backfill_before_head_commits().await?;
-        deployment.spawn_pr_monitor_service().await;
+        let pr_monitor = Arc::new(deployment.spawn_pr_monitor_service().await);
 
         let deployment_for_cache = deployment.clone();
         tokio::spawn(async move {
@@ -75,17 +106,40 @@ impl ForgeServices {
             "Loaded forge extension settings from auxiliary schema"
         );
 
+        // Add forge-owned columns/indexes the background workers rely on
+        schema::ensure_forge_schema(&pool).await?;
+
         // Install SQLite trigger for Omni notifications when tasks complete
         notification_hook::install_notification_trigger(&pool).await?;
 
+        // Rows left in 'processing' by a previous run can never finish; requeue them
+        let reclaimed = reclaim_expired_omni_leases(&pool, Duration::ZERO).await?;
+        if reclaimed > 0 {
+            tracing::warn!(
+                reclaimed,
+                "Requeued Omni notifications stranded by a previous shutdown"
+            );
+        }
+
         // Spawn background worker that processes queued Omni notifications
-        spawn_omni_notification_worker(pool.clone(), config.clone());
+        let omni_worker = OmniWorkerHandle::spawn(pool.clone(), config.clone());
+        spawn_omni_lease_reaper(pool.clone());
+        if let Some(retention) = notification_history::notification_retention_from_env() {
+            notification_history::spawn_notification_retention(pool.clone(), retention);
+        }
+        spawn_execution_wakeup_bridge(&deployment, omni_worker.clone());
+        if crate::telemetry::enabled() {
+            lifecycle_spans::spawn_execution_span_export(&deployment, pool.clone());
+        }
 
         Ok(Self {
             deployment,
             omni,
             config,
             pool,
+            omni_worker,
+            pr_monitor,
+            profile_watches: ProfileWatches::default(),
         })
     }
 
@@ -120,10 +174,18 @@ impl ForgeServices {
         workspace_root: &Path,
     ) -> Result<forge_core_executors::profile::ExecutorConfigs> {
         // Use the profile cache manager from deployment (with hot-reload)
-        self.deployment
+        let configs = self
+            .deployment
             .profile_cache()
             .get_profiles(workspace_root)
-            .await
+            .await?;
+        self.profile_watches.track(workspace_root);
+        Ok(configs)
+    }
+
+    /// Watches on the `.genie` folders of the workspaces whose profiles are loaded
+    pub fn profile_watches(&self) -> &ProfileWatches {
+        &self.profile_watches
     }
 
     /// Ensure a project's executor profiles are available in the cache.
@@ -194,7 +256,7 @@ impl ForgeServices {
             }
 
             tracing::info!(
-                "📁 Loading .genie profiles for project: {} ({})",
+                "Loading .genie profiles for project: {} ({})",
                 project.name,
                 project.git_repo_path.display()
             );
@@ -218,7 +280,7 @@ impl ForgeServices {
                         .await;
 
                     tracing::info!(
-                        "✅ Loaded {} profile variants for project: {} (registered project_id: {})",
+                        "Loaded {} profile variants for project: {} (registered project_id: {})",
                         variant_count,
                         project.name,
                         project.id
@@ -229,7 +291,7 @@ impl ForgeServices {
                 }
                 Err(e) => {
                     tracing::warn!(
-                        "⚠️  Failed to load .genie profiles for project '{}': {}",
+                        "Failed to load .genie profiles for project '{}': {}",
                         project.name,
                         e
                     );
@@ -240,7 +302,7 @@ impl ForgeServices {
 
         if loaded_count > 0 {
             tracing::info!(
-                "🎉 Successfully loaded .genie profiles for {}/{} projects ({} total variants)",
+                "Loaded .genie profiles for {}/{} projects ({} total variants)",
                 loaded_count,
                 project_count,
                 total_variants
@@ -253,6 +315,14 @@ impl ForgeServices {
     }
 }
 
+/// Bring the forge-owned schema up to date on a freshly migrated upstream database:
+/// legacy column backfills, forge tables/indexes and the notification trigger
+pub async fn migrate_database(pool: &SqlitePool) -> Result<()> {
+    ensure_legacy_base_branch_column(pool).await?;
+    schema::ensure_forge_schema(pool).await?;
+    notification_hook::install_notification_trigger(pool).await
+}
+
 /// Backfill base_branch column for legacy Vibe Kanban databases
 async fn ensure_legacy_base_branch_column(pool: &SqlitePool) -> Result<()> {
     let has_base_branch = sqlx::query_scalar::<_, i64>(
@@ -303,125 +373,322 @@ async fn ensure_legacy_base_branch_column(pool: &SqlitePool) -> Result<()> {
     Ok(())
 }
 
-fn spawn_omni_notification_worker(pool: SqlitePool, config: Arc<ForgeConfigService>) {
-    tokio::spawn(async move {
-        let mut consecutive_failures = 0u32;
-        const MAX_CONSECUTIVE_FAILURES: u32 = 10;
+/// Wake the Omni worker whenever the deployment reports an execution process change.
+///
+/// The notification trigger queues a row in the same transaction that finishes
+/// the process, so the upstream event stream doubles as a "queue changed" signal.
+fn spawn_execution_wakeup_bridge(deployment: &DeploymentImpl, omni_worker: OmniWorkerHandle) {
+    bridge_execution_events(deployment.events().msg_store().get_receiver(), omni_worker);
+}
 
+fn bridge_execution_events(mut events: broadcast::Receiver<LogMsg>, omni_worker: OmniWorkerHandle) {
+    tokio::spawn(async move {
         loop {
-            match process_next_omni_notification(&pool, &config).await {
-                Ok(true) => {
-                    // Processed at least one item, reset failure counter
-                    consecutive_failures = 0;
-                    continue;
+            match events.recv().await {
+                Ok(LogMsg::JsonPatch(patch)) if touches_execution_processes(&patch) => {
+                    omni_worker.wake();
                 }
-                Ok(false) => {
-                    // Queue empty → reset counter and short backoff
-                    consecutive_failures = 0;
-                    sleep(Duration::from_secs(10)).await;
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    // Missed events may include completions; check the queue anyway
+                    tracing::debug!(skipped, "Execution event bridge lagged");
+                    omni_worker.wake();
                 }
-                Err(err) => {
-                    consecutive_failures += 1;
-                    tracing::error!(
-                        consecutive_failures = consecutive_failures,
-                        "Omni notification worker error: {err:?}"
+                Err(broadcast::error::RecvError::Closed) => {
+                    tracing::warn!(
+                        "Deployment event stream closed; Omni worker falls back to polling"
                     );
-
-                    if consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
-                        tracing::error!(
-                            "Omni worker disabled after {} consecutive failures",
-                            consecutive_failures
-                        );
-                        break;
-                    }
-
-                    sleep(Duration::from_secs(15)).await;
+                    break;
                 }
             }
         }
     });
 }
 
+fn touches_execution_processes(patch: &json_patch::Patch) -> bool {
+    serde_json::to_string(patch).is_ok_and(|raw| raw.contains("\"/execution_processes/"))
+}
+
+/// Maximum delivery attempts before a notification is moved to `dead_letter`
+const OMNI_MAX_ATTEMPTS: i64 = 6;
+/// Delay before the first retry; doubles on every subsequent failure
+const OMNI_RETRY_BASE_DELAY: Duration = Duration::from_secs(30);
+/// Upper bound for the retry delay
+const OMNI_RETRY_MAX_DELAY: Duration = Duration::from_secs(30 * 60);
+/// How long a claimed notification may stay in `processing` before it is reclaimed.
+/// Kept well above `SINK_DELIVERY_TIMEOUT` so a hung sink fails normally instead of
+/// the row being reclaimed while still in flight.
+const OMNI_LEASE_DURATION: Duration = Duration::from_secs(5 * 60);
+/// How often the reaper looks for expired leases
+const OMNI_LEASE_REAPER_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Outcome of a single pass over the Omni queue
+#[derive(Debug, PartialEq, Eq)]
+enum OmniQueueStep {
+    /// Nothing was due
+    Idle,
+    Sent,
+    /// At least one sink accepted the notification; the rest are retried later
+    PartiallySent {
+        error: String,
+    },
+    Skipped,
+    /// Delivery failed and the row was rescheduled or dead-lettered
+    Failed {
+        error: String,
+    },
+}
+
 async fn process_next_omni_notification(
     pool: &SqlitePool,
     config: &ForgeConfigService,
-) -> Result<bool> {
-    let pending_row = sqlx::query(
-        r#"SELECT id,
-                  metadata
-             FROM forge_omni_notifications
-            WHERE status = 'pending'
-            ORDER BY created_at
-            LIMIT 1"#,
-    )
-    .fetch_optional(pool)
-    .await?;
-
-    let Some(row) = pending_row else {
-        return Ok(false);
-    };
-
-    let row = PendingNotification {
-        id: row.try_get::<String, _>("id")?,
-        metadata: row.try_get::<Option<String>, _>("metadata")?,
+) -> Result<OmniQueueStep> {
+    let Some(row) = claim_next_omni_notification(pool).await? else {
+        return Ok(OmniQueueStep::Idle);
     };
+    let span = delivery_span(&row);
+    deliver_claimed_notification(pool, config, &row)
+        .instrument(span)
+        .await
+}
 
-    // Mark as processing to avoid multiple workers picking it up
-    let claimed = sqlx::query(
-        "UPDATE forge_omni_notifications SET status = 'processing' WHERE id = ? AND status = 'pending'",
+/// Span of one delivery, carrying the ID of the request that queued the row, if any
+fn delivery_span(row: &PendingNotification) -> tracing::Span {
+    let request_id = row
+        .metadata
+        .as_deref()
+        .and_then(|raw| serde_json::from_str::<serde_json::Value>(raw).ok())
+        .and_then(|metadata| Some(metadata.get("request_id")?.as_str()?.to_string()));
+    tracing::info_span!(
+        "omni_delivery",
+        notification_id = %row.id,
+        request_id = request_id.as_deref(),
     )
-    .bind(&row.id)
-    .execute(pool)
-    .await?;
+}
 
-    if claimed.rows_affected() == 0 {
-        // Another worker grabbed it first; treat as processed and continue
-        return Ok(true);
-    }
+async fn deliver_claimed_notification(
+    pool: &SqlitePool,
+    config: &ForgeConfigService,
+    row: &PendingNotification,
+) -> Result<OmniQueueStep> {
+    let started_at = chrono::Utc::now();
 
-    match handle_omni_notification(pool, config, &row).await {
+    let step = match handle_omni_notification(pool, config, row).await {
         Ok(OmniQueueAction::Sent { message }) => {
             sqlx::query(
-                "UPDATE forge_omni_notifications SET status = 'sent', sent_at = CURRENT_TIMESTAMP, message = ? WHERE id = ?",
+                "UPDATE forge_omni_notifications SET status = 'sent', sent_at = CURRENT_TIMESTAMP, claimed_at = NULL, message = ? WHERE id = ?",
             )
             .bind(&message)
             .bind(&row.id)
             .execute(pool)
             .await?;
+            OmniQueueStep::Sent
+        }
+        Ok(OmniQueueAction::PartiallySent { error }) => {
+            schedule_omni_retry(pool, row, &error).await?;
+            OmniQueueStep::PartiallySent { error }
         }
         Ok(OmniQueueAction::Skipped { reason }) => {
             sqlx::query(
-                "UPDATE forge_omni_notifications SET status = 'skipped', error_message = ? WHERE id = ?",
+                "UPDATE forge_omni_notifications SET status = 'skipped', claimed_at = NULL, error_message = ? WHERE id = ?",
             )
             .bind(&reason)
             .bind(&row.id)
             .execute(pool)
             .await?;
+            OmniQueueStep::Skipped
         }
         Err(err) => {
-            sqlx::query(
-                "UPDATE forge_omni_notifications SET status = 'failed', error_message = ? WHERE id = ?",
-            )
-            .bind(err.to_string())
-            .bind(&row.id)
-            .execute(pool)
-            .await?;
+            let error = err.to_string();
+            schedule_omni_retry(pool, row, &error).await?;
+            OmniQueueStep::Failed { error }
         }
+    };
+
+    lifecycle_spans::record_notification(row, started_at, &step);
+    Ok(step)
+}
+
+/// Atomically claim the oldest due notification by taking a lease on it.
+///
+/// The lease (`status = 'processing'` plus `claimed_at`) is released by the final
+/// status update; if the process dies first, `reclaim_expired_omni_leases` hands
+/// the row back to the queue.
+async fn claim_next_omni_notification(pool: &SqlitePool) -> Result<Option<PendingNotification>> {
+    let claimed = sqlx::query(
+        r#"UPDATE forge_omni_notifications
+              SET status = 'processing',
+                  claimed_at = datetime('now')
+            WHERE id = (
+                    SELECT id
+                      FROM forge_omni_notifications
+                     WHERE status = 'pending'
+                       AND (next_attempt_at IS NULL OR next_attempt_at <= datetime('now'))
+                     ORDER BY created_at
+                     LIMIT 1
+                  )
+              AND status = 'pending'
+        RETURNING id,
+                  notification_type,
+                  metadata,
+                  attempts"#,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(row) = claimed else {
+        return Ok(None);
+    };
+
+    Ok(Some(PendingNotification {
+        id: row.try_get::<String, _>("id")?,
+        notification_type: row.try_get::<String, _>("notification_type")?,
+        metadata: row.try_get::<Option<String>, _>("metadata")?,
+        attempts: row.try_get::<i64, _>("attempts")?,
+    }))
+}
+
+/// Return notifications whose lease is older than `lease` to the queue.
+///
+/// A stranded lease means the worker died mid-send, so it counts as a failed
+/// attempt; rows that run out of attempts this way go to `dead_letter` instead of
+/// crash-looping the worker. Pass `Duration::ZERO` at startup, when no worker can
+/// legitimately hold a lease yet. Returns the number of rows reclaimed.
+async fn reclaim_expired_omni_leases(pool: &SqlitePool, lease: Duration) -> Result<u64> {
+    let result = sqlx::query(
+        r#"UPDATE forge_omni_notifications
+              SET status = CASE WHEN attempts + 1 >= ? THEN 'dead_letter' ELSE 'pending' END,
+                  attempts = attempts + 1,
+                  next_attempt_at = NULL,
+                  claimed_at = NULL,
+                  error_message = 'Lease expired before delivery finished (worker crashed or restarted)'
+            WHERE status = 'processing'
+              AND (claimed_at IS NULL OR claimed_at <= datetime('now', ?))"#,
+    )
+    .bind(OMNI_MAX_ATTEMPTS)
+    .bind(format!("-{} seconds", lease.as_secs()))
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+fn spawn_omni_lease_reaper(pool: SqlitePool) {
+    tokio::spawn(async move {
+        loop {
+            sleep(OMNI_LEASE_REAPER_INTERVAL).await;
+            match reclaim_expired_omni_leases(&pool, OMNI_LEASE_DURATION).await {
+                Ok(0) => {}
+                Ok(reclaimed) => {
+                    tracing::warn!(reclaimed, "Reclaimed expired Omni notification leases");
+                }
+                Err(err) => {
+                    tracing::error!("Omni lease reaper error: {err:?}");
+                }
+            }
+        }
+    });
+}
+
+/// Time until the earliest scheduled retry is due, if any row is backing off
+async fn next_omni_retry_delay(pool: &SqlitePool) -> Result<Option<Duration>> {
+    let seconds: Option<i64> = sqlx::query_scalar(
+        r#"SELECT MAX(0, CAST((julianday(MIN(next_attempt_at)) - julianday('now')) * 86400 AS INTEGER) + 1)
+             FROM forge_omni_notifications
+            WHERE status = 'pending'
+              AND next_attempt_at IS NOT NULL"#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(seconds.map(|secs| Duration::from_secs(secs as u64)))
+}
+
+/// Record a failed delivery attempt and either reschedule the row with
+/// exponential backoff or move it to the dead-letter state.
+async fn schedule_omni_retry(
+    pool: &SqlitePool,
+    row: &PendingNotification,
+    error: &str,
+) -> Result<()> {
+    let attempts = row.attempts + 1;
+
+    if attempts >= OMNI_MAX_ATTEMPTS {
+        tracing::warn!(
+            notification_id = %row.id,
+            attempts,
+            "Omni notification exhausted retries, moving to dead letter"
+        );
+        sqlx::query(
+            "UPDATE forge_omni_notifications
+                SET status = 'dead_letter', attempts = ?, next_attempt_at = NULL,
+                    claimed_at = NULL, error_message = ?
+              WHERE id = ?",
+        )
+        .bind(attempts)
+        .bind(error)
+        .bind(&row.id)
+        .execute(pool)
+        .await?;
+        return Ok(());
     }
 
-    Ok(true)
+    let delay = omni_retry_delay(attempts as u32);
+    tracing::info!(
+        notification_id = %row.id,
+        attempts,
+        retry_in_secs = delay.as_secs(),
+        "Omni notification failed, scheduling retry"
+    );
+    sqlx::query(
+        "UPDATE forge_omni_notifications
+            SET status = 'pending', attempts = ?, next_attempt_at = datetime('now', ?),
+                claimed_at = NULL, error_message = ?
+          WHERE id = ?",
+    )
+    .bind(attempts)
+    .bind(format!("+{} seconds", delay.as_secs()))
+    .bind(error)
+    .bind(&row.id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Backoff before retry number `attempt` (1-based): exponential growth capped at
+/// `OMNI_RETRY_MAX_DELAY`, with "equal jitter" so retries from a burst of
+/// failures don't hit Omni at the same instant.
+fn omni_retry_delay(attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    let capped = OMNI_RETRY_BASE_DELAY
+        .saturating_mul(1u32 << exponent)
+        .min(OMNI_RETRY_MAX_DELAY);
+    let half = capped / 2;
+    let jitter_ms = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+    half + Duration::from_millis(jitter_ms)
 }
 
 #[derive(Debug)]
 enum OmniQueueAction {
-    Sent { message: String },
-    Skipped { reason: String },
+    Sent {
+        message: String,
+    },
+    /// Some sinks accepted the notification while others failed
+    PartiallySent {
+        error: String,
+    },
+    Skipped {
+        reason: String,
+    },
 }
 
 #[derive(Debug)]
 struct PendingNotification {
     id: String,
+    notification_type: String,
     metadata: Option<String>,
+    attempts: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -431,6 +698,9 @@ struct OmniNotificationMetadata {
     executor: Option<String>,
     branch: Option<String>,
     project_id: Option<String>,
+    exit_code: Option<i64>,
+    run_reason: Option<String>,
+    details: Option<serde_json::Value>,
 }
 
 async fn handle_omni_notification(
@@ -453,189 +723,122 @@ async fn handle_omni_notification(
     let status = metadata
         .status
         .ok_or_else(|| anyhow!("metadata missing status"))?;
+    let Ok(event) = row.notification_type.parse::<NotificationEvent>() else {
+        return Ok(OmniQueueAction::Skipped {
+            reason: format!("unsupported notification type '{}'", row.notification_type),
+        });
+    };
 
-    let attempt_row = sqlx::query(
-        r#"SELECT
-                t.id         AS task_id,
-                t.title      AS title,
-                t.project_id AS project_id,
-                ta.branch    AS branch,
-                ta.executor  AS executor
-           FROM task_attempts ta
-           JOIN tasks t ON t.id = ta.task_id
-          WHERE ta.id = ?"#,
-    )
-    .bind(attempt_id)
-    .fetch_optional(pool)
-    .await?
-    .ok_or_else(|| anyhow!("task attempt not found for omni notification"))?;
+    let mut context = load_notification_context(pool, attempt_id, Some(&status))
+        .await
+        .context("failed to load task attempt for omni notification")?;
+    if let Some(branch) = metadata.branch {
+        context.branch = branch;
+    }
+    if let Some(executor) = metadata.executor {
+        context.executor = executor;
+    }
+    if metadata.exit_code.is_some() {
+        context.exit_code = metadata.exit_code;
+    }
+    context.event = event;
+    if let Some(details) = metadata.details {
+        context.details = details;
+    }
 
-    let project_id = if let Some(pid_str) = metadata.project_id {
-        Uuid::parse_str(&pid_str).with_context(|| format!("invalid project_id UUID: {pid_str}"))?
-    } else {
-        attempt_row
-            .try_get::<Uuid, _>("project_id")
-            .with_context(|| "missing project_id in database row")?
+    let project_id = match metadata.project_id {
+        Some(pid_str) => Uuid::parse_str(&pid_str)
+            .with_context(|| format!("invalid project_id UUID: {pid_str}"))?,
+        None => context.project_id,
     };
     let omni_config = config.effective_omni_config(Some(project_id)).await?;
+    let sink_settings = effective_notification_settings(pool, project_id).await?;
 
-    if !omni_config.enabled {
+    if !sink_settings.events.contains(&event) {
         return Ok(OmniQueueAction::Skipped {
-            reason: "Omni notifications disabled for project".into(),
+            reason: format!("event '{event}' not subscribed for project"),
         });
     }
 
-    let host = omni_config
-        .host
-        .as_deref()
-        .ok_or_else(|| anyhow!("Omni host not configured"))?;
-    if host.is_empty() {
-        return Err(anyhow!("Omni host configuration empty"));
-    }
-
-    let branch = metadata
-        .branch
-        .or_else(|| {
-            attempt_row
-                .try_get::<Option<String>, _>("branch")
-                .ok()
-                .flatten()
-        })
-        .unwrap_or_else(|| "unknown".to_string());
-    let executor = metadata.executor.unwrap_or_else(|| {
-        attempt_row
-            .try_get::<String, _>("executor")
-            .unwrap_or_else(|_| "unknown".into())
-    });
-
-    let title: String = attempt_row.try_get("title")?;
-    let task_id: Uuid = attempt_row.try_get("task_id")?;
-
-    let status_summary = format_status_summary(&status, &executor, &branch);
-    let task_url = format!(
-        "{}/projects/{}/tasks/{}",
-        omni_base_url(),
-        project_id,
-        task_id
-    );
-
-    tracing::info!(
-        "Attempting to send Omni notification for task '{}' with status '{}'",
-        title,
-        status_summary
-    );
-
-    let omni_service = OmniService::new(omni_config.clone());
-
-    match omni_service
-        .send_task_notification(&title, &status_summary, Some(&task_url))
-        .await
+    if sink_settings.coding_agent_only
+        && let Some(run_reason) = metadata.run_reason.as_deref()
+        && run_reason != "codingagent"
     {
-        Ok(()) => {
-            tracing::info!("Successfully sent Omni notification for task '{}'", title);
-            Ok(OmniQueueAction::Sent {
-                message: status_summary,
-            })
-        }
-        Err(e) => {
-            tracing::error!("Failed to send Omni notification: {}", e);
-            Err(e)
-        }
+        return Ok(OmniQueueAction::Skipped {
+            reason: format!("'{run_reason}' run skipped; project notifies coding agent runs only"),
+        });
     }
-}
 
-fn format_status_summary(status: &str, executor: &str, branch: &str) -> String {
-    match status {
-        "completed" => format!("✅ Execution completed\nBranch: {branch}\nExecutor: {executor}"),
-        "failed" => format!("❌ Execution failed\nBranch: {branch}\nExecutor: {executor}"),
-        "killed" => format!("🛑 Execution cancelled\nBranch: {branch}\nExecutor: {executor}"),
-        other => format!("{other}\nBranch: {branch}\nExecutor: {executor}"),
+    let http = reqwest::Client::new();
+    let mut sinks: Vec<Box<dyn NotificationSink>> = Vec::new();
+    if omni_config.enabled {
+        sinks.push(Box::new(OmniSink::new(omni_config)));
     }
-}
+    sinks.extend(
+        sink_settings
+            .sinks
+            .iter()
+            .filter(|sink| sink.enabled)
+            .map(|sink| build_sink(sink, &http)),
+    );
 
-fn omni_base_url() -> String {
-    use url::Url;
-
-    // Priority 1: Explicit PUBLIC_BASE_URL (for tunnels/production)
-    if let Ok(url_str) = std::env::var("PUBLIC_BASE_URL") {
-        // Validate URL format and scheme
-        match Url::parse(&url_str) {
-            Ok(parsed_url) => {
-                // Only allow http and https schemes
-                if parsed_url.scheme() == "http" || parsed_url.scheme() == "https" {
-                    return url_str.trim_end_matches('/').to_string();
-                } else {
-                    tracing::warn!(
-                        "PUBLIC_BASE_URL has invalid scheme '{}' (only http/https allowed), falling back to HOST/PORT",
-                        parsed_url.scheme()
-                    );
-                }
-            }
-            Err(e) => {
-                tracing::warn!(
-                    "PUBLIC_BASE_URL is not a valid URL ({}), falling back to HOST/PORT",
-                    e
-                );
-            }
-        }
+    if sinks.is_empty() {
+        return Ok(OmniQueueAction::Skipped {
+            reason: "Omni and notification sinks disabled for project".into(),
+        });
     }
 
-    // Priority 2: HOST/BACKEND_PORT env vars (for custom deployments)
-    let host = std::env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
-    let port = std::env::var("BACKEND_PORT")
-        .or_else(|_| std::env::var("PORT"))
-        .unwrap_or_else(|_| "8887".to_string());
-
-    // Sanitize host: only allow alphanumeric, dots, hyphens, and colons (for IPv6)
-    let sanitized_host = sanitize_hostname(&host);
-    if sanitized_host != host {
-        tracing::warn!(
-            "HOST env var contains invalid characters, sanitized '{}' -> '{}'",
-            host,
-            sanitized_host
-        );
-    }
+    let rendered = render_notification(&sink_settings.templates, &context).unwrap_or_else(|e| {
+        tracing::warn!("Falling back to default notification templates: {}", e);
+        render_notification(&NotificationTemplates::default(), &context)
+            .expect("built-in notification templates render")
+    });
 
-    // Sanitize port: only allow digits
-    let sanitized_port = sanitize_port(&port);
-    if sanitized_port != port {
-        tracing::warn!(
-            "PORT env var contains invalid characters, sanitized '{}' -> '{}'",
-            port,
-            sanitized_port
-        );
-    }
+    tracing::info!(
+        "Attempting to deliver '{}' notification for task '{}' with status '{}' to {} sink(s)",
+        event,
+        context.title,
+        context.status,
+        sinks.len()
+    );
 
-    format!("http://{sanitized_host}:{sanitized_port}")
-}
+    let message = NotificationMessage {
+        title: rendered.title,
+        body: rendered.body.clone(),
+        url: Some(context.url.clone()),
+        status: context.status.clone(),
+    };
+    let report = notification_sinks::dispatch(pool, &row.id, &sinks, &message).await?;
 
-/// Sanitize hostname to prevent injection attacks
-/// Allows: alphanumeric, dots, hyphens
-/// Also allows colons and square brackets only for IPv6 addresses (when wrapped in brackets or contains multiple colons)
-fn sanitize_hostname(host: &str) -> String {
-    // Check if this looks like an IPv6 address (contains [ or has multiple colons)
-    let is_ipv6 = host.starts_with('[') || host.matches(':').count() > 1;
-
-    if is_ipv6 {
-        // For IPv6, allow colons and square brackets
-        host.chars()
-            .filter(|c| {
-                c.is_alphanumeric() || *c == '.' || *c == '-' || *c == ':' || *c == '[' || *c == ']'
-            })
-            .collect()
+    if report.failures.is_empty() {
+        tracing::info!(
+            "Successfully delivered notification for task '{}'",
+            context.title
+        );
+        Ok(OmniQueueAction::Sent {
+            message: rendered.body,
+        })
+    } else if report.delivered > 0 {
+        Ok(OmniQueueAction::PartiallySent {
+            error: report.failure_summary(),
+        })
     } else {
-        // For regular hostnames, don't allow colons (prevents header injection)
-        host.chars()
-            .filter(|c| c.is_alphanumeric() || *c == '.' || *c == '-')
-            .collect()
+        let error = report.failure_summary();
+        tracing::error!("Failed to deliver notification: {}", error);
+        Err(anyhow!(error))
     }
 }
 
-/// Sanitize port to prevent injection attacks
-/// Allows: digits only
-fn sanitize_port(port: &str) -> String {
-    port.chars().filter(|c| c.is_ascii_digit()).collect()
+/// Base URL for links in notifications: the running server's public URL, or the one
+/// the environment and config file describe when no server config is installed
+pub(crate) fn omni_base_url() -> Result<String> {
+    match crate::config::ForgeServerConfig::active() {
+        Some(config) => Ok(config.public_url()),
+        None => Ok(crate::config::ForgeServerConfig::from_env()?.public_url()),
+    }
 }
 
+#[cfg(test)]
+pub(crate) mod test_support;
 #[cfg(test)]
 mod tests;
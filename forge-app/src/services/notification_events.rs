@@ -0,0 +1,385 @@
+//! Notification Events
+//!
+//! Lifecycle events a project can subscribe to. Events backed by database state
+//! (PR status, dev server crashes, tasks moving to review) are queued by SQLite
+//! triggers in `notification_hook`; events that only exist as API responses
+//! (approval requests, merge/rebase conflicts) are queued by router middleware
+//! through `queue_event_notification`.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use ts_rs_forge::TS;
+use uuid::Uuid;
+
+/// Serialized names double as `forge_omni_notifications.notification_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    ExecutionCompleted,
+    ApprovalPending,
+    PrOpened,
+    PrMerged,
+    PrClosed,
+    MergeConflict,
+    RebaseConflict,
+    DevServerCrashed,
+    TaskInReview,
+}
+
+impl NotificationEvent {
+    pub const ALL: [NotificationEvent; 9] = [
+        NotificationEvent::ExecutionCompleted,
+        NotificationEvent::ApprovalPending,
+        NotificationEvent::PrOpened,
+        NotificationEvent::PrMerged,
+        NotificationEvent::PrClosed,
+        NotificationEvent::MergeConflict,
+        NotificationEvent::RebaseConflict,
+        NotificationEvent::DevServerCrashed,
+        NotificationEvent::TaskInReview,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationEvent::ExecutionCompleted => "execution_completed",
+            NotificationEvent::ApprovalPending => "approval_pending",
+            NotificationEvent::PrOpened => "pr_opened",
+            NotificationEvent::PrMerged => "pr_merged",
+            NotificationEvent::PrClosed => "pr_closed",
+            NotificationEvent::MergeConflict => "merge_conflict",
+            NotificationEvent::RebaseConflict => "rebase_conflict",
+            NotificationEvent::DevServerCrashed => "dev_server_crashed",
+            NotificationEvent::TaskInReview => "task_in_review",
+        }
+    }
+
+    /// Subscriptions for projects that never chose any: execution completion only,
+    /// which is what forge notified about before events were configurable
+    pub fn default_subscriptions() -> Vec<NotificationEvent> {
+        vec![NotificationEvent::ExecutionCompleted]
+    }
+}
+
+impl fmt::Display for NotificationEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for NotificationEvent {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match NotificationEvent::ALL
+            .into_iter()
+            .find(|event| event.as_str() == value)
+        {
+            Some(event) => Ok(event),
+            None => bail!("unknown notification event '{value}'"),
+        }
+    }
+}
+
+/// Queue a notification for `event` on a task attempt.
+///
+/// The metadata has the same shape as the rows written by the SQLite triggers, with
+/// event-specific values under `details`. Returns false when the attempt does not exist.
+pub async fn queue_event_notification(
+    pool: &SqlitePool,
+    event: NotificationEvent,
+    task_attempt_id: Uuid,
+    status: &str,
+    details: Value,
+    request_id: Option<&str>,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"INSERT INTO forge_omni_notifications (
+               id, task_id, notification_type, recipient, message, status, metadata, created_at
+           )
+           SELECT
+               lower(hex(randomblob(16))),
+               t.id,
+               ?,
+               '',
+               '',
+               'pending',
+               json_object(
+                   'task_attempt_id', lower(hex(ta.id)),
+                   'status', ?,
+                   'executor', COALESCE(ta.executor, ''),
+                   'branch', COALESCE(ta.branch, ''),
+                   'project_id', lower(hex(t.project_id)),
+                   'details', json(?),
+                   'request_id', ?
+               ),
+               datetime('now')
+             FROM task_attempts ta
+             JOIN tasks t ON t.id = ta.task_id
+            WHERE ta.id = ?"#,
+    )
+    .bind(event.as_str())
+    .bind(status)
+    .bind(details.to_string())
+    .bind(request_id)
+    .bind(task_attempt_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{notification_hook, test_support};
+
+    async fn setup_attempt() -> (SqlitePool, Uuid, Uuid) {
+        let pool = test_support::pool().await;
+        notification_hook::install_notification_trigger(&pool)
+            .await
+            .unwrap();
+        let seeded = test_support::seed_attempt(&pool).await;
+        (pool, seeded.task_id, seeded.attempt_id)
+    }
+
+    async fn queued(pool: &SqlitePool) -> Vec<(String, Value)> {
+        sqlx::query_as::<_, (String, String)>(
+            "SELECT notification_type, metadata FROM forge_omni_notifications ORDER BY created_at, rowid",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(kind, metadata)| (kind, serde_json::from_str(&metadata).unwrap()))
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn queue_event_notification_records_details() {
+        let (pool, _task_id, attempt_id) = setup_attempt().await;
+
+        let queued_row = queue_event_notification(
+            &pool,
+            NotificationEvent::RebaseConflict,
+            attempt_id,
+            "conflict",
+            serde_json::json!({ "op": "rebase" }),
+            Some("req-1"),
+        )
+        .await
+        .unwrap();
+        assert!(queued_row);
+
+        let rows = queued(&pool).await;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].0, "rebase_conflict");
+        assert_eq!(rows[0].1["status"], "conflict");
+        assert_eq!(rows[0].1["details"]["op"], "rebase");
+        assert_eq!(rows[0].1["request_id"], "req-1");
+        assert_eq!(
+            rows[0].1["task_attempt_id"],
+            attempt_id.simple().to_string()
+        );
+
+        let missing = queue_event_notification(
+            &pool,
+            NotificationEvent::MergeConflict,
+            Uuid::new_v4(),
+            "conflict",
+            Value::Null,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(!missing);
+    }
+
+    #[tokio::test]
+    async fn triggers_queue_pr_lifecycle_events() {
+        let (pool, _task_id, attempt_id) = setup_attempt().await;
+        let merge_id = Uuid::new_v4();
+
+        sqlx::query(
+            "INSERT INTO merges (id, task_attempt_id, merge_type, pr_number, pr_url, pr_status, target_branch_name)
+             VALUES (?, ?, 'pr', 42, 'https://github.com/o/r/pull/42', 'open', 'main')",
+        )
+        .bind(merge_id)
+        .bind(attempt_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("UPDATE merges SET pr_status = 'merged' WHERE id = ?")
+            .bind(merge_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let rows = queued(&pool).await;
+        let kinds: Vec<_> = rows.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(kinds, vec!["pr_opened", "pr_merged"]);
+        assert_eq!(rows[1].1["details"]["pr_number"], 42);
+        assert_eq!(
+            rows[1].1["details"]["pr_url"],
+            "https://github.com/o/r/pull/42"
+        );
+    }
+
+    #[tokio::test]
+    async fn dev_server_crash_is_its_own_event() {
+        let (pool, _task_id, attempt_id) = setup_attempt().await;
+        let process_id = Uuid::new_v4();
+
+        sqlx::query(
+            "INSERT INTO execution_processes (id, task_attempt_id, run_reason, executor_action, status)
+             VALUES (?, ?, 'devserver', '{}', 'running')",
+        )
+        .bind(process_id)
+        .bind(attempt_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "UPDATE execution_processes SET status = 'failed', exit_code = 137 WHERE id = ?",
+        )
+        .bind(process_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let rows = queued(&pool).await;
+        assert_eq!(
+            rows.len(),
+            1,
+            "dev servers must not also queue execution_completed"
+        );
+        assert_eq!(rows[0].0, "dev_server_crashed");
+        assert_eq!(rows[0].1["exit_code"], 137);
+    }
+
+    #[tokio::test]
+    async fn task_moving_to_review_queues_event_for_latest_attempt() {
+        let (pool, task_id, attempt_id) = setup_attempt().await;
+
+        sqlx::query("UPDATE tasks SET status = 'inreview' WHERE id = ?")
+            .bind(task_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        // Re-saving a task that is already in review is not a transition
+        sqlx::query("UPDATE tasks SET status = 'inreview' WHERE id = ?")
+            .bind(task_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let rows = queued(&pool).await;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].0, "task_in_review");
+        assert_eq!(
+            rows[0].1["task_attempt_id"],
+            attempt_id.simple().to_string()
+        );
+    }
+
+    #[test]
+    fn event_names_round_trip() {
+        for event in NotificationEvent::ALL {
+            assert_eq!(event.as_str().parse::<NotificationEvent>().unwrap(), event);
+            assert_eq!(
+                serde_json::to_value(event).unwrap(),
+                Value::String(event.to_string())
+            );
+        }
+        assert!("execution_started".parse::<NotificationEvent>().is_err());
+    }
+
+    #[tokio::test]
+    async fn events_are_delivered_only_when_subscribed() {
+        use forge_core_services::services::forge_config::ForgeConfigService;
+        use httpmock::prelude::*;
+
+        use crate::services::{
+            OmniQueueStep,
+            notification_sinks::{
+                NotificationSettings, NotificationSinkConfig, NotificationSinkKind,
+                save_notification_settings,
+            },
+            process_next_omni_notification,
+        };
+
+        let (pool, _task_id, attempt_id) = setup_attempt().await;
+        let project_id: Uuid = sqlx::query_scalar(
+            "SELECT t.project_id FROM task_attempts ta JOIN tasks t ON t.id = ta.task_id WHERE ta.id = ?",
+        )
+        .bind(attempt_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let config = ForgeConfigService::new(pool.clone());
+
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/hooks/forge")
+                .body_contains("Merge hit conflicts");
+            then.status(200);
+        });
+        let mut settings = NotificationSettings {
+            sinks: vec![NotificationSinkConfig {
+                id: "project-webhook".into(),
+                enabled: true,
+                sink: NotificationSinkKind::Webhook {
+                    url: server.url("/hooks/forge"),
+                    secret: None,
+                },
+                has_secret: false,
+            }],
+            ..Default::default()
+        };
+        save_notification_settings(&pool, Some(project_id), &settings)
+            .await
+            .unwrap();
+
+        let conflict = || {
+            queue_event_notification(
+                &pool,
+                NotificationEvent::MergeConflict,
+                attempt_id,
+                "conflict",
+                serde_json::json!({ "op": "merge" }),
+                None,
+            )
+        };
+
+        conflict().await.unwrap();
+        let step = process_next_omni_notification(&pool, &config)
+            .await
+            .unwrap();
+        assert_eq!(step, OmniQueueStep::Skipped);
+        let reason: Option<String> = sqlx::query_scalar(
+            "SELECT error_message FROM forge_omni_notifications WHERE status = 'skipped'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert!(reason.unwrap_or_default().contains("not subscribed"));
+
+        settings.events.push(NotificationEvent::MergeConflict);
+        save_notification_settings(&pool, Some(project_id), &settings)
+            .await
+            .unwrap();
+
+        conflict().await.unwrap();
+        let step = process_next_omni_notification(&pool, &config)
+            .await
+            .unwrap();
+        assert_eq!(step, OmniQueueStep::Sent);
+        mock.assert_async().await;
+    }
+}
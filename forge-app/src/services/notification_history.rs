@@ -0,0 +1,500 @@
+//! Notification History
+//!
+//! Management access to the `forge_omni_notifications` queue: filtered listing,
+//! single-row retry, bulk purge and the retention sweep that keeps delivered rows
+//! from accumulating forever. Rows that are still queued (`pending`/`processing`)
+//! are never deleted from here.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+use tokio::time::sleep;
+use ts_rs_forge::TS;
+use uuid::Uuid;
+
+/// Rows that left the queue; only these can be purged
+const FINISHED_STATUS_SQL: &str = "status IN ('sent', 'skipped', 'dead_letter')";
+/// Metadata is written by triggers with `json_object`, but old rows may predate that
+const PROJECT_ID_SQL: &str =
+    "CASE WHEN json_valid(metadata) THEN json_extract(metadata, '$.project_id') END";
+const TASK_ATTEMPT_ID_SQL: &str =
+    "CASE WHEN json_valid(metadata) THEN json_extract(metadata, '$.task_attempt_id') END";
+
+pub const DEFAULT_LIST_LIMIT: u32 = 100;
+pub const MAX_LIST_LIMIT: u32 = 500;
+/// Default age after which sent and skipped rows are pruned
+pub const DEFAULT_RETENTION_DAYS: u64 = 30;
+const RETENTION_SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Filters for listing and purging notifications. Dates are RFC 3339 and compared
+/// against `created_at`; `limit`/`offset` only apply to listing.
+#[derive(Debug, Clone, Default, Deserialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct NotificationFilter {
+    pub project_id: Option<Uuid>,
+    pub status: Option<String>,
+    pub notification_type: Option<String>,
+    #[ts(type = "string | null")]
+    pub since: Option<DateTime<Utc>>,
+    #[ts(type = "string | null")]
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// One row of the notification queue
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct NotificationRecord {
+    pub id: String,
+    pub notification_type: String,
+    pub status: String,
+    pub project_id: Option<String>,
+    pub task_id: Option<Uuid>,
+    pub task_attempt_id: Option<String>,
+    pub execution_process_id: Option<String>,
+    pub message: String,
+    pub error_message: Option<String>,
+    pub attempts: u32,
+    pub metadata: Value,
+    pub created_at: Option<String>,
+    pub sent_at: Option<String>,
+    pub next_attempt_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct NotificationPurgeResult {
+    pub deleted: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetryOutcome {
+    Requeued,
+    /// The row exists but is still queued or was already sent
+    NotRetryable {
+        status: String,
+    },
+    NotFound,
+}
+
+pub async fn list_notifications(
+    pool: &SqlitePool,
+    filter: &NotificationFilter,
+) -> Result<Vec<NotificationRecord>> {
+    let mut query = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT id, notification_type, status, task_id, message, error_message, attempts,
+                metadata, execution_process_id, created_at, sent_at, next_attempt_at,
+                {PROJECT_ID_SQL} AS project_id,
+                {TASK_ATTEMPT_ID_SQL} AS task_attempt_id
+           FROM forge_omni_notifications
+          WHERE 1 = 1"
+    ));
+    push_filter(&mut query, filter);
+    query
+        .push(" ORDER BY created_at DESC, rowid DESC LIMIT ")
+        .push_bind(
+            filter
+                .limit
+                .unwrap_or(DEFAULT_LIST_LIMIT)
+                .min(MAX_LIST_LIMIT),
+        )
+        .push(" OFFSET ")
+        .push_bind(filter.offset.unwrap_or(0));
+
+    let rows = query.build().fetch_all(pool).await?;
+    rows.into_iter()
+        .map(|row| {
+            let metadata: Option<String> = row.try_get("metadata")?;
+            Ok(NotificationRecord {
+                id: row.try_get("id")?,
+                notification_type: row.try_get("notification_type")?,
+                status: row.try_get("status")?,
+                project_id: row.try_get("project_id")?,
+                task_id: row.try_get("task_id")?,
+                task_attempt_id: row.try_get("task_attempt_id")?,
+                execution_process_id: row.try_get("execution_process_id")?,
+                message: row.try_get("message")?,
+                error_message: row.try_get("error_message")?,
+                attempts: row.try_get("attempts")?,
+                metadata: metadata
+                    .map(|raw| serde_json::from_str(&raw).unwrap_or(Value::String(raw)))
+                    .unwrap_or(Value::Null),
+                created_at: row.try_get("created_at")?,
+                sent_at: row.try_get("sent_at")?,
+                next_attempt_at: row.try_get("next_attempt_at")?,
+            })
+        })
+        .collect()
+}
+
+/// Put a dead-lettered or skipped notification back in the queue with a fresh
+/// attempt budget. Sinks that already accepted it are not delivered to again.
+pub async fn retry_notification(
+    pool: &SqlitePool,
+    id: &str,
+    request_id: Option<&str>,
+) -> Result<RetryOutcome> {
+    let result = sqlx::query(
+        "UPDATE forge_omni_notifications
+            SET status = 'pending', attempts = 0, next_attempt_at = NULL,
+                claimed_at = NULL, error_message = NULL,
+                metadata = json_set(COALESCE(metadata, '{}'), '$.request_id', ?)
+          WHERE id = ? AND status IN ('dead_letter', 'skipped')",
+    )
+    .bind(request_id)
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        return Ok(RetryOutcome::Requeued);
+    }
+
+    let status: Option<String> =
+        sqlx::query_scalar("SELECT status FROM forge_omni_notifications WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(match status {
+        Some(status) => RetryOutcome::NotRetryable { status },
+        None => RetryOutcome::NotFound,
+    })
+}
+
+/// Delete finished notifications matching `filter` together with their per-sink
+/// delivery rows. Returns the number of notifications removed.
+pub async fn purge_notifications(pool: &SqlitePool, filter: &NotificationFilter) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    let mut deliveries = QueryBuilder::<Sqlite>::new(format!(
+        "DELETE FROM forge_notification_deliveries
+          WHERE notification_id IN (
+              SELECT id FROM forge_omni_notifications WHERE {FINISHED_STATUS_SQL}"
+    ));
+    push_filter(&mut deliveries, filter);
+    deliveries.push(")");
+    deliveries.build().execute(&mut *tx).await?;
+
+    let mut notifications = QueryBuilder::<Sqlite>::new(format!(
+        "DELETE FROM forge_omni_notifications WHERE {FINISHED_STATUS_SQL}"
+    ));
+    push_filter(&mut notifications, filter);
+    let deleted = notifications
+        .build()
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+/// Delete sent and skipped notifications older than `retention`. Dead letters are
+/// kept until they are retried or purged explicitly.
+pub async fn prune_notifications(pool: &SqlitePool, retention: Duration) -> Result<u64> {
+    let cutoff = sqlite_datetime(Utc::now() - chrono::Duration::from_std(retention)?);
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "DELETE FROM forge_notification_deliveries
+          WHERE notification_id IN (
+              SELECT id FROM forge_omni_notifications
+               WHERE status IN ('sent', 'skipped')
+                 AND COALESCE(sent_at, created_at) < ?
+          )",
+    )
+    .bind(&cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    let pruned = sqlx::query(
+        "DELETE FROM forge_omni_notifications
+          WHERE status IN ('sent', 'skipped')
+            AND COALESCE(sent_at, created_at) < ?",
+    )
+    .bind(&cutoff)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+    Ok(pruned)
+}
+
+/// Retention from `FORGE_NOTIFICATION_RETENTION_DAYS`; `0` disables pruning
+pub fn notification_retention_from_env() -> Option<Duration> {
+    let days = match std::env::var("FORGE_NOTIFICATION_RETENTION_DAYS") {
+        Ok(value) => value.trim().parse::<u64>().unwrap_or_else(|_| {
+            tracing::warn!(
+                "Invalid FORGE_NOTIFICATION_RETENTION_DAYS '{}', using {} days",
+                value,
+                DEFAULT_RETENTION_DAYS
+            );
+            DEFAULT_RETENTION_DAYS
+        }),
+        Err(_) => DEFAULT_RETENTION_DAYS,
+    };
+
+    (days > 0).then(|| Duration::from_secs(days * 24 * 60 * 60))
+}
+
+pub(crate) fn spawn_notification_retention(pool: SqlitePool, retention: Duration) {
+    tokio::spawn(async move {
+        loop {
+            match prune_notifications(&pool, retention).await {
+                Ok(0) => {}
+                Ok(pruned) => tracing::info!(pruned, "Pruned old notifications"),
+                Err(err) => tracing::error!("Notification retention sweep failed: {err:?}"),
+            }
+            sleep(RETENTION_SWEEP_INTERVAL).await;
+        }
+    });
+}
+
+fn push_filter(query: &mut QueryBuilder<'_, Sqlite>, filter: &NotificationFilter) {
+    if let Some(project_id) = filter.project_id {
+        query
+            .push(format!(" AND {PROJECT_ID_SQL} = "))
+            .push_bind(project_id.simple().to_string());
+    }
+    if let Some(status) = &filter.status {
+        query.push(" AND status = ").push_bind(status.clone());
+    }
+    if let Some(notification_type) = &filter.notification_type {
+        query
+            .push(" AND notification_type = ")
+            .push_bind(notification_type.clone());
+    }
+    if let Some(since) = filter.since {
+        query
+            .push(" AND created_at >= ")
+            .push_bind(sqlite_datetime(since));
+    }
+    if let Some(until) = filter.until {
+        query
+            .push(" AND created_at < ")
+            .push_bind(sqlite_datetime(until));
+    }
+}
+
+/// Format matching `datetime('now')`, which is how queue rows record timestamps
+fn sqlite_datetime(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support;
+
+    async fn insert(
+        pool: &SqlitePool,
+        id: &str,
+        kind: &str,
+        status: &str,
+        project: Uuid,
+        age: &str,
+    ) {
+        sqlx::query(
+            "INSERT INTO forge_omni_notifications
+                 (id, notification_type, recipient, message, status, metadata, created_at, sent_at)
+             VALUES (?, ?, '', '', ?, json_object('project_id', ?), datetime('now', ?),
+                     CASE WHEN ? = 'sent' THEN datetime('now', ?) END)",
+        )
+        .bind(id)
+        .bind(kind)
+        .bind(status)
+        .bind(project.simple().to_string())
+        .bind(age)
+        .bind(status)
+        .bind(age)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    fn ids(records: &[NotificationRecord]) -> Vec<&str> {
+        records.iter().map(|r| r.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn list_filters_by_project_status_type_and_date() {
+        let pool = test_support::pool().await;
+        let (project, other) = (Uuid::new_v4(), Uuid::new_v4());
+        insert(
+            &pool,
+            "old-sent",
+            "execution_completed",
+            "sent",
+            project,
+            "-3 days",
+        )
+        .await;
+        insert(
+            &pool,
+            "new-sent",
+            "execution_completed",
+            "sent",
+            project,
+            "-1 hours",
+        )
+        .await;
+        insert(&pool, "new-pr", "pr_opened", "skipped", project, "-1 hours").await;
+        insert(
+            &pool,
+            "other",
+            "execution_completed",
+            "sent",
+            other,
+            "-1 hours",
+        )
+        .await;
+
+        let by_project = NotificationFilter {
+            project_id: Some(project),
+            ..Default::default()
+        };
+        let all = list_notifications(&pool, &by_project).await.unwrap();
+        assert_eq!(ids(&all), vec!["new-pr", "new-sent", "old-sent"]);
+        assert_eq!(all[0].project_id, Some(project.simple().to_string()));
+
+        let recent_sent = NotificationFilter {
+            status: Some("sent".into()),
+            notification_type: Some("execution_completed".into()),
+            since: Some(Utc::now() - chrono::Duration::days(1)),
+            ..by_project.clone()
+        };
+        let recent = list_notifications(&pool, &recent_sent).await.unwrap();
+        assert_eq!(ids(&recent), vec!["new-sent"]);
+
+        let paged = NotificationFilter {
+            limit: Some(1),
+            offset: Some(1),
+            ..by_project
+        };
+        let page = list_notifications(&pool, &paged).await.unwrap();
+        assert_eq!(ids(&page), vec!["new-sent"]);
+    }
+
+    #[tokio::test]
+    async fn retry_only_requeues_finished_rows_that_were_not_sent() {
+        let pool = test_support::pool().await;
+        let project = Uuid::new_v4();
+        insert(
+            &pool,
+            "dead",
+            "execution_completed",
+            "dead_letter",
+            project,
+            "-1 hours",
+        )
+        .await;
+        insert(
+            &pool,
+            "sent",
+            "execution_completed",
+            "sent",
+            project,
+            "-1 hours",
+        )
+        .await;
+
+        assert_eq!(
+            retry_notification(&pool, "dead", None).await.unwrap(),
+            RetryOutcome::Requeued
+        );
+        assert_eq!(
+            retry_notification(&pool, "dead", None).await.unwrap(),
+            RetryOutcome::NotRetryable {
+                status: "pending".into()
+            }
+        );
+        assert_eq!(
+            retry_notification(&pool, "sent", None).await.unwrap(),
+            RetryOutcome::NotRetryable {
+                status: "sent".into()
+            }
+        );
+        assert_eq!(
+            retry_notification(&pool, "missing", None).await.unwrap(),
+            RetryOutcome::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn purge_and_retention_never_touch_queued_rows() {
+        let pool = test_support::pool().await;
+        let project = Uuid::new_v4();
+        insert(
+            &pool,
+            "old-sent",
+            "execution_completed",
+            "sent",
+            project,
+            "-40 days",
+        )
+        .await;
+        insert(
+            &pool,
+            "old-dead",
+            "execution_completed",
+            "dead_letter",
+            project,
+            "-40 days",
+        )
+        .await;
+        insert(
+            &pool,
+            "old-pending",
+            "execution_completed",
+            "pending",
+            project,
+            "-40 days",
+        )
+        .await;
+        insert(
+            &pool,
+            "new-sent",
+            "execution_completed",
+            "sent",
+            project,
+            "-1 hours",
+        )
+        .await;
+        sqlx::query(
+            "INSERT INTO forge_notification_deliveries (notification_id, sink_id, status)
+             VALUES ('old-sent', 'webhook', 'sent')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let pruned = prune_notifications(&pool, Duration::from_secs(30 * 24 * 60 * 60))
+            .await
+            .unwrap();
+        assert_eq!(pruned, 1);
+        let orphaned: i64 =
+            sqlx::query_scalar("SELECT COUNT(1) FROM forge_notification_deliveries")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(orphaned, 0);
+
+        let purged = purge_notifications(&pool, &NotificationFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(purged, 2);
+
+        let remaining = list_notifications(&pool, &NotificationFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(ids(&remaining), vec!["old-pending"]);
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! Uses SQLite triggers to detect when tasks complete and queue Omni notifications.
 //! This avoids polling and hooks directly into the execution completion event.
+//! The same approach covers the other database-backed lifecycle events: pull request
+//! status changes, dev server crashes and tasks moving to review.
 
 use anyhow::Result;
 use sqlx::SqlitePool;
@@ -21,6 +23,7 @@ pub async fn install_notification_trigger(pool: &SqlitePool) -> Result<()> {
         AFTER UPDATE OF status ON execution_processes
         WHEN NEW.status IN ('completed', 'failed', 'killed')
           AND OLD.status NOT IN ('completed', 'failed', 'killed')
+          AND NEW.run_reason != 'devserver'
         BEGIN
             INSERT INTO forge_omni_notifications (
                 id,
@@ -30,6 +33,7 @@ pub async fn install_notification_trigger(pool: &SqlitePool) -> Result<()> {
                 message,
                 status,
                 metadata,
+                execution_process_id,
                 created_at
             )
             SELECT
@@ -45,16 +49,18 @@ pub async fn install_notification_trigger(pool: &SqlitePool) -> Result<()> {
                     'executor', COALESCE(ta.executor, ''),
                     'branch', COALESCE(ta.branch, ''),
                     'project_id', lower(hex(t.project_id)),
-                    'exit_code', COALESCE(NEW.exit_code, 0)
+                    'exit_code', COALESCE(NEW.exit_code, 0),
+                    'run_reason', NEW.run_reason
                 ),
+                lower(hex(NEW.id)),
                 datetime('now')
             FROM task_attempts ta
             JOIN tasks t ON t.id = ta.task_id
             WHERE ta.id = NEW.task_attempt_id
               AND NOT EXISTS (
-                  -- Prevent duplicate notifications for the same task attempt
+                  -- One notification per execution process, so follow-ups and retries still notify
                   SELECT 1 FROM forge_omni_notifications
-                  WHERE metadata LIKE '%' || lower(hex(NEW.task_attempt_id)) || '%'
+                  WHERE execution_process_id = lower(hex(NEW.id))
                     AND notification_type = 'execution_completed'
               );
         END;
@@ -63,6 +69,304 @@ pub async fn install_notification_trigger(pool: &SqlitePool) -> Result<()> {
     .execute(pool)
     .await?;
 
-    tracing::info!("Installed Omni notification trigger");
+    install_lifecycle_triggers(pool).await?;
+
+    tracing::info!("Installed Omni notification triggers");
+    Ok(())
+}
+
+/// Triggers for lifecycle events other than execution completion.
+/// Each row carries the same metadata as `omni_execution_completed` plus `details`.
+async fn install_lifecycle_triggers(pool: &SqlitePool) -> Result<()> {
+    for name in [
+        "forge_notify_pr_opened",
+        "forge_notify_pr_status",
+        "forge_notify_dev_server_crashed",
+        "forge_notify_task_in_review",
+    ] {
+        sqlx::query(&format!("DROP TRIGGER IF EXISTS {name}"))
+            .execute(pool)
+            .await?;
+    }
+
+    // PR attached or created for an attempt (MergeStatus::Open)
+    sqlx::query(
+        r#"
+        CREATE TRIGGER forge_notify_pr_opened
+        AFTER INSERT ON merges
+        WHEN NEW.merge_type = 'pr'
+        BEGIN
+            INSERT INTO forge_omni_notifications (
+                id, task_id, notification_type, recipient, message, status, metadata, created_at
+            )
+            SELECT
+                lower(hex(randomblob(16))),
+                t.id,
+                'pr_opened',
+                '',
+                '',
+                'pending',
+                json_object(
+                    'task_attempt_id', lower(hex(NEW.task_attempt_id)),
+                    'status', COALESCE(NEW.pr_status, 'open'),
+                    'executor', COALESCE(ta.executor, ''),
+                    'branch', COALESCE(ta.branch, ''),
+                    'project_id', lower(hex(t.project_id)),
+                    'details', json_object(
+                        'pr_number', NEW.pr_number,
+                        'pr_url', NEW.pr_url,
+                        'target_branch', NEW.target_branch_name
+                    )
+                ),
+                datetime('now')
+            FROM task_attempts ta
+            JOIN tasks t ON t.id = ta.task_id
+            WHERE ta.id = NEW.task_attempt_id;
+        END;
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // PR monitor observed MergeStatus::Merged or MergeStatus::Closed
+    sqlx::query(
+        r#"
+        CREATE TRIGGER forge_notify_pr_status
+        AFTER UPDATE OF pr_status ON merges
+        WHEN NEW.merge_type = 'pr'
+          AND NEW.pr_status IN ('merged', 'closed')
+          AND OLD.pr_status IS NOT NEW.pr_status
+        BEGIN
+            INSERT INTO forge_omni_notifications (
+                id, task_id, notification_type, recipient, message, status, metadata, created_at
+            )
+            SELECT
+                lower(hex(randomblob(16))),
+                t.id,
+                'pr_' || NEW.pr_status,
+                '',
+                '',
+                'pending',
+                json_object(
+                    'task_attempt_id', lower(hex(NEW.task_attempt_id)),
+                    'status', NEW.pr_status,
+                    'executor', COALESCE(ta.executor, ''),
+                    'branch', COALESCE(ta.branch, ''),
+                    'project_id', lower(hex(t.project_id)),
+                    'details', json_object(
+                        'pr_number', NEW.pr_number,
+                        'pr_url', NEW.pr_url,
+                        'target_branch', NEW.target_branch_name
+                    )
+                ),
+                datetime('now')
+            FROM task_attempts ta
+            JOIN tasks t ON t.id = ta.task_id
+            WHERE ta.id = NEW.task_attempt_id;
+        END;
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Dev servers only stop as 'killed' when a user stops them; 'failed' means a crash
+    sqlx::query(
+        r#"
+        CREATE TRIGGER forge_notify_dev_server_crashed
+        AFTER UPDATE OF status ON execution_processes
+        WHEN NEW.run_reason = 'devserver'
+          AND NEW.status = 'failed'
+          AND OLD.status = 'running'
+        BEGIN
+            INSERT INTO forge_omni_notifications (
+                id, task_id, notification_type, recipient, message, status, metadata,
+                execution_process_id, created_at
+            )
+            SELECT
+                lower(hex(randomblob(16))),
+                t.id,
+                'dev_server_crashed',
+                '',
+                '',
+                'pending',
+                json_object(
+                    'task_attempt_id', lower(hex(NEW.task_attempt_id)),
+                    'status', NEW.status,
+                    'executor', COALESCE(ta.executor, ''),
+                    'branch', COALESCE(ta.branch, ''),
+                    'project_id', lower(hex(t.project_id)),
+                    'exit_code', NEW.exit_code,
+                    'details', json_object(
+                        'execution_process_id', lower(hex(NEW.id))
+                    )
+                ),
+                lower(hex(NEW.id)),
+                datetime('now')
+            FROM task_attempts ta
+            JOIN tasks t ON t.id = ta.task_id
+            WHERE ta.id = NEW.task_attempt_id
+              AND NOT EXISTS (
+                  SELECT 1 FROM forge_omni_notifications
+                  WHERE execution_process_id = lower(hex(NEW.id))
+                    AND notification_type = 'dev_server_crashed'
+              );
+        END;
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Task moved to In Review; reported against its most recent attempt
+    sqlx::query(
+        r#"
+        CREATE TRIGGER forge_notify_task_in_review
+        AFTER UPDATE OF status ON tasks
+        WHEN NEW.status = 'inreview'
+          AND OLD.status != 'inreview'
+        BEGIN
+            INSERT INTO forge_omni_notifications (
+                id, task_id, notification_type, recipient, message, status, metadata, created_at
+            )
+            SELECT
+                lower(hex(randomblob(16))),
+                NEW.id,
+                'task_in_review',
+                '',
+                '',
+                'pending',
+                json_object(
+                    'task_attempt_id', lower(hex(ta.id)),
+                    'status', NEW.status,
+                    'executor', COALESCE(ta.executor, ''),
+                    'branch', COALESCE(ta.branch, ''),
+                    'project_id', lower(hex(NEW.project_id)),
+                    'details', json_object()
+                ),
+                datetime('now')
+            FROM task_attempts ta
+            WHERE ta.id = (
+                SELECT id FROM task_attempts
+                 WHERE task_id = NEW.id
+                 ORDER BY created_at DESC
+                 LIMIT 1
+            );
+        END;
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use forge_core_services::services::forge_config::ForgeConfigService;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::services::{
+        OmniQueueStep,
+        notification_sinks::{
+            NotificationSettings, NotificationSinkConfig, NotificationSinkKind,
+            save_notification_settings,
+        },
+        process_next_omni_notification, test_support,
+    };
+
+    async fn setup_attempt() -> (SqlitePool, Uuid, Uuid) {
+        let pool = test_support::pool().await;
+        install_notification_trigger(&pool).await.unwrap();
+        let seeded = test_support::seed_attempt(&pool).await;
+        (pool, seeded.project_id, seeded.attempt_id)
+    }
+
+    async fn run_process(pool: &SqlitePool, attempt_id: Uuid, run_reason: &str) -> Uuid {
+        let process_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO execution_processes (id, task_attempt_id, run_reason, executor_action, status)
+             VALUES (?, ?, ?, '{}', 'running')",
+        )
+        .bind(process_id)
+        .bind(attempt_id)
+        .bind(run_reason)
+        .execute(pool)
+        .await
+        .unwrap();
+        set_process_status(pool, process_id, "completed").await;
+        process_id
+    }
+
+    async fn set_process_status(pool: &SqlitePool, process_id: Uuid, status: &str) {
+        sqlx::query("UPDATE execution_processes SET status = ? WHERE id = ?")
+            .bind(status)
+            .bind(process_id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn every_execution_process_notifies_once() {
+        let (pool, _project_id, attempt_id) = setup_attempt().await;
+
+        let initial = run_process(&pool, attempt_id, "codingagent").await;
+        let follow_up = run_process(&pool, attempt_id, "codingagent").await;
+        // A process that is resumed and completes again is still the same run
+        set_process_status(&pool, initial, "running").await;
+        set_process_status(&pool, initial, "completed").await;
+
+        let queued: Vec<String> = sqlx::query_scalar(
+            "SELECT execution_process_id FROM forge_omni_notifications
+              WHERE notification_type = 'execution_completed'
+              ORDER BY created_at, rowid",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            queued,
+            vec![initial.simple().to_string(), follow_up.simple().to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn coding_agent_only_skips_script_completions() {
+        let (pool, project_id, attempt_id) = setup_attempt().await;
+        let config = ForgeConfigService::new(pool.clone());
+        let settings = NotificationSettings {
+            sinks: vec![NotificationSinkConfig {
+                id: "project-webhook".into(),
+                enabled: true,
+                sink: NotificationSinkKind::Webhook {
+                    url: "http://127.0.0.1:9/hooks/forge".into(),
+                    secret: None,
+                },
+                has_secret: false,
+            }],
+            coding_agent_only: true,
+            ..Default::default()
+        };
+        save_notification_settings(&pool, Some(project_id), &settings)
+            .await
+            .unwrap();
+
+        run_process(&pool, attempt_id, "setupscript").await;
+        let step = process_next_omni_notification(&pool, &config)
+            .await
+            .unwrap();
+        assert_eq!(step, OmniQueueStep::Skipped);
+
+        let reason: Option<String> = sqlx::query_scalar(
+            "SELECT error_message FROM forge_omni_notifications WHERE status = 'skipped'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert!(
+            reason
+                .unwrap_or_default()
+                .contains("coding agent runs only")
+        );
+    }
+}
@@ -0,0 +1,804 @@
+//! Notification Sinks
+//!
+//! Delivery targets for queued forge notifications. Omni is one sink among several:
+//! projects can also fan out to signed HTTP webhooks, Slack-compatible incoming
+//! webhooks, ntfy topics and SMTP email. Sink configuration is stored per project in
+//! `forge_notification_settings`, alongside the upstream `ForgeProjectSettings`, and
+//! every sink gets its own row in `forge_notification_deliveries` so one failing sink
+//! never holds back the others.
+
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use forge_core_services::services::{
+    forge_config::ForgeConfigService,
+    omni::{OmniConfig, OmniService},
+};
+use futures_util::future::join_all;
+use hmac::{Hmac, Mac};
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, header::ContentType},
+    transport::smtp::authentication::Credentials,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::{Row, SqlitePool};
+use ts_rs_forge::TS;
+use url::Url;
+use uuid::Uuid;
+
+use super::{
+    notification_events::NotificationEvent, notification_templates::NotificationTemplates,
+};
+
+/// Delivery-record id of the built-in Omni sink
+pub const OMNI_SINK_ID: &str = "omni";
+
+/// Upper bound for a single sink delivery
+pub const SINK_DELIVERY_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+const DEFAULT_NTFY_SERVER: &str = "https://ntfy.sh";
+
+/// Stands in for a redacted secret; sending it back keeps the stored value
+pub const REDACTED_SECRET: &str = "********";
+
+/// Rendered notification handed to every sink
+#[derive(Debug, Clone)]
+pub struct NotificationMessage {
+    pub title: String,
+    pub body: String,
+    pub url: Option<String>,
+    /// Status or event key ("completed", "failed", ...) for sinks that tag messages
+    pub status: String,
+}
+
+/// A destination that queued notifications are delivered to
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    /// Stable identifier used to track delivery status per sink
+    fn id(&self) -> &str;
+
+    async fn deliver(&self, message: &NotificationMessage) -> Result<()>;
+}
+
+/// Per-project notification settings owned by forge-app
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct NotificationSettings {
+    #[serde(default)]
+    pub sinks: Vec<NotificationSinkConfig>,
+    #[serde(default)]
+    pub templates: NotificationTemplates,
+    #[serde(default = "NotificationEvent::default_subscriptions")]
+    pub events: Vec<NotificationEvent>,
+    /// Only notify `execution_completed` for coding agent runs, skipping setup and cleanup scripts
+    #[serde(default)]
+    pub coding_agent_only: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            sinks: Vec::new(),
+            templates: NotificationTemplates::default(),
+            events: NotificationEvent::default_subscriptions(),
+            coding_agent_only: false,
+        }
+    }
+}
+
+/// `id` must be unique within a project and is what delivery status is recorded
+/// against, so renaming a sink re-sends pending notifications.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct NotificationSinkConfig {
+    pub id: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub sink: NotificationSinkKind,
+    /// Set in API responses when the sink's secret was redacted; ignored on save
+    #[serde(default)]
+    pub has_secret: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationSinkKind {
+    /// JSON POST, signed with HMAC-SHA256 when `secret` is set
+    Webhook { url: String, secret: Option<String> },
+    /// Slack (or Mattermost/Discord `/slack`) incoming webhook
+    Slack { webhook_url: String },
+    /// `server_url` defaults to https://ntfy.sh
+    Ntfy {
+        server_url: Option<String>,
+        topic: String,
+        token: Option<String>,
+    },
+    Email {
+        smtp_host: String,
+        smtp_port: Option<u16>,
+        security: SmtpSecurity,
+        username: Option<String>,
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    Tls,
+    Starttls,
+    None,
+}
+
+impl NotificationSettings {
+    /// Reject settings that could never deliver, so mistakes surface on save
+    /// instead of as retries in the queue
+    pub fn validate(&self) -> Result<()> {
+        self.templates.validate()?;
+
+        let mut seen = HashSet::new();
+
+        for sink in &self.sinks {
+            let id = sink.id.trim();
+            if id.is_empty() {
+                bail!("notification sink id must not be empty");
+            }
+            if id == OMNI_SINK_ID {
+                bail!("notification sink id '{OMNI_SINK_ID}' is reserved for Omni");
+            }
+            if !seen.insert(id) {
+                bail!("duplicate notification sink id '{id}'");
+            }
+
+            match &sink.sink {
+                NotificationSinkKind::Webhook { url, .. } => validate_http_url(id, url)?,
+                NotificationSinkKind::Slack { webhook_url } => validate_http_url(id, webhook_url)?,
+                NotificationSinkKind::Ntfy {
+                    server_url, topic, ..
+                } => {
+                    if let Some(server_url) = server_url {
+                        validate_http_url(id, server_url)?;
+                    }
+                    if topic.trim().is_empty() || topic.contains('/') {
+                        bail!("sink '{id}': ntfy topic must be a non-empty name without '/'");
+                    }
+                }
+                NotificationSinkKind::Email {
+                    smtp_host,
+                    from,
+                    to,
+                    ..
+                } => {
+                    if smtp_host.trim().is_empty() {
+                        bail!("sink '{id}': smtp_host must not be empty");
+                    }
+                    from.parse::<Mailbox>()
+                        .with_context(|| format!("sink '{id}': invalid from address '{from}'"))?;
+                    if to.is_empty() {
+                        bail!("sink '{id}': at least one recipient is required");
+                    }
+                    for address in to {
+                        address.parse::<Mailbox>().with_context(|| {
+                            format!("sink '{id}': invalid recipient address '{address}'")
+                        })?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl NotificationSettings {
+    /// Copy safe to return from the API: secrets are removed and flagged with `has_secret`
+    pub fn redacted(mut self) -> Self {
+        for sink in &mut self.sinks {
+            sink.has_secret = sink.sink.redact();
+        }
+        self
+    }
+
+    /// Keep the stored secret of every sink whose update omits it or sends it back
+    /// redacted. An empty string clears the secret.
+    pub fn restore_secrets(&mut self, stored: &NotificationSettings) {
+        for sink in &mut self.sinks {
+            sink.has_secret = false;
+            let previous = stored
+                .sinks
+                .iter()
+                .find(|previous| previous.id == sink.id)
+                .map(|previous| &previous.sink);
+            sink.sink.restore_secret(previous);
+        }
+    }
+}
+
+impl NotificationSinkKind {
+    /// The credential of the sink; a Slack webhook URL is its own credential
+    fn secret(&self) -> Option<&str> {
+        match self {
+            Self::Webhook { secret, .. }
+            | Self::Ntfy { token: secret, .. }
+            | Self::Email {
+                password: secret, ..
+            } => secret.as_deref(),
+            Self::Slack { webhook_url } => Some(webhook_url),
+        }
+    }
+
+    /// Remove the credential; true when there was one
+    fn redact(&mut self) -> bool {
+        match self {
+            Self::Webhook { secret, .. }
+            | Self::Ntfy { token: secret, .. }
+            | Self::Email {
+                password: secret, ..
+            } => secret.take().is_some(),
+            Self::Slack { webhook_url } => {
+                *webhook_url = REDACTED_SECRET.to_string();
+                true
+            }
+        }
+    }
+
+    fn restore_secret(&mut self, stored: Option<&Self>) {
+        let stored = stored
+            .filter(|stored| std::mem::discriminant(*stored) == std::mem::discriminant(self))
+            .and_then(Self::secret)
+            .map(str::to_string);
+        match self {
+            Self::Webhook { secret, .. }
+            | Self::Ntfy { token: secret, .. }
+            | Self::Email {
+                password: secret, ..
+            } => match secret.as_deref() {
+                None | Some(REDACTED_SECRET) => *secret = stored,
+                Some("") => *secret = None,
+                Some(_) => {}
+            },
+            Self::Slack { webhook_url } => {
+                if webhook_url == REDACTED_SECRET
+                    && let Some(stored) = stored
+                {
+                    *webhook_url = stored;
+                }
+            }
+        }
+    }
+}
+
+fn validate_http_url(sink_id: &str, raw: &str) -> Result<()> {
+    let url = Url::parse(raw).with_context(|| format!("sink '{sink_id}': invalid URL '{raw}'"))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        bail!("sink '{sink_id}': only http/https URLs are supported");
+    }
+    Ok(())
+}
+
+/// Settings stored for exactly this scope (`None` = global), or defaults
+pub async fn load_notification_settings(
+    pool: &SqlitePool,
+    project_id: Option<Uuid>,
+) -> Result<NotificationSettings> {
+    let scope = project_id.unwrap_or(ForgeConfigService::GLOBAL_PROJECT_ID);
+    let raw: Option<String> =
+        sqlx::query_scalar("SELECT settings FROM forge_notification_settings WHERE project_id = ?")
+            .bind(scope)
+            .fetch_optional(pool)
+            .await?;
+
+    match raw {
+        Some(raw) => serde_json::from_str(&raw)
+            .with_context(|| format!("invalid notification settings for {scope}")),
+        None => Ok(NotificationSettings::default()),
+    }
+}
+
+/// Settings that apply to a project: its own row when present, otherwise the global one
+pub async fn effective_notification_settings(
+    pool: &SqlitePool,
+    project_id: Uuid,
+) -> Result<NotificationSettings> {
+    let has_project_settings = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(1) FROM forge_notification_settings WHERE project_id = ?",
+    )
+    .bind(project_id)
+    .fetch_one(pool)
+    .await?
+        > 0;
+
+    if has_project_settings {
+        load_notification_settings(pool, Some(project_id)).await
+    } else {
+        load_notification_settings(pool, None).await
+    }
+}
+
+pub async fn save_notification_settings(
+    pool: &SqlitePool,
+    project_id: Option<Uuid>,
+    settings: &NotificationSettings,
+) -> Result<()> {
+    settings.validate()?;
+
+    let scope = project_id.unwrap_or(ForgeConfigService::GLOBAL_PROJECT_ID);
+    sqlx::query(
+        r#"INSERT INTO forge_notification_settings (project_id, settings, updated_at)
+           VALUES (?, ?, datetime('now'))
+           ON CONFLICT(project_id) DO UPDATE
+              SET settings = excluded.settings,
+                  updated_at = excluded.updated_at"#,
+    )
+    .bind(scope)
+    .bind(serde_json::to_string(settings)?)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Instantiate the sink described by `config`
+pub fn build_sink(
+    config: &NotificationSinkConfig,
+    http: &reqwest::Client,
+) -> Box<dyn NotificationSink> {
+    let id = config.id.clone();
+    match &config.sink {
+        NotificationSinkKind::Webhook { url, secret } => Box::new(WebhookSink {
+            id,
+            url: url.clone(),
+            secret: secret.clone(),
+            http: http.clone(),
+        }),
+        NotificationSinkKind::Slack { webhook_url } => Box::new(SlackSink {
+            id,
+            webhook_url: webhook_url.clone(),
+            http: http.clone(),
+        }),
+        NotificationSinkKind::Ntfy {
+            server_url,
+            topic,
+            token,
+        } => Box::new(NtfySink {
+            id,
+            server_url: server_url
+                .clone()
+                .unwrap_or_else(|| DEFAULT_NTFY_SERVER.to_string()),
+            topic: topic.clone(),
+            token: token.clone(),
+            http: http.clone(),
+        }),
+        NotificationSinkKind::Email {
+            smtp_host,
+            smtp_port,
+            security,
+            username,
+            password,
+            from,
+            to,
+        } => Box::new(EmailSink {
+            id,
+            smtp_host: smtp_host.clone(),
+            smtp_port: *smtp_port,
+            security: *security,
+            credentials: username
+                .clone()
+                .zip(password.clone())
+                .map(|(user, pass)| Credentials::new(user, pass)),
+            from: from.clone(),
+            to: to.clone(),
+        }),
+    }
+}
+
+/// Result of delivering one notification to its sinks
+#[derive(Debug, Default)]
+pub struct DispatchReport {
+    /// Sinks that accepted the notification during this pass
+    pub delivered: usize,
+    /// Sinks that had already accepted it on an earlier pass
+    pub previously_delivered: usize,
+    /// `(sink id, error)` for every sink that failed during this pass
+    pub failures: Vec<(String, String)>,
+}
+
+impl DispatchReport {
+    pub fn failure_summary(&self) -> String {
+        let total = self.delivered + self.previously_delivered + self.failures.len();
+        let details = self
+            .failures
+            .iter()
+            .map(|(sink, error)| format!("{sink}: {error}"))
+            .collect::<Vec<_>>()
+            .join("; ");
+        format!(
+            "delivery failed for {} of {} sinks ({details})",
+            self.failures.len(),
+            total
+        )
+    }
+}
+
+/// Deliver `message` to every sink that has not accepted this notification yet.
+///
+/// Sinks are delivered concurrently and independently; each outcome is recorded in
+/// `forge_notification_deliveries` so a retry of the queue row only re-attempts the
+/// sinks that failed.
+pub async fn dispatch(
+    pool: &SqlitePool,
+    notification_id: &str,
+    sinks: &[Box<dyn NotificationSink>],
+    message: &NotificationMessage,
+) -> Result<DispatchReport> {
+    let already_sent = delivered_sink_ids(pool, notification_id).await?;
+    let pending: Vec<&dyn NotificationSink> = sinks
+        .iter()
+        .map(|sink| sink.as_ref())
+        .filter(|sink| !already_sent.contains(sink.id()))
+        .collect();
+
+    let outcomes = join_all(pending.iter().map(|sink| async move {
+        let started = Instant::now();
+        let result = match tokio::time::timeout(SINK_DELIVERY_TIMEOUT, sink.deliver(message)).await
+        {
+            Ok(result) => result,
+            Err(_) => Err(anyhow!(
+                "delivery timed out after {}s",
+                SINK_DELIVERY_TIMEOUT.as_secs()
+            )),
+        };
+        crate::metrics::record_notification_delivery(sink.id(), started.elapsed(), result.is_ok());
+        (sink.id(), result)
+    }))
+    .await;
+
+    let mut report = DispatchReport {
+        previously_delivered: sinks.len() - pending.len(),
+        ..Default::default()
+    };
+
+    for (sink_id, result) in outcomes {
+        record_delivery(pool, notification_id, sink_id, &result).await?;
+        match result {
+            Ok(()) => {
+                tracing::info!(notification_id, sink_id, "Delivered notification");
+                report.delivered += 1;
+            }
+            Err(err) => {
+                tracing::warn!(
+                    notification_id,
+                    sink_id,
+                    "Notification delivery failed: {err}"
+                );
+                report.failures.push((sink_id.to_string(), err.to_string()));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+async fn delivered_sink_ids(pool: &SqlitePool, notification_id: &str) -> Result<HashSet<String>> {
+    let rows = sqlx::query(
+        "SELECT sink_id FROM forge_notification_deliveries WHERE notification_id = ? AND status = 'sent'",
+    )
+    .bind(notification_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| row.try_get::<String, _>("sink_id").map_err(Into::into))
+        .collect()
+}
+
+async fn record_delivery(
+    pool: &SqlitePool,
+    notification_id: &str,
+    sink_id: &str,
+    result: &Result<()>,
+) -> Result<()> {
+    let (status, error) = match result {
+        Ok(()) => ("sent", None),
+        Err(err) => ("failed", Some(err.to_string())),
+    };
+
+    sqlx::query(
+        r#"INSERT INTO forge_notification_deliveries
+               (notification_id, sink_id, status, attempts, error_message, delivered_at, updated_at)
+           VALUES (?, ?, ?, 1, ?, CASE WHEN ? = 'sent' THEN datetime('now') END, datetime('now'))
+           ON CONFLICT(notification_id, sink_id) DO UPDATE
+              SET status = excluded.status,
+                  attempts = forge_notification_deliveries.attempts + 1,
+                  error_message = excluded.error_message,
+                  delivered_at = excluded.delivered_at,
+                  updated_at = excluded.updated_at"#,
+    )
+    .bind(notification_id)
+    .bind(sink_id)
+    .bind(status)
+    .bind(error)
+    .bind(status)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Omni (WhatsApp/Discord/Telegram via Automagik Omni), configured through
+/// the upstream `ForgeProjectSettings`
+pub struct OmniSink {
+    config: OmniConfig,
+}
+
+impl OmniSink {
+    pub fn new(config: OmniConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for OmniSink {
+    fn id(&self) -> &str {
+        OMNI_SINK_ID
+    }
+
+    async fn deliver(&self, message: &NotificationMessage) -> Result<()> {
+        let host = self
+            .config
+            .host
+            .as_deref()
+            .ok_or_else(|| anyhow!("Omni host not configured"))?;
+        if host.is_empty() {
+            bail!("Omni host configuration empty");
+        }
+
+        OmniService::new(self.config.clone())
+            .send_task_notification(&message.title, &message.body, message.url.as_deref())
+            .await
+    }
+}
+
+/// Generic JSON webhook. When a secret is configured the request carries
+/// `X-Forge-Timestamp` and `X-Forge-Signature: sha256=<hex>`, an HMAC-SHA256 over
+/// `"{timestamp}.{body}"`, so receivers can authenticate and reject replays.
+pub struct WebhookSink {
+    id: String,
+    url: String,
+    secret: Option<String>,
+    http: reqwest::Client,
+}
+
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Forge-Signature";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Forge-Timestamp";
+
+/// HMAC-SHA256 signature sent in `X-Forge-Signature`
+pub fn webhook_signature(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("sha256={hex}")
+}
+
+#[async_trait]
+impl NotificationSink for WebhookSink {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn deliver(&self, message: &NotificationMessage) -> Result<()> {
+        let body = json!({
+            "title": message.title,
+            "body": message.body,
+            "url": message.url,
+            "status": message.status,
+        })
+        .to_string();
+
+        let mut request = self
+            .http
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+
+        if let Some(secret) = &self.secret {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            request = request
+                .header(WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string())
+                .header(
+                    WEBHOOK_SIGNATURE_HEADER,
+                    webhook_signature(secret, timestamp, &body),
+                );
+        }
+
+        request.body(body).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Slack-compatible incoming webhook (`{"text": ...}` payload with mrkdwn)
+pub struct SlackSink {
+    id: String,
+    webhook_url: String,
+    http: reqwest::Client,
+}
+
+fn slack_text(message: &NotificationMessage) -> String {
+    let mut text = format!("*{}*\n{}", message.title, message.body);
+    if let Some(url) = &message.url {
+        text.push_str(&format!("\n<{url}|Open in Forge>"));
+    }
+    text
+}
+
+#[async_trait]
+impl NotificationSink for SlackSink {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn deliver(&self, message: &NotificationMessage) -> Result<()> {
+        self.http
+            .post(&self.webhook_url)
+            .json(&json!({ "text": slack_text(message) }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// ntfy topic, published through ntfy's JSON API so titles may contain any UTF-8
+pub struct NtfySink {
+    id: String,
+    server_url: String,
+    topic: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+#[async_trait]
+impl NotificationSink for NtfySink {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn deliver(&self, message: &NotificationMessage) -> Result<()> {
+        let mut payload = json!({
+            "topic": self.topic,
+            "title": message.title,
+            "message": message.body,
+            "tags": [message.status],
+        });
+        if let Some(url) = &message.url {
+            payload["click"] = json!(url);
+        }
+
+        let mut request = self
+            .http
+            .post(self.server_url.trim_end_matches('/'))
+            .json(&payload);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Plain-text email over SMTP
+pub struct EmailSink {
+    id: String,
+    smtp_host: String,
+    smtp_port: Option<u16>,
+    security: SmtpSecurity,
+    credentials: Option<Credentials>,
+    from: String,
+    to: Vec<String>,
+}
+
+#[async_trait]
+impl NotificationSink for EmailSink {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn deliver(&self, message: &NotificationMessage) -> Result<()> {
+        let mut builder = Message::builder()
+            .from(self.from.parse::<Mailbox>()?)
+            .subject(message.title.clone())
+            .header(ContentType::TEXT_PLAIN);
+        for address in &self.to {
+            builder = builder.to(address.parse::<Mailbox>()?);
+        }
+
+        let mut text = message.body.clone();
+        if let Some(url) = &message.url {
+            text.push_str(&format!("\n\n{url}"));
+        }
+        let email = builder.body(text)?;
+
+        let mut transport = match self.security {
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&self.smtp_host)?,
+            SmtpSecurity::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.smtp_host)?
+            }
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.smtp_host)
+            }
+        };
+        if let Some(port) = self.smtp_port {
+            transport = transport.port(port);
+        }
+        if let Some(credentials) = &self.credentials {
+            transport = transport.credentials(credentials.clone());
+        }
+
+        transport.build().send(email).await?;
+        Ok(())
+    }
+}
+
+/// Delivery status of one notification at one sink
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct NotificationDelivery {
+    pub sink_id: String,
+    pub status: String,
+    pub attempts: u32,
+    pub error_message: Option<String>,
+    pub delivered_at: Option<String>,
+    pub updated_at: String,
+}
+
+pub async fn list_notification_deliveries(
+    pool: &SqlitePool,
+    notification_id: &str,
+) -> Result<Vec<NotificationDelivery>> {
+    let rows = sqlx::query(
+        r#"SELECT sink_id, status, attempts, error_message, delivered_at, updated_at
+             FROM forge_notification_deliveries
+            WHERE notification_id = ?
+            ORDER BY sink_id"#,
+    )
+    .bind(notification_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(NotificationDelivery {
+                sink_id: row.try_get("sink_id")?,
+                status: row.try_get("status")?,
+                attempts: row.try_get("attempts")?,
+                error_message: row.try_get("error_message")?,
+                delivered_at: row.try_get("delivered_at")?,
+                updated_at: row.try_get("updated_at")?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
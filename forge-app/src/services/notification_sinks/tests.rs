@@ -0,0 +1,438 @@
+//! Tests for notification sinks
+
+use httpmock::prelude::*;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::oneshot,
+};
+
+use super::*;
+use crate::services::test_support;
+
+fn sample_message() -> NotificationMessage {
+    NotificationMessage {
+        title: "Fix login".into(),
+        body: "Execution completed\nBranch: forge/fix-login".into(),
+        url: Some("http://forge.example/projects/p/tasks/t".into()),
+        status: "completed".into(),
+    }
+}
+
+fn sink_config(id: &str, sink: NotificationSinkKind) -> NotificationSinkConfig {
+    NotificationSinkConfig {
+        id: id.into(),
+        enabled: true,
+        sink,
+        has_secret: false,
+    }
+}
+
+fn webhook(id: &str, url: String) -> NotificationSinkConfig {
+    sink_config(id, NotificationSinkKind::Webhook { url, secret: None })
+}
+
+#[test]
+fn webhook_signature_is_hmac_sha256_of_timestamp_and_body() {
+    assert_eq!(
+        webhook_signature("key", 1_700_000_000, r#"{"a":1}"#),
+        "sha256=a438e398bfafc57e4396bb7fc2304422f0f768e965d073ca313cb52e22e6ad03"
+    );
+}
+
+#[tokio::test]
+async fn webhook_sink_posts_signed_json() {
+    let server = MockServer::start_async().await;
+    let mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/hooks/forge")
+            .header("content-type", "application/json")
+            .header_exists(WEBHOOK_TIMESTAMP_HEADER)
+            .header_exists(WEBHOOK_SIGNATURE_HEADER)
+            .json_body_partial(r#"{"title": "Fix login", "status": "completed"}"#);
+        then.status(204);
+    });
+
+    let config = sink_config(
+        "ops-webhook",
+        NotificationSinkKind::Webhook {
+            url: server.url("/hooks/forge"),
+            secret: Some("s3cret".into()),
+        },
+    );
+    build_sink(&config, &reqwest::Client::new())
+        .deliver(&sample_message())
+        .await
+        .expect("webhook delivery should succeed");
+
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn webhook_sink_surfaces_http_errors() {
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(POST).path("/hooks/forge");
+        then.status(500);
+    });
+
+    let config = webhook("ops-webhook", server.url("/hooks/forge"));
+    let err = build_sink(&config, &reqwest::Client::new())
+        .deliver(&sample_message())
+        .await
+        .expect_err("5xx responses should fail delivery");
+
+    assert!(err.to_string().contains("500"));
+}
+
+#[tokio::test]
+async fn slack_sink_posts_text_payload() {
+    let server = MockServer::start_async().await;
+    let mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/services/T000/B000/XXX")
+            .body_contains("*Fix login*")
+            .body_contains("Open in Forge");
+        then.status(200).body("ok");
+    });
+
+    let config = sink_config(
+        "team-slack",
+        NotificationSinkKind::Slack {
+            webhook_url: server.url("/services/T000/B000/XXX"),
+        },
+    );
+    build_sink(&config, &reqwest::Client::new())
+        .deliver(&sample_message())
+        .await
+        .expect("slack delivery should succeed");
+
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn ntfy_sink_publishes_to_topic_with_token() {
+    let server = MockServer::start_async().await;
+    let mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/")
+            .header("authorization", "Bearer tk_forge")
+            .json_body_partial(
+                r#"{"topic": "forge-alerts", "title": "Fix login", "tags": ["completed"], "click": "http://forge.example/projects/p/tasks/t"}"#,
+            );
+        then.status(200);
+    });
+
+    let config = sink_config(
+        "phone",
+        NotificationSinkKind::Ntfy {
+            server_url: Some(server.base_url()),
+            topic: "forge-alerts".into(),
+            token: Some("tk_forge".into()),
+        },
+    );
+    build_sink(&config, &reqwest::Client::new())
+        .deliver(&sample_message())
+        .await
+        .expect("ntfy delivery should succeed");
+
+    mock.assert_async().await;
+}
+
+/// Minimal plaintext SMTP server that accepts one message and hands back its DATA section
+async fn spawn_smtp_server() -> (u16, oneshot::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        let mut data = String::new();
+        let mut in_data = false;
+
+        write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    write.write_all(b"250 OK queued\r\n").await.unwrap();
+                } else {
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                continue;
+            }
+
+            let command = line.to_ascii_uppercase();
+            let reply: &[u8] = if command.starts_with("EHLO") || command.starts_with("HELO") {
+                b"250 localhost\r\n"
+            } else if command.starts_with("DATA") {
+                in_data = true;
+                b"354 End data with <CR><LF>.<CR><LF>\r\n"
+            } else if command.starts_with("QUIT") {
+                write.write_all(b"221 Bye\r\n").await.unwrap();
+                break;
+            } else {
+                b"250 OK\r\n"
+            };
+            write.write_all(reply).await.unwrap();
+        }
+
+        let _ = tx.send(data);
+    });
+
+    (port, rx)
+}
+
+#[tokio::test]
+async fn email_sink_sends_over_smtp() {
+    let (port, received) = spawn_smtp_server().await;
+
+    let config = sink_config(
+        "oncall-email",
+        NotificationSinkKind::Email {
+            smtp_host: "127.0.0.1".into(),
+            smtp_port: Some(port),
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            from: "Forge <forge@example.com>".into(),
+            to: vec!["oncall@example.com".into()],
+        },
+    );
+    build_sink(&config, &reqwest::Client::new())
+        .deliver(&sample_message())
+        .await
+        .expect("email delivery should succeed");
+
+    let data = received
+        .await
+        .expect("smtp server should capture the message");
+    assert!(data.contains("Subject: Fix login"));
+    assert!(data.contains("To: oncall@example.com"));
+    assert!(data.contains("Execution completed"));
+}
+
+#[tokio::test]
+async fn dispatch_tracks_status_per_sink_and_only_retries_failures() {
+    let pool = test_support::pool().await;
+    let server = MockServer::start_async().await;
+    let healthy = server.mock(|when, then| {
+        when.method(POST).path("/healthy");
+        then.status(200);
+    });
+    let broken = server.mock(|when, then| {
+        when.method(POST).path("/broken");
+        then.status(503);
+    });
+
+    let http = reqwest::Client::new();
+    let sinks = vec![
+        build_sink(&webhook("healthy", server.url("/healthy")), &http),
+        build_sink(&webhook("broken", server.url("/broken")), &http),
+    ];
+
+    let report = dispatch(&pool, "notif-1", &sinks, &sample_message())
+        .await
+        .expect("dispatch records failures instead of returning them");
+    assert_eq!(report.delivered, 1);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].0, "broken");
+
+    let deliveries = list_notification_deliveries(&pool, "notif-1")
+        .await
+        .unwrap();
+    let statuses: Vec<_> = deliveries
+        .iter()
+        .map(|d| (d.sink_id.as_str(), d.status.as_str(), d.attempts))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![("broken", "failed", 1), ("healthy", "sent", 1)]
+    );
+
+    // Once the broken sink recovers, a retry only re-delivers to it
+    broken.delete_async().await;
+    let recovered = server.mock(|when, then| {
+        when.method(POST).path("/broken");
+        then.status(200);
+    });
+
+    let report = dispatch(&pool, "notif-1", &sinks, &sample_message())
+        .await
+        .unwrap();
+    assert_eq!(report.delivered, 1);
+    assert_eq!(report.previously_delivered, 1);
+    assert!(report.failures.is_empty());
+
+    healthy.assert_hits_async(1).await;
+    recovered.assert_hits_async(1).await;
+
+    let broken_row = list_notification_deliveries(&pool, "notif-1")
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|d| d.sink_id == "broken")
+        .unwrap();
+    assert_eq!(broken_row.status, "sent");
+    assert_eq!(broken_row.attempts, 2);
+    assert!(broken_row.error_message.is_none());
+    assert!(broken_row.delivered_at.is_some());
+}
+
+#[tokio::test]
+async fn project_settings_override_global_settings() {
+    let pool = test_support::pool().await;
+    let project_id = Uuid::new_v4();
+    let global = NotificationSettings {
+        sinks: vec![webhook("global", "https://hooks.example/global".into())],
+        ..Default::default()
+    };
+    let project = NotificationSettings {
+        sinks: vec![webhook("project", "https://hooks.example/project".into())],
+        ..Default::default()
+    };
+
+    save_notification_settings(&pool, None, &global)
+        .await
+        .unwrap();
+    assert_eq!(
+        effective_notification_settings(&pool, project_id)
+            .await
+            .unwrap(),
+        global
+    );
+
+    save_notification_settings(&pool, Some(project_id), &project)
+        .await
+        .unwrap();
+    assert_eq!(
+        effective_notification_settings(&pool, project_id)
+            .await
+            .unwrap(),
+        project
+    );
+    assert_eq!(
+        load_notification_settings(&pool, None).await.unwrap(),
+        global
+    );
+}
+
+#[test]
+fn validation_rejects_unusable_sinks() {
+    let cases = [
+        (vec![webhook("", "https://hooks.example".into())], "empty"),
+        (
+            vec![webhook(OMNI_SINK_ID, "https://hooks.example".into())],
+            "reserved",
+        ),
+        (
+            vec![
+                webhook("dup", "https://hooks.example/a".into()),
+                webhook("dup", "https://hooks.example/b".into()),
+            ],
+            "duplicate",
+        ),
+        (
+            vec![webhook("ftp", "ftp://hooks.example".into())],
+            "http/https",
+        ),
+        (
+            vec![sink_config(
+                "ntfy",
+                NotificationSinkKind::Ntfy {
+                    server_url: None,
+                    topic: "a/b".into(),
+                    token: None,
+                },
+            )],
+            "topic",
+        ),
+        (
+            vec![sink_config(
+                "mail",
+                NotificationSinkKind::Email {
+                    smtp_host: "smtp.example.com".into(),
+                    smtp_port: None,
+                    security: SmtpSecurity::Starttls,
+                    username: None,
+                    password: None,
+                    from: "forge@example.com".into(),
+                    to: vec![],
+                },
+            )],
+            "recipient",
+        ),
+    ];
+
+    for (sinks, expected) in cases {
+        let err = NotificationSettings {
+            sinks,
+            ..Default::default()
+        }
+        .validate()
+        .expect_err("invalid settings should be rejected");
+        assert!(
+            err.to_string().contains(expected),
+            "expected '{expected}' in '{err}'"
+        );
+    }
+}
+
+#[test]
+fn secrets_are_redacted_on_read_and_kept_on_update() {
+    let stored = NotificationSettings {
+        sinks: vec![
+            sink_config(
+                "ops-webhook",
+                NotificationSinkKind::Webhook {
+                    url: "https://hooks.example".into(),
+                    secret: Some("s3cret".into()),
+                },
+            ),
+            sink_config(
+                "team-slack",
+                NotificationSinkKind::Slack {
+                    webhook_url: "https://hooks.slack.com/services/T000/B000/XXX".into(),
+                },
+            ),
+            sink_config(
+                "phone",
+                NotificationSinkKind::Ntfy {
+                    server_url: None,
+                    topic: "forge".into(),
+                    token: Some("tk_forge".into()),
+                },
+            ),
+        ],
+        ..Default::default()
+    };
+
+    let read = stored.clone().redacted();
+    let body = serde_json::to_string(&read).unwrap();
+    for secret in ["s3cret", "T000/B000/XXX", "tk_forge"] {
+        assert!(!body.contains(secret), "'{secret}' leaked in {body}");
+    }
+    assert!(read.sinks.iter().all(|sink| sink.has_secret));
+
+    // The client sends back what it read, clearing the ntfy token
+    let mut update = read;
+    update.sinks[2].sink = NotificationSinkKind::Ntfy {
+        server_url: None,
+        topic: "forge".into(),
+        token: Some(String::new()),
+    };
+    update.restore_secrets(&stored);
+    assert_eq!(update.sinks[..2], stored.sinks[..2]);
+    assert_eq!(
+        update.sinks[2].sink,
+        NotificationSinkKind::Ntfy {
+            server_url: None,
+            topic: "forge".into(),
+            token: None,
+        }
+    );
+    assert!(update.validate().is_ok());
+}
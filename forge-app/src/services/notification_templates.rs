@@ -0,0 +1,546 @@
+//! Notification Templates
+//!
+//! Per-project minijinja templates for notification titles and bodies. Templates are
+//! stored with the rest of the project's notification settings and rendered against a
+//! `NotificationContext` built from the task, the attempt and its coding agent runs.
+//! Projects without custom templates get the built-in ones, which match the messages
+//! forge has always sent.
+
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use minijinja::Environment;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Row, SqlitePool};
+use tokio::process::Command;
+use ts_rs_forge::TS;
+use uuid::Uuid;
+
+use super::notification_events::NotificationEvent;
+
+pub const DEFAULT_TITLE_TEMPLATE: &str = "{{ title }}";
+
+pub const DEFAULT_BODY_TEMPLATE: &str = r#"
+{%- if event == "approval_pending" -%}
+⏳ Approval pending{% if details.tool_name %}: {{ details.tool_name }}{% endif %}
+{%- elif event == "pr_opened" -%}
+🔀 Pull request opened
+{%- elif event == "pr_merged" -%}
+🎉 Pull request merged
+{%- elif event == "pr_closed" -%}
+🚫 Pull request closed
+{%- elif event == "merge_conflict" -%}
+⚠️ Merge hit conflicts
+{%- elif event == "rebase_conflict" -%}
+⚠️ Rebase hit conflicts
+{%- elif event == "dev_server_crashed" -%}
+💥 Dev server crashed
+{%- elif event == "task_in_review" -%}
+👀 Task ready for review
+{%- elif status == "completed" -%}
+✅ Execution completed
+{%- elif status == "failed" -%}
+❌ Execution failed
+{%- elif status == "killed" -%}
+🛑 Execution cancelled
+{%- else -%}
+{{ status }}
+{%- endif %}
+{% if details.pr_url %}Pull request: {{ details.pr_url }}
+{% endif %}Branch: {{ branch }}
+Executor: {{ executor }}"#;
+
+/// Unset templates fall back to DEFAULT_TITLE_TEMPLATE / DEFAULT_BODY_TEMPLATE
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct NotificationTemplates {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+impl NotificationTemplates {
+    fn title_source(&self) -> &str {
+        self.title
+            .as_deref()
+            .filter(|source| !source.trim().is_empty())
+            .unwrap_or(DEFAULT_TITLE_TEMPLATE)
+    }
+
+    fn body_source(&self) -> &str {
+        self.body
+            .as_deref()
+            .filter(|source| !source.trim().is_empty())
+            .unwrap_or(DEFAULT_BODY_TEMPLATE)
+    }
+
+    /// Compile both templates so syntax errors are reported when settings are saved
+    pub fn validate(&self) -> Result<()> {
+        let env = Environment::new();
+        env.template_from_str(self.title_source())
+            .map_err(|e| anyhow!("invalid title template: {e}"))?;
+        env.template_from_str(self.body_source())
+            .map_err(|e| anyhow!("invalid body template: {e}"))?;
+        Ok(())
+    }
+}
+
+/// Everything a template can reference
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct NotificationContext {
+    pub project_id: Uuid,
+    pub task_id: Uuid,
+    pub task_attempt_id: Uuid,
+    pub event: NotificationEvent,
+    pub title: String,
+    pub description: Option<String>,
+    pub status: String,
+    pub executor: String,
+    pub variant: Option<String>,
+    pub branch: String,
+    pub target_branch: String,
+    #[ts(type = "number | null")]
+    pub exit_code: Option<i64>,
+    #[ts(type = "number | null")]
+    pub duration_seconds: Option<i64>,
+    #[ts(type = "number | null")]
+    pub input_tokens: Option<i64>,
+    #[ts(type = "number | null")]
+    pub output_tokens: Option<i64>,
+    #[ts(type = "number | null")]
+    pub cache_creation_tokens: Option<i64>,
+    #[ts(type = "number | null")]
+    pub cache_read_tokens: Option<i64>,
+    pub diff: Option<DiffStats>,
+    pub url: String,
+    pub details: Value,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct DiffStats {
+    pub files_changed: u32,
+    pub insertions: u32,
+    pub deletions: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct RenderedNotification {
+    pub title: String,
+    pub body: String,
+}
+
+/// Build the template context for an attempt.
+///
+/// `status` falls back to the status of the latest coding agent run; exit code,
+/// variant and duration also come from that run, while the diff stats span every
+/// coding agent run of the attempt.
+pub async fn load_notification_context(
+    pool: &SqlitePool,
+    task_attempt_id: Uuid,
+    status: Option<&str>,
+) -> Result<NotificationContext> {
+    let attempt = sqlx::query(
+        r#"SELECT
+                t.id                     AS task_id,
+                t.title                  AS title,
+                t.description            AS description,
+                t.project_id             AS project_id,
+                p.git_repo_path          AS git_repo_path,
+                ta.branch                AS branch,
+                ta.target_branch         AS target_branch,
+                ta.executor              AS executor,
+                ta.input_tokens          AS input_tokens,
+                ta.output_tokens         AS output_tokens,
+                ta.cache_creation_tokens AS cache_creation_tokens,
+                ta.cache_read_tokens     AS cache_read_tokens
+           FROM task_attempts ta
+           JOIN tasks t    ON t.id = ta.task_id
+           JOIN projects p ON p.id = t.project_id
+          WHERE ta.id = ?"#,
+    )
+    .bind(task_attempt_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| anyhow!("task attempt {task_attempt_id} not found"))?;
+
+    let latest_run = sqlx::query(
+        r#"SELECT
+                status,
+                exit_code,
+                after_head_commit,
+                json_extract(executor_action, '$.typ.executor_profile_id.variant') AS variant,
+                CAST(ROUND((julianday(COALESCE(completed_at, datetime('now'))) - julianday(started_at)) * 86400) AS INTEGER)
+                    AS duration_seconds
+           FROM execution_processes
+          WHERE task_attempt_id = ? AND run_reason = 'codingagent'
+          ORDER BY created_at DESC
+          LIMIT 1"#,
+    )
+    .bind(task_attempt_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let first_before_commit: Option<String> = sqlx::query_scalar(
+        r#"SELECT before_head_commit
+             FROM execution_processes
+            WHERE task_attempt_id = ? AND run_reason = 'codingagent'
+            ORDER BY created_at ASC
+            LIMIT 1"#,
+    )
+    .bind(task_attempt_id)
+    .fetch_optional(pool)
+    .await?
+    .flatten();
+
+    let project_id: Uuid = attempt.try_get("project_id")?;
+    let task_id: Uuid = attempt.try_get("task_id")?;
+    let git_repo_path: String = attempt.try_get("git_repo_path")?;
+
+    let run_value = |column: &str| -> Option<String> {
+        latest_run
+            .as_ref()
+            .and_then(|row| row.try_get::<Option<String>, _>(column).ok().flatten())
+    };
+    let run_number = |column: &str| -> Option<i64> {
+        latest_run
+            .as_ref()
+            .and_then(|row| row.try_get::<Option<i64>, _>(column).ok().flatten())
+    };
+
+    let diff = match (first_before_commit, run_value("after_head_commit")) {
+        (Some(before), Some(after)) => diff_stats(Path::new(&git_repo_path), &before, &after).await,
+        _ => None,
+    };
+
+    Ok(NotificationContext {
+        project_id,
+        task_id,
+        task_attempt_id,
+        event: NotificationEvent::ExecutionCompleted,
+        title: attempt.try_get("title")?,
+        description: attempt.try_get("description")?,
+        status: status
+            .map(str::to_string)
+            .or_else(|| run_value("status"))
+            .unwrap_or_else(|| "completed".to_string()),
+        executor: attempt.try_get("executor")?,
+        variant: run_value("variant"),
+        branch: attempt.try_get("branch")?,
+        target_branch: attempt.try_get("target_branch")?,
+        exit_code: run_number("exit_code"),
+        duration_seconds: run_number("duration_seconds"),
+        input_tokens: attempt.try_get("input_tokens")?,
+        output_tokens: attempt.try_get("output_tokens")?,
+        cache_creation_tokens: attempt.try_get("cache_creation_tokens")?,
+        cache_read_tokens: attempt.try_get("cache_read_tokens")?,
+        diff,
+        url: format!(
+            "{}/projects/{}/tasks/{}",
+            super::omni_base_url()?,
+            project_id,
+            task_id
+        ),
+        details: Value::Object(Default::default()),
+    })
+}
+
+/// `git diff --shortstat before after`; best effort, since the repo may have moved
+/// or the commits may have been garbage collected since the run
+async fn diff_stats(repo: &Path, before: &str, after: &str) -> Option<DiffStats> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["diff", "--shortstat", before, after])
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        tracing::debug!(
+            "git diff --shortstat failed in {}: {}",
+            repo.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+
+    Some(parse_shortstat(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse ` 3 files changed, 10 insertions(+), 2 deletions(-)`; empty output means no changes
+fn parse_shortstat(output: &str) -> DiffStats {
+    let mut stats = DiffStats::default();
+
+    for part in output.trim().split(',') {
+        let mut words = part.split_whitespace();
+        let (Some(count), Some(label)) = (words.next(), words.next()) else {
+            continue;
+        };
+        let Ok(count) = count.parse::<u32>() else {
+            continue;
+        };
+        if label.starts_with("file") {
+            stats.files_changed = count;
+        } else if label.starts_with("insertion") {
+            stats.insertions = count;
+        } else if label.starts_with("deletion") {
+            stats.deletions = count;
+        }
+    }
+
+    stats
+}
+
+pub fn render_notification(
+    templates: &NotificationTemplates,
+    context: &NotificationContext,
+) -> Result<RenderedNotification> {
+    let env = Environment::new();
+    let render = |source: &str, which: &str| -> Result<String> {
+        env.render_str(source, context)
+            .map(|rendered| rendered.trim().to_string())
+            .map_err(|e| anyhow!("failed to render {which} template: {e}"))
+    };
+
+    let title = render(templates.title_source(), "title")?;
+    let body = render(templates.body_source(), "body")?;
+
+    Ok(RenderedNotification {
+        title: if title.is_empty() {
+            context.title.clone()
+        } else {
+            title
+        },
+        body,
+    })
+}
+
+/// Render against an attempt for the preview endpoint
+pub async fn preview_notification(
+    pool: &SqlitePool,
+    task_attempt_id: Uuid,
+    event: NotificationEvent,
+    status: Option<&str>,
+    templates: &NotificationTemplates,
+) -> Result<(RenderedNotification, NotificationContext)> {
+    templates.validate()?;
+    let mut context = load_notification_context(pool, task_attempt_id, status)
+        .await
+        .context("failed to build notification context")?;
+    context.event = event;
+    let rendered = render_notification(templates, &context)?;
+    Ok((rendered, context))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::services::test_support;
+
+    fn context(status: &str) -> NotificationContext {
+        NotificationContext {
+            project_id: Uuid::nil(),
+            task_id: Uuid::nil(),
+            task_attempt_id: Uuid::nil(),
+            event: NotificationEvent::ExecutionCompleted,
+            title: "Fix login".into(),
+            description: Some("Users are logged out on refresh".into()),
+            status: status.into(),
+            executor: "CLAUDE_CODE".into(),
+            variant: Some("PLAN".into()),
+            branch: "forge/fix-login".into(),
+            target_branch: "main".into(),
+            exit_code: Some(0),
+            duration_seconds: Some(754),
+            input_tokens: Some(1200),
+            output_tokens: Some(300),
+            cache_creation_tokens: None,
+            cache_read_tokens: None,
+            diff: Some(DiffStats {
+                files_changed: 3,
+                insertions: 10,
+                deletions: 2,
+            }),
+            url: "http://forge.example/projects/p/tasks/t".into(),
+            details: json!({}),
+        }
+    }
+
+    #[test]
+    fn default_templates_match_legacy_messages() {
+        let templates = NotificationTemplates::default();
+        for (status, headline) in [
+            ("completed", "✅ Execution completed"),
+            ("failed", "❌ Execution failed"),
+            ("killed", "🛑 Execution cancelled"),
+            ("paused", "paused"),
+        ] {
+            let rendered = render_notification(&templates, &context(status)).unwrap();
+            assert_eq!(rendered.title, "Fix login");
+            assert_eq!(
+                rendered.body,
+                format!("{headline}\nBranch: forge/fix-login\nExecutor: CLAUDE_CODE")
+            );
+        }
+    }
+
+    #[test]
+    fn custom_templates_see_the_full_context() {
+        let templates = NotificationTemplates {
+            title: Some("[{{ status | upper }}] {{ title }}".into()),
+            body: Some(
+                "{{ executor }}:{{ variant }} {{ branch }} -> {{ target_branch }} \
+                 exit={{ exit_code }} {{ duration_seconds // 60 }}m \
+                 tokens={{ input_tokens + output_tokens }} \
+                 +{{ diff.insertions }}/-{{ diff.deletions }} in {{ diff.files_changed }} files \
+                 {{ url }}"
+                    .into(),
+            ),
+        };
+
+        let rendered = render_notification(&templates, &context("failed")).unwrap();
+        assert_eq!(rendered.title, "[FAILED] Fix login");
+        assert_eq!(
+            rendered.body,
+            "CLAUDE_CODE:PLAN forge/fix-login -> main exit=0 12m tokens=1500 \
+             +10/-2 in 3 files http://forge.example/projects/p/tasks/t"
+        );
+    }
+
+    #[test]
+    fn default_templates_describe_lifecycle_events() {
+        let templates = NotificationTemplates::default();
+
+        let mut merged = context("merged");
+        merged.event = NotificationEvent::PrMerged;
+        merged.details = json!({ "pr_number": 42, "pr_url": "https://github.com/o/r/pull/42" });
+        assert_eq!(
+            render_notification(&templates, &merged).unwrap().body,
+            "🎉 Pull request merged\nPull request: https://github.com/o/r/pull/42\n\
+             Branch: forge/fix-login\nExecutor: CLAUDE_CODE"
+        );
+
+        let mut approval = context("pending");
+        approval.event = NotificationEvent::ApprovalPending;
+        approval.details = json!({ "tool_name": "Bash" });
+        assert!(
+            render_notification(&templates, &approval)
+                .unwrap()
+                .body
+                .starts_with("⏳ Approval pending: Bash\nBranch:")
+        );
+    }
+
+    #[test]
+    fn blank_title_falls_back_to_task_title() {
+        let templates = NotificationTemplates {
+            title: Some("{{ missing }}".into()),
+            body: None,
+        };
+        let rendered = render_notification(&templates, &context("completed")).unwrap();
+        assert_eq!(rendered.title, "Fix login");
+    }
+
+    #[test]
+    fn validate_reports_syntax_errors() {
+        let templates = NotificationTemplates {
+            title: None,
+            body: Some("{% if status %}unterminated".into()),
+        };
+        let err = templates.validate().unwrap_err();
+        assert!(err.to_string().contains("body template"));
+    }
+
+    #[test]
+    fn parses_git_shortstat_output() {
+        assert_eq!(
+            parse_shortstat(" 3 files changed, 10 insertions(+), 2 deletions(-)\n"),
+            DiffStats {
+                files_changed: 3,
+                insertions: 10,
+                deletions: 2
+            }
+        );
+        assert_eq!(
+            parse_shortstat(" 1 file changed, 1 deletion(-)"),
+            DiffStats {
+                files_changed: 1,
+                insertions: 0,
+                deletions: 1
+            }
+        );
+        assert_eq!(parse_shortstat(""), DiffStats::default());
+    }
+
+    #[tokio::test]
+    async fn context_is_built_from_attempt_and_latest_coding_agent_run() {
+        let pool = test_support::pool().await;
+        let test_support::SeededAttempt {
+            project_id,
+            task_id,
+            attempt_id,
+        } = test_support::seed_attempt(&pool).await;
+        sqlx::query("UPDATE tasks SET description = 'Users are logged out' WHERE id = ?")
+            .bind(task_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE task_attempts SET target_branch = 'develop', input_tokens = 1200, output_tokens = 300
+              WHERE id = ?",
+        )
+        .bind(attempt_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let action = json!({
+            "typ": {
+                "type": "CodingAgentInitialRequest",
+                "prompt": "Fix login",
+                "executor_profile_id": { "executor": "CLAUDE_CODE", "variant": "PLAN" }
+            },
+            "next_action": null
+        });
+        sqlx::query(
+            "INSERT INTO execution_processes
+                 (id, task_attempt_id, run_reason, executor_action, status, exit_code, started_at, completed_at)
+             VALUES (?, ?, 'codingagent', ?, 'failed', 2, datetime('now', '-90 seconds'), datetime('now'))",
+        )
+        .bind(Uuid::new_v4())
+        .bind(attempt_id)
+        .bind(action.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let context = load_notification_context(&pool, attempt_id, None)
+            .await
+            .expect("context should load");
+
+        assert_eq!(context.project_id, project_id);
+        assert_eq!(context.task_id, task_id);
+        assert_eq!(context.description.as_deref(), Some("Users are logged out"));
+        assert_eq!(context.status, "failed");
+        assert_eq!(context.variant.as_deref(), Some("PLAN"));
+        assert_eq!(context.target_branch, "develop");
+        assert_eq!(context.exit_code, Some(2));
+        assert_eq!(context.duration_seconds, Some(90));
+        assert_eq!(context.input_tokens, Some(1200));
+        assert_eq!(context.output_tokens, Some(300));
+        assert_eq!(context.diff, None);
+        assert!(
+            context
+                .url
+                .ends_with(&format!("/projects/{project_id}/tasks/{task_id}"))
+        );
+    }
+}
@@ -0,0 +1,423 @@
+//! Omni Notification Worker
+//!
+//! Supervised background worker that drains the Omni notification queue.
+//! A circuit breaker pauses delivery while Omni keeps failing instead of burning
+//! every queued row's retry budget, and the supervisor restarts the loop if it
+//! panics or an operator resets it. A reset is only acted on between deliveries,
+//! so a notification being sent is never left leased with its attempt spent.
+//!
+//! Deliveries run in an `omni_delivery` span carrying the ID of the request that
+//! queued the notification, stored in its metadata.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use forge_core_services::services::forge_config::ForgeConfigService;
+use futures_util::FutureExt;
+use schemars::JsonSchema;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tokio::{
+    sync::{Mutex, Notify},
+    time::{Duration, Instant, sleep},
+};
+use ts_rs_forge::TS;
+
+use super::{OmniQueueStep, next_omni_retry_delay, process_next_omni_notification};
+
+/// Consecutive failures that trip the breaker from closed to open
+const BREAKER_FAILURE_THRESHOLD: u32 = 5;
+/// Cooldown after the first trip; doubles on every trip that follows a failed probe
+const BREAKER_BASE_COOLDOWN: Duration = Duration::from_secs(30);
+/// Upper bound for the open-state cooldown
+const BREAKER_MAX_COOLDOWN: Duration = Duration::from_secs(10 * 60);
+/// Pause after a database error before the worker polls again
+const WORKER_ERROR_BACKOFF: Duration = Duration::from_secs(15);
+/// Safety-net poll while the queue is empty; normally the worker is woken by
+/// `OmniWorkerHandle::wake` long before this elapses
+const WORKER_IDLE_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Short pause after a wakeup so a burst of state changes is drained in one pass
+/// and the triggering transaction has committed before the queue is read
+const WAKE_SETTLE_DELAY: Duration = Duration::from_millis(100);
+/// Pause before the supervisor restarts a worker that panicked
+const SUPERVISOR_RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// Breaker state: `closed` delivers normally, `open` pauses deliveries until the
+/// cooldown elapses, `half_open` lets a single probe decide between the two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// Snapshot of the Omni worker for `/health` and `/api/forge/omni/worker`
+#[derive(Debug, Clone, Serialize, TS, JsonSchema)]
+#[ts(crate = "ts_rs_forge")]
+pub struct OmniWorkerStatus {
+    pub running: bool,
+    pub circuit: CircuitState,
+    pub consecutive_failures: u32,
+    pub open_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub restarts: u32,
+}
+
+/// Closed → open → half-open circuit breaker around Omni deliveries
+#[derive(Debug)]
+struct CircuitBreaker {
+    state: CircuitState,
+    consecutive_failures: u32,
+    trips: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            trips: 0,
+            open_until: None,
+        }
+    }
+
+    /// Time left before deliveries may resume, moving open → half-open once the
+    /// cooldown has elapsed. `None` means the worker may proceed.
+    fn remaining_cooldown(&mut self, now: Instant) -> Option<Duration> {
+        if self.state != CircuitState::Open {
+            return None;
+        }
+        match self.open_until {
+            Some(until) if until > now => Some(until - now),
+            _ => {
+                self.state = CircuitState::HalfOpen;
+                self.open_until = None;
+                None
+            }
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.state = CircuitState::Closed;
+        self.consecutive_failures = 0;
+        self.trips = 0;
+        self.open_until = None;
+    }
+
+    fn record_failure(&mut self, now: Instant) {
+        self.consecutive_failures += 1;
+
+        let should_open = match self.state {
+            CircuitState::HalfOpen => true,
+            CircuitState::Closed => self.consecutive_failures >= BREAKER_FAILURE_THRESHOLD,
+            CircuitState::Open => false,
+        };
+
+        if should_open {
+            let cooldown = BREAKER_BASE_COOLDOWN
+                .saturating_mul(1u32 << self.trips.min(8))
+                .min(BREAKER_MAX_COOLDOWN);
+            self.trips += 1;
+            self.state = CircuitState::Open;
+            self.open_until = Some(now + cooldown);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct WorkerState {
+    breaker: CircuitBreaker,
+    running: bool,
+    open_until_utc: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    last_success_at: Option<DateTime<Utc>>,
+    restarts: u32,
+}
+
+struct Shared {
+    state: Mutex<WorkerState>,
+    reset: Notify,
+    wake: Notify,
+}
+
+/// Handle to the supervised Omni worker
+#[derive(Clone)]
+pub struct OmniWorkerHandle {
+    shared: Arc<Shared>,
+}
+
+impl OmniWorkerHandle {
+    /// Spawn the supervisor, which in turn spawns and restarts the worker loop
+    pub fn spawn(pool: SqlitePool, config: Arc<ForgeConfigService>) -> Self {
+        let handle = Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(WorkerState {
+                    breaker: CircuitBreaker::new(),
+                    running: false,
+                    open_until_utc: None,
+                    last_error: None,
+                    last_success_at: None,
+                    restarts: 0,
+                }),
+                reset: Notify::new(),
+                wake: Notify::new(),
+            }),
+        };
+
+        let supervisor = handle.clone();
+        tokio::spawn(async move { supervisor.supervise(pool, config).await });
+
+        handle
+    }
+
+    pub async fn status(&self) -> OmniWorkerStatus {
+        let state = self.shared.state.lock().await;
+        OmniWorkerStatus {
+            running: state.running,
+            circuit: state.breaker.state,
+            consecutive_failures: state.breaker.consecutive_failures,
+            open_until: state.open_until_utc,
+            last_error: state.last_error.clone(),
+            last_success_at: state.last_success_at,
+            restarts: state.restarts,
+        }
+    }
+
+    /// Wake the worker if it is waiting on an empty queue.
+    ///
+    /// Wakeups are coalesced: if the worker is busy, its next idle wait returns
+    /// immediately instead of sleeping.
+    pub fn wake(&self) {
+        self.shared.wake.notify_one();
+    }
+
+    /// Close the breaker and restart the worker loop without restarting the process.
+    ///
+    /// The loop restarts once its current delivery, if any, has finished.
+    pub async fn reset(&self) {
+        {
+            let mut state = self.shared.state.lock().await;
+            state.breaker = CircuitBreaker::new();
+            state.open_until_utc = None;
+            state.last_error = None;
+        }
+        self.shared.reset.notify_one();
+        tracing::info!("Omni worker reset requested");
+    }
+
+    async fn supervise(&self, pool: SqlitePool, config: Arc<ForgeConfigService>) {
+        loop {
+            let worker = self.clone();
+            let pool = pool.clone();
+            let config = config.clone();
+            let task = tokio::spawn(async move { worker.run(pool, config).await });
+            self.shared.state.lock().await.running = true;
+
+            // The loop only returns on its own when a reset was requested
+            let result = task.await;
+            self.shared.state.lock().await.running = false;
+            match result {
+                Ok(()) => tracing::info!("Restarting Omni worker after reset"),
+                Err(err) => {
+                    tracing::error!("Omni worker panicked, restarting: {err}");
+                    sleep(SUPERVISOR_RESTART_DELAY).await;
+                }
+            }
+
+            self.shared.state.lock().await.restarts += 1;
+        }
+    }
+
+    /// Deliver queued notifications until a reset is requested. Resets are only
+    /// observed between deliveries, never while one is in flight.
+    async fn run(&self, pool: SqlitePool, config: Arc<ForgeConfigService>) {
+        loop {
+            if self.reset_requested() {
+                return;
+            }
+
+            let cooldown = self
+                .shared
+                .state
+                .lock()
+                .await
+                .breaker
+                .remaining_cooldown(Instant::now());
+            if let Some(wait) = cooldown {
+                if !self.sleep_unless_reset(sleep(wait)).await {
+                    return;
+                }
+                continue;
+            }
+
+            let step = process_next_omni_notification(&pool, &config).await;
+            match step {
+                Ok(OmniQueueStep::Sent) => self.record_success().await,
+                Ok(OmniQueueStep::Skipped) => {}
+                // A sink that keeps failing must trip the breaker even while others succeed
+                Ok(OmniQueueStep::PartiallySent { error } | OmniQueueStep::Failed { error }) => {
+                    self.record_failure(error).await
+                }
+                Ok(OmniQueueStep::Idle) => {
+                    if !self.wait_for_work(&pool).await {
+                        return;
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Omni notification worker error: {err:?}");
+                    self.record_failure(err.to_string()).await;
+                    if !self.sleep_unless_reset(sleep(WORKER_ERROR_BACKOFF)).await {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Consume a reset requested while the worker was busy, without waiting
+    fn reset_requested(&self) -> bool {
+        // `notify_one` stores a permit when nobody is waiting, so a pending reset
+        // completes `notified()` on its first poll
+        self.shared.reset.notified().now_or_never().is_some()
+    }
+
+    /// Wait for `pause` to finish; false if a reset was requested first
+    async fn sleep_unless_reset(&self, pause: impl Future<Output = ()>) -> bool {
+        tokio::select! {
+            _ = self.shared.reset.notified() => false,
+            _ = pause => true,
+        }
+    }
+
+    /// Sleep until woken, until the next scheduled retry is due, or until the
+    /// safety-net poll interval elapses, whichever comes first. Returns false if
+    /// a reset was requested instead.
+    async fn wait_for_work(&self, pool: &SqlitePool) -> bool {
+        let idle = match next_omni_retry_delay(pool).await {
+            Ok(Some(due)) => due.min(WORKER_IDLE_POLL_INTERVAL),
+            Ok(None) => WORKER_IDLE_POLL_INTERVAL,
+            Err(err) => {
+                tracing::warn!("Failed to look up next Omni retry: {err:?}");
+                WORKER_IDLE_POLL_INTERVAL
+            }
+        };
+
+        tokio::select! {
+            _ = self.shared.wake.notified() => {
+                self.sleep_unless_reset(sleep(WAKE_SETTLE_DELAY)).await
+            }
+            _ = self.shared.reset.notified() => false,
+            _ = sleep(idle) => true,
+        }
+    }
+
+    async fn record_success(&self) {
+        let mut state = self.shared.state.lock().await;
+        if state.breaker.state != CircuitState::Closed {
+            tracing::info!("Omni delivery succeeded, closing circuit breaker");
+        }
+        state.breaker.record_success();
+        state.open_until_utc = None;
+        state.last_success_at = Some(Utc::now());
+    }
+
+    async fn record_failure(&self, error: String) {
+        let mut state = self.shared.state.lock().await;
+        let was_open = state.breaker.state == CircuitState::Open;
+        let now = Instant::now();
+        state.breaker.record_failure(now);
+        state.last_error = Some(error);
+
+        if !was_open
+            && state.breaker.state == CircuitState::Open
+            && let Some(until) = state.breaker.open_until
+        {
+            let cooldown = until - now;
+            state.open_until_utc = chrono::Duration::from_std(cooldown)
+                .ok()
+                .map(|cooldown| Utc::now() + cooldown);
+            tracing::warn!(
+                consecutive_failures = state.breaker.consecutive_failures,
+                cooldown_secs = cooldown.as_secs(),
+                "Omni circuit breaker opened, pausing deliveries"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaker_opens_after_threshold() {
+        let mut breaker = CircuitBreaker::new();
+        let now = Instant::now();
+
+        for _ in 0..BREAKER_FAILURE_THRESHOLD - 1 {
+            breaker.record_failure(now);
+            assert_eq!(breaker.state, CircuitState::Closed);
+        }
+        breaker.record_failure(now);
+        assert_eq!(breaker.state, CircuitState::Open);
+        assert_eq!(breaker.remaining_cooldown(now), Some(BREAKER_BASE_COOLDOWN));
+    }
+
+    #[test]
+    fn breaker_half_opens_after_cooldown_and_closes_on_success() {
+        let mut breaker = CircuitBreaker::new();
+        let now = Instant::now();
+        for _ in 0..BREAKER_FAILURE_THRESHOLD {
+            breaker.record_failure(now);
+        }
+
+        assert_eq!(
+            breaker.remaining_cooldown(now + BREAKER_BASE_COOLDOWN),
+            None
+        );
+        assert_eq!(breaker.state, CircuitState::HalfOpen);
+
+        breaker.record_success();
+        assert_eq!(breaker.state, CircuitState::Closed);
+        assert_eq!(breaker.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn failed_probe_reopens_with_longer_cooldown() {
+        let mut breaker = CircuitBreaker::new();
+        let now = Instant::now();
+        for _ in 0..BREAKER_FAILURE_THRESHOLD {
+            breaker.record_failure(now);
+        }
+
+        let probe_at = now + BREAKER_BASE_COOLDOWN;
+        assert_eq!(breaker.remaining_cooldown(probe_at), None);
+        breaker.record_failure(probe_at);
+
+        assert_eq!(breaker.state, CircuitState::Open);
+        assert_eq!(
+            breaker.remaining_cooldown(probe_at),
+            Some(BREAKER_BASE_COOLDOWN * 2)
+        );
+    }
+
+    #[test]
+    fn cooldown_is_capped() {
+        let mut breaker = CircuitBreaker::new();
+        let mut now = Instant::now();
+        for _ in 0..BREAKER_FAILURE_THRESHOLD {
+            breaker.record_failure(now);
+        }
+        for _ in 0..20 {
+            now += BREAKER_MAX_COOLDOWN;
+            breaker.remaining_cooldown(now);
+            breaker.record_failure(now);
+        }
+
+        assert_eq!(breaker.remaining_cooldown(now), Some(BREAKER_MAX_COOLDOWN));
+    }
+}
@@ -0,0 +1,166 @@
+//! `.genie` Change Tracking
+//!
+//! The upstream profile cache hot-reloads a workspace's executor profiles when its
+//! `.genie` files change. Forge watches the same folders for every workspace it
+//! loads profiles for, so readiness can report the active and failed watchers and
+//! metrics can count the reloads.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::time::{Duration, sleep};
+
+/// Changes within this window (one editor save is often several events) count once
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Workspaces whose profiles are cached, with the watch on their `.genie` folder
+#[derive(Clone, Default)]
+pub struct ProfileWatches {
+    workspaces: Arc<Mutex<HashMap<PathBuf, Watch>>>,
+}
+
+enum Watch {
+    /// The workspace has no `.genie` folder
+    Unwatched,
+    /// Watching, with the last error the watcher reported, if any
+    Active {
+        _watcher: RecommendedWatcher,
+        error: Arc<Mutex<Option<String>>>,
+    },
+    /// The watch could not be set up
+    Failed(String),
+}
+
+/// Watched folders and the ones whose watch is broken
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ProfileWatchStatus {
+    pub watched: usize,
+    /// `<.genie dir>: <error>` for each broken watch
+    pub failed: Vec<String>,
+}
+
+impl ProfileWatches {
+    /// Start watching `workspace_root/.genie` the first time the workspace is loaded
+    pub fn track(&self, workspace_root: &Path) {
+        let mut workspaces = self.workspaces.lock().unwrap_or_else(|e| e.into_inner());
+        if workspaces.contains_key(workspace_root) {
+            return;
+        }
+
+        let genie_dir = workspace_root.join(".genie");
+        let watch = if genie_dir.is_dir() {
+            match watch_genie_dir(&genie_dir) {
+                Ok(watch) => watch,
+                Err(e) => {
+                    tracing::warn!("Failed to watch {}: {}", genie_dir.display(), e);
+                    Watch::Failed(e.to_string())
+                }
+            }
+        } else {
+            Watch::Unwatched
+        };
+        workspaces.insert(workspace_root.to_path_buf(), watch);
+    }
+
+    /// Count the watched `.genie` folders and collect the broken watches
+    pub fn status(&self) -> ProfileWatchStatus {
+        let workspaces = self.workspaces.lock().unwrap_or_else(|e| e.into_inner());
+        let mut status = ProfileWatchStatus::default();
+        for (workspace_root, watch) in workspaces.iter() {
+            let error = match watch {
+                Watch::Unwatched => continue,
+                Watch::Active { error, .. } => {
+                    status.watched += 1;
+                    error.lock().unwrap_or_else(|e| e.into_inner()).clone()
+                }
+                Watch::Failed(error) => Some(error.clone()),
+            };
+            if let Some(error) = error {
+                let genie_dir = workspace_root.join(".genie");
+                status
+                    .failed
+                    .push(format!("{}: {error}", genie_dir.display()));
+            }
+        }
+        status.failed.sort();
+        status
+    }
+}
+
+fn watch_genie_dir(genie_dir: &Path) -> notify::Result<Watch> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let error = Arc::new(Mutex::new(None));
+    let watcher_error = error.clone();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if !event.kind.is_access() => {
+                let _ = tx.send(());
+            }
+            Ok(_) => {}
+            Err(e) => {
+                *watcher_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(e.to_string());
+            }
+        })?;
+    watcher.watch(genie_dir, RecursiveMode::Recursive)?;
+
+    // Ends when the watcher is dropped and the channel closes
+    tokio::spawn(async move {
+        while rx.recv().await.is_some() {
+            sleep(RELOAD_DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+            crate::metrics::record_profile_reload();
+        }
+    });
+
+    Ok(Watch::Active {
+        _watcher: watcher,
+        error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn status_counts_watched_folders_and_reports_broken_ones() {
+        let root =
+            std::env::temp_dir().join(format!("forge-profile-watch-{}", uuid::Uuid::new_v4()));
+        let with_genie = root.join("with-genie");
+        std::fs::create_dir_all(with_genie.join(".genie")).unwrap();
+        let without_genie = root.join("without-genie");
+        std::fs::create_dir_all(&without_genie).unwrap();
+
+        let watches = ProfileWatches::default();
+        watches.track(&with_genie);
+        watches.track(&without_genie);
+        assert_eq!(
+            watches.status(),
+            ProfileWatchStatus {
+                watched: 1,
+                failed: Vec::new(),
+            }
+        );
+
+        let broken = root.join("broken");
+        watches.workspaces.lock().unwrap().insert(
+            broken.clone(),
+            Watch::Failed("inotify limit reached".into()),
+        );
+        let status = watches.status();
+        assert_eq!(status.watched, 1);
+        assert_eq!(
+            status.failed,
+            [format!(
+                "{}: inotify limit reached",
+                broken.join(".genie").display()
+            )]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+//! Forge Schema Extensions
+//!
+//! Idempotent schema tweaks for forge-owned tables that are applied at startup.
+//! Base tables are created by upstream migrations; forge-app only adds the columns
+//! and indexes its own background workers depend on.
+
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+/// Apply all forge-app schema extensions
+pub async fn ensure_forge_schema(pool: &SqlitePool) -> Result<()> {
+    ensure_omni_queue_columns(pool).await?;
+    ensure_notification_sink_tables(pool).await?;
+    ensure_auth_session_table(pool).await?;
+    ensure_api_token_table(pool).await?;
+    Ok(())
+}
+
+/// Add retry and lease bookkeeping to the Omni notification queue
+async fn ensure_omni_queue_columns(pool: &SqlitePool) -> Result<()> {
+    add_column_if_missing(
+        pool,
+        "forge_omni_notifications",
+        "attempts",
+        "INTEGER NOT NULL DEFAULT 0",
+    )
+    .await?;
+    add_column_if_missing(pool, "forge_omni_notifications", "next_attempt_at", "TEXT").await?;
+    add_column_if_missing(pool, "forge_omni_notifications", "claimed_at", "TEXT").await?;
+    add_column_if_missing(
+        pool,
+        "forge_omni_notifications",
+        "execution_process_id",
+        "TEXT",
+    )
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_forge_omni_notifications_due
+             ON forge_omni_notifications (status, next_attempt_at)",
+    )
+    .execute(pool)
+    .await?;
+
+    // Process-level notifications are deduplicated on this index by the triggers
+    sqlx::query(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_forge_omni_notifications_process
+             ON forge_omni_notifications (execution_process_id, notification_type)
+             WHERE execution_process_id IS NOT NULL",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Per-project sink configuration and per-sink delivery status
+async fn ensure_notification_sink_tables(pool: &SqlitePool) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS forge_notification_settings (
+             project_id BLOB PRIMARY KEY,
+             settings   TEXT NOT NULL,
+             updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+         )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS forge_notification_deliveries (
+             notification_id TEXT NOT NULL,
+             sink_id         TEXT NOT NULL,
+             status          TEXT NOT NULL,
+             attempts        INTEGER NOT NULL DEFAULT 0,
+             error_message   TEXT,
+             delivered_at    TEXT,
+             updated_at      TEXT NOT NULL DEFAULT (datetime('now')),
+             PRIMARY KEY (notification_id, sink_id)
+         )",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Browser sessions issued after the GitHub device flow; only token hashes are stored
+async fn ensure_auth_session_table(pool: &SqlitePool) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS forge_auth_sessions (
+             token_hash   TEXT PRIMARY KEY,
+             github_login TEXT,
+             created_at   TEXT NOT NULL DEFAULT (datetime('now')),
+             last_seen_at TEXT NOT NULL DEFAULT (datetime('now')),
+             expires_at   TEXT NOT NULL
+         )",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Personal access tokens; `scopes` is a comma-separated list such as `read,tasks:write`
+async fn ensure_api_token_table(pool: &SqlitePool) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS forge_api_tokens (
+             id           BLOB PRIMARY KEY,
+             name         TEXT NOT NULL,
+             token_hash   TEXT NOT NULL UNIQUE,
+             token_prefix TEXT NOT NULL,
+             scopes       TEXT NOT NULL,
+             created_at   TEXT NOT NULL DEFAULT (datetime('now')),
+             last_used_at TEXT,
+             expires_at   TEXT,
+             revoked_at   TEXT
+         )",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Add a column to a table unless it already exists. Returns true when the column was added.
+async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<bool> {
+    let exists =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(1) FROM pragma_table_info(?) WHERE name = ?")
+            .bind(table)
+            .bind(column)
+            .fetch_one(pool)
+            .await?
+            > 0;
+
+    if exists {
+        return Ok(false);
+    }
+
+    sqlx::query(&format!(
+        "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+    ))
+    .execute(pool)
+    .await?;
+
+    tracing::info!("Added {table}.{column} column");
+    Ok(true)
+}
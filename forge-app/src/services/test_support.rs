@@ -0,0 +1,119 @@
+//! Database fixtures shared by the unit tests
+//!
+//! forge-core-db only migrates the database named by `DATABASE_URL`, so the upstream
+//! schema is migrated once per test binary into a template; every test then gets its
+//! own in-memory copy, opened from `SqliteConnectOptions`.
+
+use std::str::FromStr;
+
+use sqlx::{
+    SqliteConnection, SqlitePool,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+};
+use tokio::sync::{Mutex, OnceCell};
+use uuid::Uuid;
+
+use super::schema;
+
+/// Connection keeping the migrated template database alive
+static TEMPLATE: OnceCell<Mutex<SqliteConnection>> = OnceCell::const_new();
+
+/// A project with one `inprogress` task ("Fix login") and one attempt of it
+pub(crate) struct SeededAttempt {
+    pub project_id: Uuid,
+    pub task_id: Uuid,
+    pub attempt_id: Uuid,
+}
+
+/// A fresh in-memory database with the upstream and forge schemas
+pub(crate) async fn pool() -> SqlitePool {
+    let name = format!("forge-test-{}", Uuid::new_v4().simple());
+    // A single connection that never idles out keeps the database alive for the test
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .min_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(memory_options(&name))
+        .await
+        .expect("failed to open the test database");
+
+    let mut template = template().await.lock().await;
+    sqlx::query(&format!("VACUUM INTO '{}'", memory_uri(&name)))
+        .execute(&mut *template)
+        .await
+        .expect("failed to copy the template schema");
+    pool
+}
+
+pub(crate) async fn seed_attempt(pool: &SqlitePool) -> SeededAttempt {
+    let seeded = SeededAttempt {
+        project_id: Uuid::new_v4(),
+        task_id: Uuid::new_v4(),
+        attempt_id: Uuid::new_v4(),
+    };
+    sqlx::query("INSERT INTO projects (id, name, git_repo_path) VALUES (?, 'Forge', ?)")
+        .bind(seeded.project_id)
+        .bind(format!("/tmp/test-project-{}", seeded.project_id))
+        .execute(pool)
+        .await
+        .expect("failed to insert project row");
+    sqlx::query(
+        "INSERT INTO tasks (id, project_id, title, status) VALUES (?, ?, 'Fix login', 'inprogress')",
+    )
+    .bind(seeded.task_id)
+    .bind(seeded.project_id)
+    .execute(pool)
+    .await
+    .expect("failed to insert task row");
+    sqlx::query(
+        "INSERT INTO task_attempts (id, task_id, branch, target_branch, executor)
+         VALUES (?, ?, 'forge/fix-login', 'main', 'CLAUDE_CODE')",
+    )
+    .bind(seeded.attempt_id)
+    .bind(seeded.task_id)
+    .execute(pool)
+    .await
+    .expect("failed to insert task attempt row");
+    seeded
+}
+
+async fn template() -> &'static Mutex<SqliteConnection> {
+    TEMPLATE
+        .get_or_init(|| async {
+            // The only environment write in the test binary, made once before the
+            // first test database exists
+            unsafe {
+                std::env::set_var("DATABASE_URL", "sqlite::memory:");
+            }
+            let migrated = forge_core_db::DBService::new()
+                .await
+                .expect("failed to create db service with migrations")
+                .pool;
+            schema::ensure_forge_schema(&migrated)
+                .await
+                .expect("forge schema extensions should apply");
+
+            let name = format!("forge-test-template-{}", Uuid::new_v4().simple());
+            let template = memory_options(&name)
+                .connect()
+                .await
+                .expect("failed to open the template database");
+            sqlx::query(&format!("VACUUM INTO '{}'", memory_uri(&name)))
+                .execute(&migrated)
+                .await
+                .expect("failed to snapshot the migrated schema");
+            migrated.close().await;
+            Mutex::new(template)
+        })
+        .await
+}
+
+fn memory_uri(name: &str) -> String {
+    format!("file:{name}?mode=memory&cache=shared")
+}
+
+fn memory_options(name: &str) -> SqliteConnectOptions {
+    SqliteConnectOptions::from_str(&format!("sqlite:{}", memory_uri(name)))
+        .expect("in-memory database URLs are valid")
+}
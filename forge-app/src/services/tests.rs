@@ -13,16 +13,6 @@ use uuid::Uuid;
 
 use super::*;
 
-async fn setup_pool() -> SqlitePool {
-    unsafe {
-        std::env::set_var("DATABASE_URL", "sqlite::memory:");
-    }
-    let db_service = forge_core_db::DBService::new()
-        .await
-        .expect("failed to create db service with migrations");
-    db_service.pool
-}
-
 async fn insert_project(pool: &SqlitePool, project_id: Uuid) {
     let unique_path = format!("/tmp/test-project-{project_id}");
     sqlx::query("INSERT INTO projects (id, name, git_repo_path) VALUES (?, 'Forge Project', ?)")
@@ -73,7 +63,7 @@ fn pending_metadata(attempt_id: Uuid, project_id: Uuid) -> String {
 
 #[tokio::test]
 async fn omni_notification_skips_when_disabled() {
-    let pool = setup_pool().await;
+    let pool = test_support::pool().await;
     let project_id = Uuid::new_v4();
     insert_project(&pool, project_id).await;
     let (_task_id, attempt_id) = insert_task_graph(&pool, project_id).await;
@@ -85,7 +75,9 @@ async fn omni_notification_skips_when_disabled() {
         &config,
         &PendingNotification {
             id: "notif-1".into(),
+            notification_type: "execution_completed".into(),
             metadata: Some(pending_metadata(attempt_id, project_id)),
+            attempts: 0,
         },
     )
     .await
@@ -101,7 +93,7 @@ async fn omni_notification_skips_when_disabled() {
 
 #[tokio::test]
 async fn omni_notification_requires_host_configuration() {
-    let pool = setup_pool().await;
+    let pool = test_support::pool().await;
     let project_id = Uuid::new_v4();
     insert_project(&pool, ForgeConfigService::GLOBAL_PROJECT_ID).await;
     insert_project(&pool, project_id).await;
@@ -130,7 +122,9 @@ async fn omni_notification_requires_host_configuration() {
         &config_service,
         &PendingNotification {
             id: "notif-missing-host".into(),
+            notification_type: "execution_completed".into(),
             metadata: Some(pending_metadata(attempt_id, project_id)),
+            attempts: 0,
         },
     )
     .await
@@ -142,7 +136,7 @@ async fn omni_notification_requires_host_configuration() {
 #[tokio::test(flavor = "multi_thread")]
 #[serial_test::serial]
 async fn process_next_notification_marks_sent() {
-    let pool = setup_pool().await;
+    let pool = test_support::pool().await;
     let project_id = Uuid::new_v4();
     insert_project(&pool, ForgeConfigService::GLOBAL_PROJECT_ID).await;
     insert_project(&pool, project_id).await;
@@ -195,10 +189,10 @@ async fn process_next_notification_marks_sent() {
         std::env::set_var("PUBLIC_BASE_URL", "http://forge.example");
     }
 
-    let processed = process_next_omni_notification(&pool, &config_service)
+    let step = process_next_omni_notification(&pool, &config_service)
         .await
         .expect("processing should succeed");
-    assert!(processed);
+    assert_eq!(step, OmniQueueStep::Sent);
 
     let row: (String, Option<String>, Option<String>) = sqlx::query_as(
         "SELECT status, message, sent_at FROM forge_omni_notifications WHERE id = 'execution-1'",
@@ -222,9 +216,541 @@ async fn process_next_notification_marks_sent() {
     }
 }
 
+/// Enable Omni globally against a host that refuses connections so every send fails
+async fn configure_unreachable_omni(config_service: &ForgeConfigService) {
+    let settings = ForgeProjectSettings {
+        omni_enabled: true,
+        omni_config: Some(OmniConfig {
+            enabled: true,
+            host: Some("http://127.0.0.1:1".into()),
+            api_key: None,
+            instance: Some("forge-instance".into()),
+            recipient: Some("+15550001111".into()),
+            recipient_type: Some(RecipientType::PhoneNumber),
+        }),
+    };
+    config_service
+        .set_global_settings(&settings)
+        .await
+        .expect("should persist omni settings");
+}
+
+async fn queue_notification(pool: &SqlitePool, id: &str, task_id: Uuid, metadata: String) {
+    sqlx::query(
+        "INSERT INTO forge_omni_notifications (id, task_id, notification_type, recipient, message, status, metadata)
+         VALUES (?, ?, 'execution_completed', '', '', 'pending', ?)",
+    )
+    .bind(id)
+    .bind(task_id)
+    .bind(metadata)
+    .execute(pool)
+    .await
+    .expect("failed to queue notification");
+}
+
+#[tokio::test]
+async fn process_next_notification_schedules_retry_on_failure() {
+    let pool = test_support::pool().await;
+    let project_id = Uuid::new_v4();
+    insert_project(&pool, ForgeConfigService::GLOBAL_PROJECT_ID).await;
+    insert_project(&pool, project_id).await;
+    let (task_id, attempt_id) = insert_task_graph(&pool, project_id).await;
+
+    let config_service = ForgeConfigService::new(pool.clone());
+    configure_unreachable_omni(&config_service).await;
+    queue_notification(
+        &pool,
+        "retry-1",
+        task_id,
+        pending_metadata(attempt_id, project_id),
+    )
+    .await;
+
+    let step = process_next_omni_notification(&pool, &config_service)
+        .await
+        .expect("send failures are recorded on the row, not returned");
+    assert!(matches!(step, OmniQueueStep::Failed { .. }));
+
+    let (status, attempts, scheduled_in_future, error): (String, i64, bool, Option<String>) =
+        sqlx::query_as(
+            "SELECT status, attempts, next_attempt_at > datetime('now'), error_message
+               FROM forge_omni_notifications WHERE id = 'retry-1'",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("queue row remains accessible");
+
+    assert_eq!(status, "pending");
+    assert_eq!(attempts, 1);
+    assert!(scheduled_in_future);
+    assert!(error.is_some());
+
+    // The row is backing off, so the worker should find nothing due yet
+    let step = process_next_omni_notification(&pool, &config_service)
+        .await
+        .expect("empty queue should not error");
+    assert_eq!(step, OmniQueueStep::Idle);
+}
+
+#[tokio::test]
+async fn process_next_notification_dead_letters_after_max_attempts() {
+    let pool = test_support::pool().await;
+    let project_id = Uuid::new_v4();
+    insert_project(&pool, ForgeConfigService::GLOBAL_PROJECT_ID).await;
+    insert_project(&pool, project_id).await;
+    let (task_id, attempt_id) = insert_task_graph(&pool, project_id).await;
+
+    let config_service = ForgeConfigService::new(pool.clone());
+    configure_unreachable_omni(&config_service).await;
+    queue_notification(
+        &pool,
+        "dead-1",
+        task_id,
+        pending_metadata(attempt_id, project_id),
+    )
+    .await;
+    sqlx::query("UPDATE forge_omni_notifications SET attempts = ? WHERE id = 'dead-1'")
+        .bind(OMNI_MAX_ATTEMPTS - 1)
+        .execute(&pool)
+        .await
+        .expect("failed to seed attempts");
+
+    process_next_omni_notification(&pool, &config_service)
+        .await
+        .expect("processing should succeed");
+
+    let (status, attempts): (String, i64) =
+        sqlx::query_as("SELECT status, attempts FROM forge_omni_notifications WHERE id = 'dead-1'")
+            .fetch_one(&pool)
+            .await
+            .expect("queue row remains accessible");
+    assert_eq!(status, "dead_letter");
+    assert_eq!(attempts, OMNI_MAX_ATTEMPTS);
+
+    assert_eq!(
+        notification_history::retry_notification(&pool, "dead-1", None)
+            .await
+            .expect("requeue should succeed"),
+        notification_history::RetryOutcome::Requeued
+    );
+    assert_eq!(
+        notification_history::retry_notification(&pool, "dead-1", None)
+            .await
+            .expect("retrying a pending row should not error"),
+        notification_history::RetryOutcome::NotRetryable {
+            status: "pending".into()
+        }
+    );
+
+    let (status, attempts): (String, i64) =
+        sqlx::query_as("SELECT status, attempts FROM forge_omni_notifications WHERE id = 'dead-1'")
+            .fetch_one(&pool)
+            .await
+            .expect("queue row remains accessible");
+    assert_eq!(status, "pending");
+    assert_eq!(attempts, 0);
+}
+
+#[tokio::test]
+async fn crash_mid_send_leaves_lease_that_reaper_reclaims() {
+    let pool = test_support::pool().await;
+    let project_id = Uuid::new_v4();
+    insert_project(&pool, project_id).await;
+    let (task_id, attempt_id) = insert_task_graph(&pool, project_id).await;
+    queue_notification(
+        &pool,
+        "crash-1",
+        task_id,
+        pending_metadata(attempt_id, project_id),
+    )
+    .await;
+
+    // Worker claims the row, then the process dies before the final UPDATE
+    let claimed = claim_next_omni_notification(&pool)
+        .await
+        .expect("claim should succeed")
+        .expect("a pending row should be claimed");
+    assert_eq!(claimed.id, "crash-1");
+    drop(claimed);
+
+    assert!(
+        claim_next_omni_notification(&pool)
+            .await
+            .expect("claim should succeed")
+            .is_none(),
+        "a leased row must not be handed out twice"
+    );
+
+    // A fresh lease is left alone by the periodic reaper
+    let reclaimed = reclaim_expired_omni_leases(&pool, OMNI_LEASE_DURATION)
+        .await
+        .expect("reaper should run");
+    assert_eq!(reclaimed, 0);
+
+    sqlx::query(
+        "UPDATE forge_omni_notifications SET claimed_at = datetime('now', '-10 minutes') WHERE id = 'crash-1'",
+    )
+    .execute(&pool)
+    .await
+    .expect("failed to age lease");
+
+    let reclaimed = reclaim_expired_omni_leases(&pool, OMNI_LEASE_DURATION)
+        .await
+        .expect("reaper should run");
+    assert_eq!(reclaimed, 1);
+
+    let (status, attempts, claimed_at): (String, i64, Option<String>) = sqlx::query_as(
+        "SELECT status, attempts, claimed_at FROM forge_omni_notifications WHERE id = 'crash-1'",
+    )
+    .fetch_one(&pool)
+    .await
+    .expect("queue row remains accessible");
+    assert_eq!(status, "pending");
+    assert_eq!(attempts, 1);
+    assert!(claimed_at.is_none());
+
+    let reclaimed_row = claim_next_omni_notification(&pool)
+        .await
+        .expect("claim should succeed")
+        .expect("reclaimed row should be claimable again");
+    assert_eq!(reclaimed_row.id, "crash-1");
+    assert_eq!(reclaimed_row.attempts, 1);
+}
+
+#[tokio::test]
+async fn startup_sweep_reclaims_every_processing_row() {
+    let pool = test_support::pool().await;
+    let project_id = Uuid::new_v4();
+    insert_project(&pool, project_id).await;
+    let (task_id, attempt_id) = insert_task_graph(&pool, project_id).await;
+
+    // One row claimed by the previous run (SIGTERM mid-send), one left over from
+    // a build that predates the lease column
+    queue_notification(
+        &pool,
+        "stranded-1",
+        task_id,
+        pending_metadata(attempt_id, project_id),
+    )
+    .await;
+    claim_next_omni_notification(&pool)
+        .await
+        .expect("claim should succeed")
+        .expect("a pending row should be claimed");
+    queue_notification(
+        &pool,
+        "stranded-2",
+        task_id,
+        pending_metadata(attempt_id, project_id),
+    )
+    .await;
+    sqlx::query(
+        "UPDATE forge_omni_notifications SET status = 'processing', claimed_at = NULL WHERE id = 'stranded-2'",
+    )
+    .execute(&pool)
+    .await
+    .expect("failed to strand legacy row");
+
+    let reclaimed = reclaim_expired_omni_leases(&pool, Duration::ZERO)
+        .await
+        .expect("startup sweep should run");
+    assert_eq!(reclaimed, 2);
+
+    let processing: i64 = sqlx::query_scalar(
+        "SELECT COUNT(1) FROM forge_omni_notifications WHERE status = 'processing'",
+    )
+    .fetch_one(&pool)
+    .await
+    .expect("count should succeed");
+    assert_eq!(processing, 0);
+}
+
+#[tokio::test]
+async fn reclaiming_a_poisoned_row_dead_letters_it() {
+    let pool = test_support::pool().await;
+    let project_id = Uuid::new_v4();
+    insert_project(&pool, project_id).await;
+    let (task_id, attempt_id) = insert_task_graph(&pool, project_id).await;
+    queue_notification(
+        &pool,
+        "poison-1",
+        task_id,
+        pending_metadata(attempt_id, project_id),
+    )
+    .await;
+    sqlx::query(
+        "UPDATE forge_omni_notifications SET status = 'processing', attempts = ? WHERE id = 'poison-1'",
+    )
+    .bind(OMNI_MAX_ATTEMPTS - 1)
+    .execute(&pool)
+    .await
+    .expect("failed to seed crash-looping row");
+
+    reclaim_expired_omni_leases(&pool, Duration::ZERO)
+        .await
+        .expect("startup sweep should run");
+
+    let status: String =
+        sqlx::query_scalar("SELECT status FROM forge_omni_notifications WHERE id = 'poison-1'")
+            .fetch_one(&pool)
+            .await
+            .expect("queue row remains accessible");
+    assert_eq!(status, "dead_letter");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial_test::serial]
+async fn execution_events_wake_worker_well_before_safety_poll() {
+    let pool = test_support::pool().await;
+    let project_id = Uuid::new_v4();
+    insert_project(&pool, ForgeConfigService::GLOBAL_PROJECT_ID).await;
+    insert_project(&pool, project_id).await;
+    let (task_id, attempt_id) = insert_task_graph(&pool, project_id).await;
+
+    let config_service = Arc::new(ForgeConfigService::new(pool.clone()));
+    let server = MockServer::start_async().await;
+    let mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/api/v1/instance/forge-instance/send-text");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+                "success": true,
+                "message_id": "msg-wake",
+                "status": "queued",
+                "error": null
+            }));
+    });
+    let settings = ForgeProjectSettings {
+        omni_enabled: true,
+        omni_config: Some(OmniConfig {
+            enabled: true,
+            host: Some(server.base_url()),
+            api_key: None,
+            instance: Some("forge-instance".into()),
+            recipient: Some("+15550001111".into()),
+            recipient_type: Some(RecipientType::PhoneNumber),
+        }),
+    };
+    config_service
+        .set_global_settings(&settings)
+        .await
+        .expect("should persist omni settings");
+
+    // Let the worker drain the empty queue and settle into its idle wait
+    let worker = OmniWorkerHandle::spawn(pool.clone(), config_service.clone());
+    let (events, receiver) = broadcast::channel(16);
+    bridge_execution_events(receiver, worker);
+    sleep(Duration::from_millis(300)).await;
+
+    queue_notification(
+        &pool,
+        "wake-1",
+        task_id,
+        pending_metadata(attempt_id, project_id),
+    )
+    .await;
+    let queued_at = std::time::Instant::now();
+    // What the deployment's event stream carries when an execution process finishes
+    let patch: json_patch::Patch = serde_json::from_value(json!([{
+        "op": "replace",
+        "path": format!("/execution_processes/{}", Uuid::new_v4()),
+        "value": { "status": "completed" },
+    }]))
+    .expect("patch should deserialize");
+    events
+        .send(LogMsg::JsonPatch(patch))
+        .expect("the bridge should be listening");
+
+    let deadline = Duration::from_secs(3);
+    loop {
+        let status: String =
+            sqlx::query_scalar("SELECT status FROM forge_omni_notifications WHERE id = 'wake-1'")
+                .fetch_one(&pool)
+                .await
+                .expect("queue row remains accessible");
+        if status == "sent" {
+            break;
+        }
+        assert!(
+            queued_at.elapsed() < deadline,
+            "notification still '{status}' after {:?}",
+            queued_at.elapsed()
+        );
+        sleep(Duration::from_millis(25)).await;
+    }
+
+    assert!(queued_at.elapsed() < deadline);
+    mock.assert_async().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial_test::serial]
+async fn reset_lets_in_flight_delivery_finish() {
+    let pool = test_support::pool().await;
+    let project_id = Uuid::new_v4();
+    insert_project(&pool, ForgeConfigService::GLOBAL_PROJECT_ID).await;
+    insert_project(&pool, project_id).await;
+    let (task_id, attempt_id) = insert_task_graph(&pool, project_id).await;
+
+    let config_service = Arc::new(ForgeConfigService::new(pool.clone()));
+    let server = MockServer::start_async().await;
+    let mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/api/v1/instance/forge-instance/send-text");
+        then.status(200)
+            .delay(Duration::from_millis(600))
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+                "success": true,
+                "message_id": "msg-reset",
+                "status": "queued",
+                "error": null
+            }));
+    });
+    let settings = ForgeProjectSettings {
+        omni_enabled: true,
+        omni_config: Some(OmniConfig {
+            enabled: true,
+            host: Some(server.base_url()),
+            api_key: None,
+            instance: Some("forge-instance".into()),
+            recipient: Some("+15550001111".into()),
+            recipient_type: Some(RecipientType::PhoneNumber),
+        }),
+    };
+    config_service
+        .set_global_settings(&settings)
+        .await
+        .expect("should persist omni settings");
+
+    let worker = OmniWorkerHandle::spawn(pool.clone(), config_service.clone());
+    sleep(Duration::from_millis(300)).await;
+    queue_notification(
+        &pool,
+        "reset-1",
+        task_id,
+        pending_metadata(attempt_id, project_id),
+    )
+    .await;
+    worker.wake();
+
+    // Reset while Omni is still answering the send
+    sleep(Duration::from_millis(300)).await;
+    worker.reset().await;
+
+    let deadline = std::time::Instant::now() + Duration::from_secs(3);
+    let (status, attempts, claimed_at) = loop {
+        let row: (String, i64, Option<String>) = sqlx::query_as(
+            "SELECT status, attempts, claimed_at FROM forge_omni_notifications WHERE id = 'reset-1'",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("queue row remains accessible");
+        if row.0 != "pending" && row.0 != "processing" {
+            break row;
+        }
+        assert!(
+            std::time::Instant::now() < deadline,
+            "notification still '{}' after the reset",
+            row.0
+        );
+        sleep(Duration::from_millis(25)).await;
+    };
+    assert_eq!(status, "sent");
+    assert_eq!(
+        attempts, 0,
+        "the delivery must not be charged a failed attempt"
+    );
+    assert!(claimed_at.is_none(), "the lease must be released");
+    mock.assert_hits_async(1).await;
+
+    sleep(Duration::from_millis(100)).await;
+    let status = worker.status().await;
+    assert_eq!(status.restarts, 1);
+    assert!(status.running);
+}
+
+#[tokio::test]
+async fn next_retry_delay_tracks_earliest_backoff() {
+    let pool = test_support::pool().await;
+    let project_id = Uuid::new_v4();
+    insert_project(&pool, project_id).await;
+    let (task_id, attempt_id) = insert_task_graph(&pool, project_id).await;
+
+    assert_eq!(
+        next_omni_retry_delay(&pool)
+            .await
+            .expect("query should succeed"),
+        None
+    );
+
+    queue_notification(
+        &pool,
+        "later-1",
+        task_id,
+        pending_metadata(attempt_id, project_id),
+    )
+    .await;
+    sqlx::query(
+        "UPDATE forge_omni_notifications SET next_attempt_at = datetime('now', '+90 seconds') WHERE id = 'later-1'",
+    )
+    .execute(&pool)
+    .await
+    .expect("failed to schedule retry");
+
+    let delay = next_omni_retry_delay(&pool)
+        .await
+        .expect("query should succeed")
+        .expect("a scheduled retry should be reported");
+    assert!(delay > Duration::from_secs(80) && delay <= Duration::from_secs(91));
+}
+
 #[test]
-fn status_summary_includes_branch_and_executor() {
-    let summary = format_status_summary("completed", "forge-agent", "feature/auth");
+fn execution_process_patches_wake_the_worker() {
+    let process_patch: json_patch::Patch = serde_json::from_value(json!([
+        { "op": "replace", "path": "/execution_processes/abc", "value": { "status": "completed" } }
+    ]))
+    .expect("valid patch");
+    let task_patch: json_patch::Patch = serde_json::from_value(json!([
+        { "op": "replace", "path": "/tasks/abc", "value": { "status": "inreview" } }
+    ]))
+    .expect("valid patch");
+
+    assert!(touches_execution_processes(&process_patch));
+    assert!(!touches_execution_processes(&task_patch));
+}
+
+#[test]
+fn omni_retry_delay_grows_with_jitter_and_caps() {
+    for _ in 0..20 {
+        let first = omni_retry_delay(1);
+        assert!(first >= OMNI_RETRY_BASE_DELAY / 2 && first <= OMNI_RETRY_BASE_DELAY);
+
+        let third = omni_retry_delay(3);
+        assert!(third >= OMNI_RETRY_BASE_DELAY * 2 && third <= OMNI_RETRY_BASE_DELAY * 4);
+
+        let huge = omni_retry_delay(40);
+        assert!(huge >= OMNI_RETRY_MAX_DELAY / 2 && huge <= OMNI_RETRY_MAX_DELAY);
+    }
+}
+
+#[tokio::test]
+async fn status_summary_includes_branch_and_executor() {
+    let pool = test_support::pool().await;
+    let project_id = Uuid::new_v4();
+    insert_project(&pool, project_id).await;
+    let (_task_id, attempt_id) = insert_task_graph(&pool, project_id).await;
+
+    let mut context = load_notification_context(&pool, attempt_id, Some("completed"))
+        .await
+        .expect("context should load for a real attempt");
+    context.executor = "forge-agent".into();
+    context.branch = "feature/auth".into();
+
+    let summary = render_notification(&NotificationTemplates::default(), &context)
+        .expect("default templates render")
+        .body;
     assert!(summary.contains("forge-agent"));
     assert!(summary.contains("feature/auth"));
     assert!(summary.starts_with("✅"));
@@ -243,26 +769,26 @@ fn omni_base_url_respects_env_vars() {
         std::env::set_var("HOST", "10.0.0.1");
         std::env::set_var("BACKEND_PORT", "9999");
     }
-    assert_eq!(omni_base_url(), "https://forge.example.com");
+    assert_eq!(omni_base_url().unwrap(), "https://forge.example.com");
 
     unsafe {
         std::env::remove_var("PUBLIC_BASE_URL");
         std::env::set_var("HOST", "10.0.0.2");
         std::env::set_var("BACKEND_PORT", "9999");
     }
-    assert_eq!(omni_base_url(), "http://10.0.0.2:9999");
+    assert_eq!(omni_base_url().unwrap(), "http://10.0.0.2:9999");
 
     unsafe {
         std::env::remove_var("BACKEND_PORT");
         std::env::set_var("PORT", "8080");
     }
-    assert_eq!(omni_base_url(), "http://10.0.0.2:8080");
+    assert_eq!(omni_base_url().unwrap(), "http://10.0.0.2:8080");
 
     unsafe {
         std::env::remove_var("HOST");
         std::env::remove_var("PORT");
     }
-    assert_eq!(omni_base_url(), "http://127.0.0.1:8887");
+    assert_eq!(omni_base_url().unwrap(), "http://127.0.0.1:8887");
 
     unsafe {
         if let Some(url) = previous_public {
@@ -290,185 +816,80 @@ fn omni_base_url_respects_env_vars() {
 
 #[test]
 #[serial_test::serial]
-fn test_omni_base_url_rejects_javascript_scheme() {
-    unsafe {
-        std::env::set_var("PUBLIC_BASE_URL", "javascript:alert(1)");
-    }
-    // Should fall back to HOST/PORT when invalid scheme detected
-    let result = omni_base_url();
-    assert!(result.starts_with("http://"));
-    assert!(!result.contains("javascript"));
-    unsafe {
-        std::env::remove_var("PUBLIC_BASE_URL");
-    }
-}
-
-#[test]
-#[serial_test::serial]
-fn test_omni_base_url_rejects_data_scheme() {
-    unsafe {
-        std::env::set_var(
+fn omni_base_url_rejects_invalid_settings() {
+    for (name, value) in [
+        ("PUBLIC_BASE_URL", "javascript:alert(1)"),
+        (
             "PUBLIC_BASE_URL",
             "data:text/html,<script>alert(1)</script>",
-        );
-    }
-    // Should fall back to HOST/PORT when invalid scheme detected
-    let result = omni_base_url();
-    assert!(result.starts_with("http://"));
-    assert!(!result.contains("data:"));
-    unsafe {
-        std::env::remove_var("PUBLIC_BASE_URL");
-    }
-}
-
-#[test]
-#[serial_test::serial]
-fn test_omni_base_url_rejects_file_scheme() {
-    unsafe {
-        std::env::set_var("PUBLIC_BASE_URL", "file:///etc/passwd");
-    }
-    // Should fall back to HOST/PORT when invalid scheme detected
-    let result = omni_base_url();
-    assert!(result.starts_with("http://"));
-    assert!(!result.contains("file://"));
-    unsafe {
-        std::env::remove_var("PUBLIC_BASE_URL");
-    }
-}
-
-#[test]
-#[serial_test::serial]
-fn test_omni_base_url_rejects_invalid_url() {
-    unsafe {
-        std::env::set_var("PUBLIC_BASE_URL", "not-a-valid-url");
-    }
-    // Should fall back to HOST/PORT when URL parsing fails
-    let result = omni_base_url();
-    assert_eq!(result, "http://127.0.0.1:8887");
-    unsafe {
-        std::env::remove_var("PUBLIC_BASE_URL");
+        ),
+        ("PUBLIC_BASE_URL", "file:///etc/passwd"),
+        ("PUBLIC_BASE_URL", "not-a-valid-url"),
+        ("HOST", "evil.com\nX-Injected: header"),
+        ("BACKEND_PORT", "8080?evil=param"),
+    ] {
+        let previous = std::env::var(name).ok();
+        unsafe {
+            std::env::set_var(name, value);
+        }
+        let result = omni_base_url();
+        unsafe {
+            match previous {
+                Some(previous) => std::env::set_var(name, previous),
+                None => std::env::remove_var(name),
+            }
+        }
+        assert!(result.is_err(), "{name}={value:?} gave {result:?}");
     }
 }
 
-#[test]
-fn test_sanitize_hostname_removes_newlines() {
-    let malicious = "evil.com\nX-Injected: true";
-    let sanitized = sanitize_hostname(malicious);
-    assert_eq!(sanitized, "evil.comX-Injectedtrue");
-    assert!(!sanitized.contains('\n'));
-}
-
-#[test]
-fn test_sanitize_hostname_allows_ipv4() {
-    let ipv4 = "192.168.1.1";
-    let sanitized = sanitize_hostname(ipv4);
-    assert_eq!(sanitized, ipv4);
-}
-
-#[test]
-fn test_sanitize_hostname_allows_ipv6() {
-    let ipv6 = "[::1]";
-    let sanitized = sanitize_hostname(ipv6);
-    assert_eq!(sanitized, ipv6);
-
-    let ipv6_full = "[2001:db8::1]";
-    let sanitized_full = sanitize_hostname(ipv6_full);
-    assert_eq!(sanitized_full, ipv6_full);
-}
-
-#[test]
-fn test_sanitize_hostname_allows_valid_domains() {
-    let domain = "api.example-test.com";
-    let sanitized = sanitize_hostname(domain);
-    assert_eq!(sanitized, domain);
-}
-
-#[test]
-fn test_sanitize_hostname_removes_special_chars() {
-    let malicious = "evil.com?param=value";
-    let sanitized = sanitize_hostname(malicious);
-    assert_eq!(sanitized, "evil.comparamvalue");
-    assert!(!sanitized.contains('?'));
-}
-
-#[test]
-fn test_sanitize_port_removes_non_digits() {
-    let malicious = "8080?evil=param";
-    let sanitized = sanitize_port(malicious);
-    assert_eq!(sanitized, "8080");
-}
-
-#[test]
-fn test_sanitize_port_removes_newlines() {
-    let malicious = "8080\nX-Injected: true";
-    let sanitized = sanitize_port(malicious);
-    assert_eq!(sanitized, "8080");
-    assert!(!sanitized.contains('\n'));
-}
-
-#[test]
-fn test_sanitize_port_allows_valid_port() {
-    let valid = "8887";
-    let sanitized = sanitize_port(valid);
-    assert_eq!(sanitized, valid);
-}
+#[tokio::test]
+async fn configured_sinks_receive_notifications_without_omni() {
+    let pool = test_support::pool().await;
+    let project_id = Uuid::new_v4();
+    insert_project(&pool, project_id).await;
+    let (task_id, attempt_id) = insert_task_graph(&pool, project_id).await;
 
-#[test]
-#[serial_test::serial]
-fn test_omni_base_url_sanitizes_host_injection() {
-    unsafe {
-        std::env::remove_var("PUBLIC_BASE_URL");
-        std::env::set_var("HOST", "evil.com\nX-Injected: header");
-        std::env::set_var("BACKEND_PORT", "8080");
-    }
-    let result = omni_base_url();
-    assert_eq!(result, "http://evil.comX-Injectedheader:8080");
-    assert!(!result.contains('\n'));
-    unsafe {
-        std::env::remove_var("HOST");
-        std::env::remove_var("BACKEND_PORT");
-    }
-}
+    let server = MockServer::start_async().await;
+    let mock = server.mock(|when, then| {
+        when.method(POST).path("/hooks/forge");
+        then.status(200);
+    });
+    let settings = notification_sinks::NotificationSettings {
+        sinks: vec![notification_sinks::NotificationSinkConfig {
+            id: "project-webhook".into(),
+            enabled: true,
+            sink: notification_sinks::NotificationSinkKind::Webhook {
+                url: server.url("/hooks/forge"),
+                secret: None,
+            },
+            has_secret: false,
+        }],
+        ..Default::default()
+    };
+    notification_sinks::save_notification_settings(&pool, Some(project_id), &settings)
+        .await
+        .expect("should persist sink settings");
 
-#[test]
-#[serial_test::serial]
-fn test_omni_base_url_sanitizes_port_injection() {
-    unsafe {
-        std::env::remove_var("PUBLIC_BASE_URL");
-        std::env::set_var("HOST", "localhost");
-        std::env::set_var("BACKEND_PORT", "8080?evil=param");
-    }
-    let result = omni_base_url();
-    assert_eq!(result, "http://localhost:8080");
-    assert!(!result.contains('?'));
-    unsafe {
-        std::env::remove_var("HOST");
-        std::env::remove_var("BACKEND_PORT");
-    }
-}
+    queue_notification(
+        &pool,
+        "sink-1",
+        task_id,
+        pending_metadata(attempt_id, project_id),
+    )
+    .await;
 
-#[test]
-#[serial_test::serial]
-fn test_omni_base_url_accepts_valid_https() {
-    unsafe {
-        std::env::set_var("PUBLIC_BASE_URL", "https://secure.example.com");
-    }
-    let result = omni_base_url();
-    assert_eq!(result, "https://secure.example.com");
-    unsafe {
-        std::env::remove_var("PUBLIC_BASE_URL");
-    }
-}
+    let config_service = ForgeConfigService::new(pool.clone());
+    let step = process_next_omni_notification(&pool, &config_service)
+        .await
+        .expect("processing should succeed");
+    assert_eq!(step, OmniQueueStep::Sent);
+    mock.assert_async().await;
 
-#[test]
-#[serial_test::serial]
-fn test_omni_base_url_accepts_valid_http() {
-    unsafe {
-        std::env::set_var("PUBLIC_BASE_URL", "http://local.example.com");
-    }
-    let result = omni_base_url();
-    assert_eq!(result, "http://local.example.com");
-    unsafe {
-        std::env::remove_var("PUBLIC_BASE_URL");
-    }
+    let deliveries = notification_sinks::list_notification_deliveries(&pool, "sink-1")
+        .await
+        .expect("deliveries should be recorded");
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].sink_id, "project-webhook");
+    assert_eq!(deliveries[0].status, "sent");
 }
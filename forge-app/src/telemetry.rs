@@ -0,0 +1,246 @@
+//! Task Lifecycle Traces
+//!
+//! With `[telemetry] otlp_endpoint` (`OTEL_EXPORTER_OTLP_ENDPOINT`, `--otlp-endpoint`)
+//! set, every task attempt becomes one trace, exported over OTLP/HTTP:
+//!
+//! - `create_and_start` / `create_attempt`: the request that created the attempt,
+//!   the root of the trace
+//! - `setup_script`, `coding_agent`, `follow_up` and `cleanup_script`: finished
+//!   execution processes
+//! - `follow_up_request`, `merge`, `push`, `create_pr` and `attach_pr`: the API calls
+//!   acting on the attempt
+//! - `notification`: each Omni delivery pass for the attempt
+//!
+//! The phases happen minutes or days apart, so they are not nested tracing spans.
+//! Each is recorded once it has finished, with its real start and end times, under
+//! a trace ID equal to the attempt ID; Jaeger finds a trace by pasting the attempt
+//! ID without dashes. For a local collector:
+//!
+//! ```sh
+//! docker run --rm -p 16686:16686 -p 4318:4318 jaegertracing/all-in-one
+//! forge --otlp-endpoint http://localhost:4318
+//! ```
+
+use std::{sync::OnceLock, time::SystemTime};
+
+use anyhow::{Context as _, Result, bail};
+use chrono::{DateTime, Utc};
+use opentelemetry::{
+    Context, KeyValue, Value,
+    trace::{
+        Span as _, SpanContext, SpanId, SpanKind, Status, TraceContextExt, TraceFlags, TraceId,
+        TraceState, Tracer as _, TracerProvider as _,
+    },
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    Resource,
+    trace::{SdkTracer, SdkTracerProvider},
+};
+use uuid::Uuid;
+
+pub const DEFAULT_SERVICE_NAME: &str = "forge";
+const TRACES_PATH: &str = "/v1/traces";
+
+static TRACER: OnceLock<SdkTracer> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelemetrySettings {
+    /// Base URL of an OTLP/HTTP collector, e.g. `http://localhost:4318`
+    pub otlp_endpoint: String,
+    pub service_name: String,
+}
+
+impl TelemetrySettings {
+    /// `None` without an endpoint
+    pub(crate) fn resolve(
+        otlp_endpoint: Option<String>,
+        service_name: Option<String>,
+    ) -> Result<Option<Self>> {
+        let Some(raw) = otlp_endpoint else {
+            return Ok(None);
+        };
+        let url =
+            url::Url::parse(&raw).with_context(|| format!("Invalid otlp_endpoint '{raw}'"))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            bail!("otlp_endpoint must be an http or https URL, got '{raw}'");
+        }
+
+        Ok(Some(Self {
+            otlp_endpoint: raw.trim_end_matches('/').to_string(),
+            service_name: service_name.unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string()),
+        }))
+    }
+
+    fn traces_url(&self) -> String {
+        if self.otlp_endpoint.ends_with(TRACES_PATH) {
+            self.otlp_endpoint.clone()
+        } else {
+            format!("{}{TRACES_PATH}", self.otlp_endpoint)
+        }
+    }
+}
+
+/// Flushes the spans still queued and stops exporting when dropped. Install and
+/// drop it outside the async runtime: the exporter uses a blocking HTTP client.
+pub struct TelemetryGuard {
+    provider: SdkTracerProvider,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            tracing::warn!("Failed to flush lifecycle traces: {}", e);
+        }
+    }
+}
+
+/// Start exporting lifecycle spans; only the first call in a process takes effect
+pub fn install(settings: &TelemetrySettings) -> Result<TelemetryGuard> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(settings.traces_url())
+        .build()
+        .context("Failed to create the OTLP exporter")?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(settings.service_name.clone())
+                .build(),
+        )
+        .build();
+
+    let _ = TRACER.set(provider.tracer("forge-app"));
+    tracing::info!(
+        "Exporting task lifecycle traces to {}",
+        settings.otlp_endpoint
+    );
+    Ok(TelemetryGuard { provider })
+}
+
+/// Whether lifecycle spans are exported; lets callers skip gathering their details
+pub fn enabled() -> bool {
+    TRACER.get().is_some()
+}
+
+/// A finished phase of a task attempt, recorded as a span of the attempt's trace
+pub struct Phase {
+    name: &'static str,
+    started_at: SystemTime,
+    finished_at: SystemTime,
+    attributes: Vec<KeyValue>,
+    error: Option<String>,
+}
+
+impl Phase {
+    pub fn new(name: &'static str, started_at: DateTime<Utc>, finished_at: DateTime<Utc>) -> Self {
+        Self {
+            name,
+            started_at: started_at.into(),
+            finished_at: finished_at.max(started_at).into(),
+            attributes: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn attribute(mut self, key: &'static str, value: impl Into<Value>) -> Self {
+        self.attributes.push(KeyValue::new(key, value));
+        self
+    }
+
+    pub fn failed(mut self, error: impl Into<String>) -> Self {
+        self.error = Some(error.into());
+        self
+    }
+
+    /// Record the phase as a child of the attempt's root span
+    pub fn record(self, task_attempt_id: Uuid) {
+        self.export(task_attempt_id, false);
+    }
+
+    /// Record the phase as the attempt's root span
+    pub fn record_as_root(self, task_attempt_id: Uuid) {
+        self.export(task_attempt_id, true);
+    }
+
+    fn export(mut self, task_attempt_id: Uuid, as_root: bool) {
+        let Some(tracer) = TRACER.get() else {
+            return;
+        };
+
+        self.attributes.push(KeyValue::new(
+            "forge.task_attempt_id",
+            task_attempt_id.to_string(),
+        ));
+        let mut builder = tracer
+            .span_builder(self.name)
+            .with_kind(SpanKind::Internal)
+            .with_start_time(self.started_at)
+            .with_attributes(self.attributes)
+            .with_status(match self.error {
+                Some(error) => Status::error(error),
+                None => Status::Ok,
+            });
+
+        let root = root_span_context(task_attempt_id);
+        let parent = if as_root {
+            builder = builder
+                .with_trace_id(root.trace_id())
+                .with_span_id(root.span_id());
+            Context::new()
+        } else {
+            Context::new().with_remote_span_context(root)
+        };
+        tracer
+            .build_with_context(builder, &parent)
+            .end_with_timestamp(self.finished_at);
+    }
+}
+
+/// Same trace and root span for every phase of an attempt, however far apart
+fn root_span_context(task_attempt_id: Uuid) -> SpanContext {
+    let bytes = task_attempt_id.into_bytes();
+    let mut span_id = [0; 8];
+    span_id.copy_from_slice(&bytes[8..]);
+    SpanContext::new(
+        TraceId::from_bytes(bytes),
+        SpanId::from_bytes(span_id),
+        TraceFlags::SAMPLED,
+        true,
+        TraceState::default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_phase_of_an_attempt_shares_its_trace() {
+        let attempt = Uuid::new_v4();
+        let root = root_span_context(attempt);
+        assert_eq!(root, root_span_context(attempt));
+        assert_eq!(root.trace_id().to_string(), attempt.simple().to_string());
+        assert!(root.is_valid());
+        assert!(root.is_sampled());
+    }
+
+    #[test]
+    fn endpoints_point_at_the_traces_path() {
+        let settings = TelemetrySettings::resolve(Some("http://localhost:4318/".into()), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(settings.traces_url(), "http://localhost:4318/v1/traces");
+        assert_eq!(settings.service_name, DEFAULT_SERVICE_NAME);
+
+        let settings =
+            TelemetrySettings::resolve(Some("https://otel.example.com/v1/traces".into()), None)
+                .unwrap()
+                .unwrap();
+        assert_eq!(settings.traces_url(), "https://otel.example.com/v1/traces");
+
+        assert!(TelemetrySettings::resolve(None, None).unwrap().is_none());
+        assert!(TelemetrySettings::resolve(Some("localhost:4318".into()), None).is_err());
+    }
+}
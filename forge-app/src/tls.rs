@@ -0,0 +1,385 @@
+//! TLS Serving
+//!
+//! Optional HTTPS for `run_server_with_readiness`, for instances reachable beyond
+//! localhost (`HOST=0.0.0.0`) where session cookies and API tokens must not travel in
+//! cleartext. Certificates come from PEM files (`FORGE_TLS_CERT` / `FORGE_TLS_KEY`) and
+//! are reloaded when those files change, so renewals need no restart. With
+//! `FORGE_TLS_SELF_SIGNED` a certificate is generated once under the asset directory
+//! for quick LAN use; it covers localhost, the hostname, the LAN address and any
+//! `FORGE_TLS_SELF_SIGNED_NAMES`. `FORGE_TLS_REDIRECT_PORT` adds a plain HTTP listener
+//! that redirects to HTTPS.
+
+use std::{
+    ffi::OsString,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use axum::{
+    Router,
+    extract::{Request, State},
+    http::{StatusCode, Uri, header},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_server::tls_rustls::RustlsConfig;
+use notify::{RecursiveMode, Watcher};
+
+/// Wait for writes to settle before reloading; renewals often replace cert and key separately
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsSettings {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// Generate a self-signed certificate at the paths when they do not exist yet
+    pub self_signed: bool,
+    /// Extra DNS names and IPs the generated certificate is valid for
+    pub self_signed_names: Vec<String>,
+    /// Plain HTTP port that redirects to the HTTPS listener
+    pub redirect_port: Option<u16>,
+}
+
+impl TlsSettings {
+    /// `None` when TLS is not configured; errors on half-configured settings
+    pub(crate) fn resolve(
+        cert: Option<PathBuf>,
+        key: Option<PathBuf>,
+        self_signed: bool,
+        self_signed_names: Vec<String>,
+        redirect_port: Option<u16>,
+        asset_dir: &Path,
+    ) -> Result<Option<Self>> {
+        if !self_signed && !self_signed_names.is_empty() {
+            bail!("Self-signed certificate names require self-signed mode");
+        }
+
+        let (cert_path, key_path) = match (cert, key) {
+            (Some(cert), Some(key)) => (cert, key),
+            (None, None) if self_signed => (
+                asset_dir.join("tls").join("self-signed-cert.pem"),
+                asset_dir.join("tls").join("self-signed-key.pem"),
+            ),
+            (None, None) => {
+                if redirect_port.is_some() {
+                    bail!("A TLS redirect port requires a certificate or self-signed mode");
+                }
+                return Ok(None);
+            }
+            _ => bail!("The TLS certificate and key must be configured together"),
+        };
+
+        Ok(Some(Self {
+            cert_path,
+            key_path,
+            self_signed,
+            self_signed_names,
+            redirect_port,
+        }))
+    }
+}
+
+/// Load the certificate, generating a self-signed one first when requested
+pub async fn load_rustls_config(settings: &TlsSettings, host: &str) -> Result<RustlsConfig> {
+    // Several dependencies enable rustls; pin the provider so the choice is not ambiguous
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    if settings.self_signed && !(settings.cert_path.exists() && settings.key_path.exists()) {
+        generate_self_signed(
+            &settings.cert_path,
+            &settings.key_path,
+            host,
+            &settings.self_signed_names,
+        )?;
+    }
+
+    RustlsConfig::from_pem_file(&settings.cert_path, &settings.key_path)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to load TLS certificate {} and key {}",
+                settings.cert_path.display(),
+                settings.key_path.display()
+            )
+        })
+}
+
+/// Write a self-signed certificate valid for localhost, the machine's hostname, `host`
+/// (or the LAN address when bound to all interfaces) and `extra_names`
+pub fn generate_self_signed(
+    cert_path: &Path,
+    key_path: &Path,
+    host: &str,
+    extra_names: &[String],
+) -> Result<()> {
+    let mut names = vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ];
+    if let Ok(hostname) = std::env::var("HOSTNAME")
+        && !hostname.is_empty()
+    {
+        names.push(hostname);
+    }
+    match host.parse::<IpAddr>() {
+        Ok(ip) if ip.is_unspecified() => {
+            names.extend(lan_addresses(ip).iter().map(IpAddr::to_string))
+        }
+        Ok(_) => names.push(host.to_string()),
+        Err(_) => {}
+    }
+    names.extend(extra_names.iter().cloned());
+    let mut seen = std::collections::HashSet::new();
+    names.retain(|name| seen.insert(name.to_ascii_lowercase()));
+
+    let certified = rcgen::generate_simple_self_signed(names.clone())
+        .context("Failed to generate self-signed certificate")?;
+
+    for path in [cert_path, key_path] {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+    }
+    std::fs::write(cert_path, certified.cert.pem())
+        .with_context(|| format!("Failed to write {}", cert_path.display()))?;
+    write_private_key(key_path, &certified.key_pair.serialize_pem())?;
+
+    tracing::warn!(
+        "Generated a self-signed TLS certificate for {} at {}; browsers will ask you to trust it",
+        names.join(", "),
+        cert_path.display()
+    );
+    Ok(())
+}
+
+/// Addresses other machines reach this one at when it listens on `unspecified`: the
+/// source address of the default route, found without sending anything
+fn lan_addresses(unspecified: IpAddr) -> Vec<IpAddr> {
+    let mut probes = vec![(
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+    )];
+    if unspecified.is_ipv6() {
+        probes.push((
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        ));
+    }
+
+    probes
+        .into_iter()
+        .filter_map(|(bind, target)| {
+            let socket = UdpSocket::bind((bind, 0)).ok()?;
+            socket.connect((target, 9)).ok()?;
+            Some(socket.local_addr().ok()?.ip())
+        })
+        .filter(|ip| !ip.is_loopback() && !ip.is_unspecified())
+        .collect()
+}
+
+#[cfg(unix)]
+fn write_private_key(path: &Path, pem: &str) -> Result<()> {
+    use std::{io::Write, os::unix::fs::OpenOptionsExt};
+
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut file| file.write_all(pem.as_bytes()))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn write_private_key(path: &Path, pem: &str) -> Result<()> {
+    std::fs::write(path, pem).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Reload the certificate whenever the cert or key file changes. A failed reload
+/// keeps serving the previous certificate.
+pub fn watch_certificate_files(config: RustlsConfig, settings: &TlsSettings) -> Result<()> {
+    let cert_path = settings.cert_path.clone();
+    let key_path = settings.key_path.clone();
+    let watched: Vec<OsString> = [&cert_path, &key_path]
+        .iter()
+        .filter_map(|path| path.file_name().map(OsString::from))
+        .collect();
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event
+            && !event.kind.is_access()
+            && event.paths.iter().any(|path| {
+                path.file_name()
+                    .is_some_and(|name| watched.iter().any(|watched| watched == name))
+            })
+        {
+            let _ = tx.send(());
+        }
+    })?;
+
+    // Watch the directories: renewals usually replace the files rather than edit them
+    for path in [&cert_path, &key_path] {
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", dir.display()))?;
+    }
+
+    tokio::spawn(async move {
+        let _watcher = watcher;
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(RELOAD_DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+
+            match config.reload_from_pem_file(&cert_path, &key_path).await {
+                Ok(()) => tracing::info!("Reloaded TLS certificate {}", cert_path.display()),
+                Err(e) => tracing::error!(
+                    "Failed to reload TLS certificate {}; keeping the previous one: {}",
+                    cert_path.display(),
+                    e
+                ),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Plain HTTP router that sends every request to the same host on `https_port`
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new()
+        .fallback(redirect_to_https)
+        .with_state(https_port)
+}
+
+async fn redirect_to_https(State(https_port): State<u16>, request: Request) -> Response {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+    match https_url(host, https_port, request.uri()) {
+        Some(url) => Redirect::permanent(&url).into_response(),
+        None => (StatusCode::BAD_REQUEST, "Missing or invalid Host header").into_response(),
+    }
+}
+
+fn https_url(host: Option<&str>, https_port: u16, uri: &Uri) -> Option<String> {
+    let authority: axum::http::uri::Authority = host?.parse().ok()?;
+    let path = uri
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+    let port = if https_port == 443 {
+        String::new()
+    } else {
+        format!(":{https_port}")
+    };
+    Some(format!("https://{}{port}{path}", authority.host()))
+}
+
+/// Serve the redirect listener until shutdown
+pub async fn serve_https_redirect(
+    listener: tokio::net::TcpListener,
+    https_port: u16,
+    shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    tracing::info!(
+        "Redirecting HTTP on {} to HTTPS port {}",
+        listener.local_addr()?,
+        https_port
+    );
+    axum::serve(listener, redirect_router(https_port))
+        .with_graceful_shutdown(shutdown)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_need_both_paths_or_self_signed() {
+        let assets = Path::new("/var/forge");
+
+        assert_eq!(
+            TlsSettings::resolve(None, None, false, Vec::new(), None, assets).unwrap(),
+            None
+        );
+        assert!(
+            TlsSettings::resolve(
+                Some("cert.pem".into()),
+                None,
+                false,
+                Vec::new(),
+                None,
+                assets
+            )
+            .is_err()
+        );
+        assert!(TlsSettings::resolve(None, None, false, Vec::new(), Some(8080), assets).is_err());
+        assert!(
+            TlsSettings::resolve(None, None, false, vec!["forge.lan".into()], None, assets)
+                .is_err()
+        );
+
+        let settings = TlsSettings::resolve(None, None, true, Vec::new(), Some(8080), assets)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            settings.cert_path,
+            Path::new("/var/forge/tls/self-signed-cert.pem")
+        );
+        assert_eq!(settings.redirect_port, Some(8080));
+    }
+
+    #[test]
+    fn redirects_keep_host_path_and_query() {
+        let uri: Uri = "/api/tasks?project_id=1".parse().unwrap();
+
+        assert_eq!(
+            https_url(Some("forge.lan:8080"), 8887, &uri).as_deref(),
+            Some("https://forge.lan:8887/api/tasks?project_id=1")
+        );
+        assert_eq!(
+            https_url(Some("192.168.1.20"), 443, &"/".parse().unwrap()).as_deref(),
+            Some("https://192.168.1.20/")
+        );
+        assert_eq!(https_url(None, 8887, &uri), None);
+    }
+
+    #[tokio::test]
+    async fn self_signed_certificates_load_and_reload() {
+        let dir = std::env::temp_dir().join(format!("forge-tls-{}", uuid::Uuid::new_v4()));
+        let settings = TlsSettings {
+            cert_path: dir.join("cert.pem"),
+            key_path: dir.join("key.pem"),
+            self_signed: true,
+            self_signed_names: vec!["forge.lan".into(), "192.168.1.20".into()],
+            redirect_port: None,
+        };
+
+        let config = load_rustls_config(&settings, "0.0.0.0").await.unwrap();
+        let first = std::fs::read(&settings.cert_path).unwrap();
+
+        // Existing files are reused so a trusted certificate stays trusted
+        load_rustls_config(&settings, "0.0.0.0").await.unwrap();
+        assert_eq!(std::fs::read(&settings.cert_path).unwrap(), first);
+
+        generate_self_signed(&settings.cert_path, &settings.key_path, "127.0.0.1", &[]).unwrap();
+        config
+            .reload_from_pem_file(&settings.cert_path, &settings.key_path)
+            .await
+            .unwrap();
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
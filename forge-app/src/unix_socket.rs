@@ -0,0 +1,244 @@
+//! Unix Socket Listener
+//!
+//! Local tooling (MCP bridges, editor plugins, the npx wrapper) can reach Forge over a
+//! Unix domain socket, next to the TCP listener or instead of it (`FORGE_SOCKET_ONLY`).
+//! The socket file is created with `FORGE_SOCKET_MODE` permissions (default `0600`), and
+//! those permissions are the access control: requests arriving on the socket carry
+//! [`LocalSocket`] and skip the session check.
+
+use std::{future::Future, path::PathBuf};
+
+use anyhow::{Context, Result, bail};
+use axum::Router;
+use tokio::task::JoinHandle;
+
+#[cfg(unix)]
+use crate::middleware::auth::LocalSocket;
+
+const DEFAULT_SOCKET_MODE: u32 = 0o600;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketSettings {
+    pub path: PathBuf,
+    /// Serve only on the socket, without binding TCP
+    pub exclusive: bool,
+    /// Permission bits applied to the socket file
+    pub mode: u32,
+}
+
+impl SocketSettings {
+    /// `None` without a socket path
+    pub(crate) fn resolve(
+        path: Option<PathBuf>,
+        exclusive: bool,
+        mode: Option<&str>,
+    ) -> Result<Option<Self>> {
+        let Some(path) = path else {
+            if exclusive {
+                bail!("Socket-only mode requires a socket path (FORGE_SOCKET or --socket)");
+            }
+            return Ok(None);
+        };
+
+        let mode = match mode {
+            Some(raw) => u32::from_str_radix(raw.trim().trim_start_matches("0o"), 8)
+                .ok()
+                .filter(|mode| *mode <= 0o777)
+                .with_context(|| {
+                    format!("Invalid socket mode '{raw}'; expected octal, e.g. 660")
+                })?,
+            None => DEFAULT_SOCKET_MODE,
+        };
+
+        Ok(Some(Self {
+            path,
+            exclusive,
+            mode,
+        }))
+    }
+}
+
+/// Bind the socket and serve `app` on it until `shutdown` resolves, then remove the file
+#[cfg(unix)]
+pub fn start(
+    settings: &SocketSettings,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<JoinHandle<Result<()>>> {
+    let listener = bind(settings)?;
+    tracing::info!(
+        "Forge app listening on unix:{} (mode {:o})",
+        settings.path.display(),
+        settings.mode
+    );
+
+    let path = settings.path.clone();
+    let app = app.layer(axum::Extension(LocalSocket));
+    Ok(tokio::spawn(async move {
+        let served = axum::serve(listener, app.into_make_service())
+            .with_graceful_shutdown(shutdown)
+            .await;
+        if let Err(e) = std::fs::remove_file(&path) {
+            tracing::warn!("Failed to remove socket {}: {}", path.display(), e);
+        }
+        served.context("Unix socket listener failed")
+    }))
+}
+
+#[cfg(not(unix))]
+pub fn start(
+    _settings: &SocketSettings,
+    _app: Router,
+    _shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<JoinHandle<Result<()>>> {
+    bail!("Unix socket listeners are not supported on this platform")
+}
+
+#[cfg(unix)]
+fn bind(settings: &SocketSettings) -> Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Some(parent) = settings.path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    remove_stale_socket(&settings.path)?;
+
+    let listener = tokio::net::UnixListener::bind(&settings.path)
+        .with_context(|| format!("Failed to bind socket {}", settings.path.display()))?;
+    std::fs::set_permissions(
+        &settings.path,
+        std::fs::Permissions::from_mode(settings.mode),
+    )
+    .with_context(|| format!("Failed to set permissions on {}", settings.path.display()))?;
+
+    Ok(listener)
+}
+
+/// Remove a socket left behind by a previous run; refuses to touch regular files or a
+/// socket another process is still serving
+#[cfg(unix)]
+fn remove_stale_socket(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !metadata.file_type().is_socket() {
+        bail!(
+            "{} exists and is not a socket; refusing to replace it",
+            path.display()
+        );
+    }
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+        bail!("Another process is already listening on {}", path.display());
+    }
+
+    std::fs::remove_file(path)
+        .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    tracing::info!("Removed stale socket {}", path.display());
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use axum::{extract::Request, routing::get};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::UnixStream,
+        sync::oneshot,
+    };
+
+    use super::*;
+
+    fn socket_path() -> PathBuf {
+        std::env::temp_dir().join(format!("forge-{}.sock", uuid::Uuid::new_v4().simple()))
+    }
+
+    #[test]
+    fn settings_parse_octal_modes() {
+        let path = Some(PathBuf::from("/tmp/forge.sock"));
+
+        assert_eq!(SocketSettings::resolve(None, false, None).unwrap(), None);
+        assert!(SocketSettings::resolve(None, true, None).is_err());
+        assert_eq!(
+            SocketSettings::resolve(path.clone(), false, None)
+                .unwrap()
+                .unwrap()
+                .mode,
+            0o600
+        );
+        assert_eq!(
+            SocketSettings::resolve(path.clone(), true, Some("0660"))
+                .unwrap()
+                .unwrap()
+                .mode,
+            0o660
+        );
+        assert!(SocketSettings::resolve(path, false, Some("999")).is_err());
+    }
+
+    #[tokio::test]
+    async fn serves_requests_and_cleans_up_the_socket() {
+        let settings = SocketSettings {
+            path: socket_path(),
+            exclusive: true,
+            mode: 0o600,
+        };
+        // Left behind by a crashed run
+        drop(std::os::unix::net::UnixListener::bind(&settings.path).unwrap());
+
+        let app = Router::new().route(
+            "/health",
+            get(|request: Request| async move {
+                if request.extensions().get::<LocalSocket>().is_some() {
+                    "local"
+                } else {
+                    "remote"
+                }
+            }),
+        );
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = start(&settings, app, async {
+            let _ = stopped.await;
+        })
+        .unwrap();
+
+        let mode = std::fs::metadata(&settings.path)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // A second instance must not steal the socket
+        assert!(bind(&settings).is_err());
+
+        let mut stream = UnixStream::connect(&settings.path).await.unwrap();
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("local"), "{response}");
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!settings.path.exists());
+    }
+
+    #[test]
+    fn regular_files_are_never_replaced() {
+        let path = socket_path();
+        std::fs::write(&path, "not a socket").unwrap();
+
+        assert!(remove_stale_socket(&path).is_err());
+        assert!(path.exists());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -10,14 +10,21 @@ static VERSION: OnceLock<String> = OnceLock::new();
 
 /// Get the application version.
 ///
-/// Reads from `FORGE_VERSION` environment variable (set by npx-cli wrapper).
-/// Falls back to "unknown" if not set.
+/// Uses the version from the server configuration once it is installed (see
+/// [`set_version`]), otherwise reads the `FORGE_VERSION` environment variable (set by
+/// npx-cli wrapper). Falls back to "unknown" if not set.
 ///
 /// The version is cached after first read using `OnceLock` for efficiency.
 pub fn get_version() -> &'static str {
     VERSION.get_or_init(|| std::env::var("FORGE_VERSION").unwrap_or_else(|_| "unknown".to_string()))
 }
 
+/// Report `version` instead of reading `FORGE_VERSION`; has no effect once the
+/// version has been read.
+pub fn set_version(version: &str) {
+    let _ = VERSION.set(version.to_string());
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
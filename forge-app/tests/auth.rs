@@ -0,0 +1,464 @@
+//! AUTH_REQUIRED enforcement through the session guard layer, for sessions and API tokens
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::{
+    Json, Router,
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+    middleware::from_fn_with_state,
+    response::Response,
+    routing::{get, post},
+};
+use forge_app_lib::{
+    middleware::auth::{self, AuthGuard, GitHubAccount, LocalSocket, LoginGuard, StoredAccount},
+    services::{
+        api_tokens::{self, CreateApiToken, TokenScope},
+        schema,
+    },
+};
+use serde_json::json;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+async fn setup_pool() -> SqlitePool {
+    unsafe {
+        std::env::set_var("DATABASE_URL", "sqlite::memory:");
+    }
+    let pool = forge_core_db::DBService::new()
+        .await
+        .expect("failed to create db service with migrations")
+        .pool;
+    schema::ensure_forge_schema(&pool).await.unwrap();
+    pool
+}
+
+/// Access rules shaped like the ones `create_router` derives from the route registry;
+/// the image route exercises path parameters in public templates
+fn app(pool: SqlitePool, required: bool) -> Router {
+    let routes = [
+        (Method::GET, "/health", None),
+        (Method::GET, "/site.webmanifest", None),
+        (Method::GET, "/api/auth/github/check", None),
+        (Method::POST, "/api/auth/github/device/start", None),
+        (Method::POST, "/api/auth/github/device/poll", None),
+        (Method::GET, "/api/images/{id}/file", None),
+        (Method::GET, "/api/tasks", Some(TokenScope::Read)),
+        (Method::POST, "/api/tasks", Some(TokenScope::TasksWrite)),
+        (
+            Method::GET,
+            "/api/filesystem/directory",
+            Some(TokenScope::Admin),
+        ),
+    ]
+    .map(|(method, path, scope)| (method, path.to_string(), scope));
+
+    Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .route("/site.webmanifest", get(|| async { "{}" }))
+        .route("/api/auth/github/check", get(|| async { "ok" }))
+        .route("/api/auth/github/device/start", post(|| async { "ok" }))
+        .route("/api/auth/github/device/poll", post(|| async { "ok" }))
+        .route("/api/images/{id}/file", get(|| async { "image" }))
+        .route("/api/tasks", get(|| async { "[]" }).post(|| async { "{}" }))
+        .route("/api/filesystem/directory", get(|| async { "[]" }))
+        .layer(from_fn_with_state(
+            AuthGuard::new(pool, required, routes),
+            auth::require_session,
+        ))
+}
+
+async fn status(app: &Router, method: Method, uri: &str, session: Option<&str>) -> StatusCode {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = session {
+        request = request.header(header::COOKIE, format!("theme=dark; forge_session={token}"));
+    }
+    send(app, request).await
+}
+
+async fn bearer_status(app: &Router, method: Method, uri: &str, token: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {token}"));
+    send(app, request).await
+}
+
+async fn send(app: &Router, request: axum::http::request::Builder) -> StatusCode {
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn public_routes_need_no_session() {
+    let app = app(setup_pool().await, true);
+
+    for (method, uri) in [
+        (Method::GET, "/health"),
+        (Method::HEAD, "/health"),
+        (Method::GET, "/site.webmanifest"),
+        (Method::GET, "/api/auth/github/check"),
+        (Method::POST, "/api/auth/github/device/start"),
+        (Method::POST, "/api/auth/github/device/poll"),
+        (Method::GET, "/api/images/5d1c/file"),
+    ] {
+        assert_eq!(
+            status(&app, method.clone(), uri, None).await,
+            StatusCode::OK,
+            "{method} {uri}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn api_routes_reject_missing_or_unknown_sessions() {
+    let app = app(setup_pool().await, true);
+
+    assert_eq!(
+        status(&app, Method::GET, "/api/tasks", None).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(&app, Method::POST, "/api/tasks", None).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(
+            &app,
+            Method::GET,
+            "/api/filesystem/directory",
+            Some("not-a-session")
+        )
+        .await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn sessions_unlock_api_routes_until_revoked_or_expired() {
+    let pool = setup_pool().await;
+    let app = app(pool.clone(), true);
+
+    let token = auth::create_session(&pool, Some("octocat")).await.unwrap();
+    assert_eq!(
+        status(&app, Method::GET, "/api/filesystem/directory", Some(&token)).await,
+        StatusCode::OK
+    );
+
+    auth::revoke_session(&pool, &token).await.unwrap();
+    assert_eq!(
+        status(&app, Method::GET, "/api/filesystem/directory", Some(&token)).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    let token = auth::create_session(&pool, None).await.unwrap();
+    sqlx::query("UPDATE forge_auth_sessions SET expires_at = datetime('now', '-1 minute')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        status(&app, Method::GET, "/api/tasks", Some(&token)).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn sessions_are_stored_hashed() {
+    let pool = setup_pool().await;
+    let token = auth::create_session(&pool, Some("octocat")).await.unwrap();
+
+    let stored: Vec<String> = sqlx::query_scalar("SELECT token_hash FROM forge_auth_sessions")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_ne!(stored[0], token);
+}
+
+#[tokio::test]
+async fn local_socket_requests_skip_the_session_check() {
+    let app = app(setup_pool().await, true);
+
+    let request = Request::builder()
+        .uri("/api/filesystem/directory")
+        .extension(LocalSocket);
+    assert_eq!(send(&app, request).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn nothing_is_enforced_when_auth_is_not_required() {
+    let app = app(setup_pool().await, false);
+
+    assert_eq!(
+        status(&app, Method::GET, "/api/tasks", None).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(&app, Method::POST, "/api/tasks", Some("stale")).await,
+        StatusCode::OK
+    );
+}
+
+async fn mint_token(pool: &SqlitePool, scopes: Vec<TokenScope>) -> api_tokens::CreatedApiToken {
+    api_tokens::create_api_token(
+        pool,
+        &CreateApiToken {
+            name: "ci".into(),
+            scopes,
+            expires_in_days: None,
+        },
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn api_tokens_are_limited_to_their_scopes() {
+    let pool = setup_pool().await;
+    let app = app(pool.clone(), true);
+
+    let reader = mint_token(&pool, vec![TokenScope::Read]).await.token;
+    assert_eq!(
+        bearer_status(&app, Method::GET, "/api/tasks", &reader).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        bearer_status(&app, Method::POST, "/api/tasks", &reader).await,
+        StatusCode::FORBIDDEN
+    );
+
+    let writer = mint_token(&pool, vec![TokenScope::TasksWrite]).await.token;
+    assert_eq!(
+        bearer_status(&app, Method::GET, "/api/tasks", &writer).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        bearer_status(&app, Method::POST, "/api/tasks", &writer).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        bearer_status(&app, Method::GET, "/api/filesystem/directory", &writer).await,
+        StatusCode::FORBIDDEN
+    );
+
+    let admin = mint_token(&pool, vec![TokenScope::Admin]).await.token;
+    assert_eq!(
+        bearer_status(&app, Method::GET, "/api/filesystem/directory", &admin).await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn revoked_or_unknown_api_tokens_are_rejected() {
+    let pool = setup_pool().await;
+    let app = app(pool.clone(), true);
+
+    let created = mint_token(&pool, vec![TokenScope::Admin]).await;
+    assert_eq!(
+        bearer_status(&app, Method::GET, "/api/tasks", &created.token).await,
+        StatusCode::OK
+    );
+
+    api_tokens::revoke_api_token(&pool, created.record.id)
+        .await
+        .unwrap();
+    assert_eq!(
+        bearer_status(&app, Method::GET, "/api/tasks", &created.token).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        bearer_status(&app, Method::GET, "/api/tasks", "forge_pat_0000").await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+/// Stands in for the deployment config: `signed_in` is who the device flow signs in
+#[derive(Default)]
+struct FakeAccount {
+    stored: Mutex<Option<String>>,
+    signed_in: Mutex<Option<String>>,
+}
+
+#[async_trait]
+impl GitHubAccount for FakeAccount {
+    async fn current(&self) -> StoredAccount {
+        let login = self.stored.lock().unwrap().clone();
+        StoredAccount {
+            saved: json!({ "username": login }),
+            login,
+        }
+    }
+
+    async fn restore(&self, account: StoredAccount) -> anyhow::Result<()> {
+        *self.stored.lock().unwrap() = account.login;
+        Ok(())
+    }
+}
+
+/// The upstream device flow routes behind the login guard; polling signs in
+/// `signed_in`
+fn device_flow_app(pool: SqlitePool, account: Arc<FakeAccount>, allowed: &[&str]) -> Router {
+    device_flow_app_with(pool, account, allowed, false)
+}
+
+fn device_flow_app_with(
+    pool: SqlitePool,
+    account: Arc<FakeAccount>,
+    allowed: &[&str],
+    secure_cookies: bool,
+) -> Router {
+    let poll_account = account.clone();
+    Router::new()
+        .route(
+            "/api/auth/github/device/start",
+            post(|| async {
+                Json(json!({ "success": true, "data": { "user_code": "ABCD-1234" } }))
+            }),
+        )
+        .route(
+            "/api/auth/github/device/poll",
+            post(move || async move {
+                let login = poll_account.signed_in.lock().unwrap().clone();
+                *poll_account.stored.lock().unwrap() = login;
+                Json(json!({ "success": true, "data": "SUCCESS" }))
+            }),
+        )
+        .layer(from_fn_with_state(
+            LoginGuard::new(
+                pool,
+                true,
+                account,
+                allowed.iter().map(|login| login.to_string()),
+            )
+            .with_secure_cookies(secure_cookies),
+            auth::start_session_on_login,
+        ))
+}
+
+async fn device_call(app: &Router, step: &str, cookie: Option<&str>) -> Response {
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri(format!("/api/auth/github/device/{step}"));
+    if let Some(cookie) = cookie {
+        request = request.header(header::COOKIE, cookie);
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+fn set_cookies(response: &Response) -> Vec<String> {
+    response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|value| value.to_str().unwrap().to_string())
+        .collect()
+}
+
+/// `name=value` of the nonce cookie set by `device/start`
+async fn start_flow(app: &Router) -> String {
+    let response = device_call(app, "start", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    set_cookies(&response)
+        .iter()
+        .find(|cookie| cookie.starts_with("forge_login="))
+        .and_then(|cookie| cookie.split(';').next())
+        .expect("device/start sets the login nonce")
+        .to_string()
+}
+
+#[tokio::test]
+async fn the_signed_in_owner_gets_a_session() {
+    let account = Arc::new(FakeAccount::default());
+    *account.stored.lock().unwrap() = Some("octocat".into());
+    *account.signed_in.lock().unwrap() = Some("OctoCat".into());
+    let app = device_flow_app(setup_pool().await, account, &[]);
+
+    let nonce = start_flow(&app).await;
+    let response = device_call(&app, "poll", Some(&nonce)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        set_cookies(&response)
+            .iter()
+            .any(|cookie| cookie.starts_with("forge_session="))
+    );
+}
+
+#[tokio::test]
+async fn cookies_are_secure_over_tls() {
+    let account = Arc::new(FakeAccount::default());
+    *account.stored.lock().unwrap() = Some("octocat".into());
+    *account.signed_in.lock().unwrap() = Some("octocat".into());
+    let app = device_flow_app_with(setup_pool().await, account, &[], true);
+
+    let response = device_call(&app, "start", None).await;
+    let nonce = set_cookies(&response)
+        .into_iter()
+        .find(|cookie| cookie.starts_with("forge_login="))
+        .expect("device/start sets the login nonce");
+    assert!(nonce.ends_with("; Secure"), "{nonce}");
+
+    let nonce = nonce.split(';').next().unwrap().to_string();
+    let response = device_call(&app, "poll", Some(&nonce)).await;
+    let session = set_cookies(&response)
+        .into_iter()
+        .find(|cookie| cookie.starts_with("forge_session="))
+        .expect("poll starts a session");
+    assert!(session.ends_with("; Secure"), "{session}");
+}
+
+#[tokio::test]
+async fn foreign_github_logins_get_no_session() {
+    let pool = setup_pool().await;
+    let account = Arc::new(FakeAccount::default());
+    *account.stored.lock().unwrap() = Some("octocat".into());
+    *account.signed_in.lock().unwrap() = Some("mallory".into());
+    let app = device_flow_app(pool.clone(), account.clone(), &["hubot"]);
+
+    let nonce = start_flow(&app).await;
+    let response = device_call(&app, "poll", Some(&nonce)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(
+        !set_cookies(&response)
+            .iter()
+            .any(|cookie| cookie.starts_with("forge_session="))
+    );
+    // The owner's account is put back
+    assert_eq!(account.stored.lock().unwrap().as_deref(), Some("octocat"));
+    let sessions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM forge_auth_sessions")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(sessions, 0);
+
+    // An allowed login may sign in even though another account was stored
+    *account.signed_in.lock().unwrap() = Some("hubot".into());
+    let nonce = start_flow(&app).await;
+    assert_eq!(
+        device_call(&app, "poll", Some(&nonce)).await.status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn polls_from_another_browser_are_rejected() {
+    let account = Arc::new(FakeAccount::default());
+    *account.stored.lock().unwrap() = Some("octocat".into());
+    *account.signed_in.lock().unwrap() = Some("octocat".into());
+    let app = device_flow_app(setup_pool().await, account, &[]);
+
+    start_flow(&app).await;
+    for cookie in [None, Some("forge_login=0123abcd")] {
+        assert_eq!(
+            device_call(&app, "poll", cookie).await.status(),
+            StatusCode::FORBIDDEN,
+            "{cookie:?}"
+        );
+    }
+}
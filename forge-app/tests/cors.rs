@@ -0,0 +1,131 @@
+//! Cross-origin policy: same-origin, localhost and configured origins only, on a known host
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Method, Request, Response, StatusCode, header},
+    middleware::from_fn_with_state,
+    routing::post,
+};
+use forge_app_lib::middleware::cors::{self, CorsPolicy};
+use tower::ServiceExt;
+
+const MALICIOUS_ORIGIN: &str = "https://evil.example";
+
+fn app(policy: CorsPolicy) -> Router {
+    let layer = policy.layer();
+    Router::new()
+        .route("/api/tasks/create-and-start", post(|| async { "started" }))
+        .layer(from_fn_with_state(policy, cors::reject_disallowed_origins))
+        .layer(layer)
+}
+
+async fn send(app: &Router, method: Method, origin: Option<&str>) -> Response<Body> {
+    send_to(app, "127.0.0.1:8887", method, origin).await
+}
+
+async fn send_to(app: &Router, host: &str, method: Method, origin: Option<&str>) -> Response<Body> {
+    let mut request = Request::builder()
+        .method(method.clone())
+        .uri("/api/tasks/create-and-start")
+        .header(header::HOST, host);
+    if let Some(origin) = origin {
+        request = request.header(header::ORIGIN, origin);
+    }
+    if method == Method::OPTIONS {
+        request = request
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type");
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+fn allowed_origin(response: &Response<Body>) -> Option<&str> {
+    response
+        .headers()
+        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        .and_then(|value| value.to_str().ok())
+}
+
+#[tokio::test]
+async fn malicious_origin_is_rejected() {
+    let app = app(CorsPolicy::default());
+
+    let preflight = send(&app, Method::OPTIONS, Some(MALICIOUS_ORIGIN)).await;
+    assert_eq!(allowed_origin(&preflight), None);
+
+    // A "simple" request skips the preflight, so it must not reach the handler
+    let response = send(&app, Method::POST, Some(MALICIOUS_ORIGIN)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(allowed_origin(&response), None);
+}
+
+#[tokio::test]
+async fn localhost_preflight_allows_patch_and_is_cached() {
+    let app = app(CorsPolicy::default());
+
+    let preflight = send(&app, Method::OPTIONS, Some("http://localhost:3000")).await;
+    assert_eq!(allowed_origin(&preflight), Some("http://localhost:3000"));
+    let methods = preflight.headers()[header::ACCESS_CONTROL_ALLOW_METHODS]
+        .to_str()
+        .unwrap();
+    assert!(methods.contains("PATCH"), "{methods}");
+    assert_eq!(preflight.headers()[header::ACCESS_CONTROL_MAX_AGE], "3600");
+
+    let response = send(&app, Method::POST, Some("http://localhost:3000")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn same_origin_scripts_and_configured_origins_are_allowed() {
+    let app = app(CorsPolicy::new(["https://dashboard.example.com"]));
+
+    for origin in [
+        None,
+        Some("http://127.0.0.1:8887"),
+        Some("https://dashboard.example.com"),
+    ] {
+        let response = send(&app, Method::POST, origin).await;
+        assert_eq!(response.status(), StatusCode::OK, "{origin:?}");
+    }
+    assert_eq!(
+        send(&app, Method::POST, Some(MALICIOUS_ORIGIN))
+            .await
+            .status(),
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn rebound_hosts_are_rejected() {
+    let app = app(CorsPolicy::default().with_bind_host("0.0.0.0"));
+
+    // evil.example resolves to this machine, making the attacker's page same-origin
+    let rebound = "evil.example:8887";
+    let response = send_to(
+        &app,
+        rebound,
+        Method::POST,
+        Some("http://evil.example:8887"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(allowed_origin(&response), None);
+    assert_eq!(
+        send_to(&app, rebound, Method::POST, None).await.status(),
+        StatusCode::FORBIDDEN
+    );
+
+    // Bound to all interfaces, the LAN address still works
+    let response = send_to(
+        &app,
+        "192.168.1.20:8887",
+        Method::POST,
+        Some("http://192.168.1.20:8887"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -4,6 +4,7 @@ const { execSync, spawn } = require("child_process");
 const AdmZip = require("adm-zip");
 const path = require("path");
 const fs = require("fs");
+const os = require("os");
 const dotenv = require("dotenv");
 
 // Load .env from current working directory if present
@@ -460,6 +461,56 @@ function extractAndRun(baseName, args, launch) {
   return launch(binPath, args);
 }
 
+/**
+ * Whether the port is probed by the server (`auto` or `8887+`): there is no
+ * conflict to resolve, and the chosen port is logged and written to server.json
+ * in the asset directory
+ * @param {string|undefined} port - BACKEND_PORT/PORT value
+ * @returns {boolean}
+ */
+function isProbedPort(port) {
+  return /^auto$|\+$/i.test((port || "").trim());
+}
+
+/**
+ * The server's asset directory, where it writes server.json (the data directory
+ * of `ProjectDirs::from("ai", "bloop", "automagik-forge")` in release builds)
+ * @returns {string}
+ */
+function getAssetDir() {
+  const home = os.homedir();
+  if (platform === "win32") {
+    const appData = process.env.APPDATA || path.join(home, "AppData", "Roaming");
+    return path.join(appData, "bloop", "automagik-forge", "data");
+  }
+  if (platform === "darwin") {
+    return path.join(home, "Library", "Application Support", "ai.bloop.automagik-forge");
+  }
+  const dataHome = process.env.XDG_DATA_HOME || path.join(home, ".local", "share");
+  return path.join(dataHome, "automagik-forge");
+}
+
+/**
+ * Wait until server.json describes the server with the given PID
+ * @param {number} pid - PID of the spawned server
+ * @param {number} timeout - Maximum wait time in ms
+ * @returns {Promise<object|null>} - The discovery info, or null on timeout
+ */
+async function waitForServerInfo(pid, timeout = 60000) {
+  const file = path.join(getAssetDir(), "server.json");
+  const startTime = Date.now();
+  while (Date.now() - startTime < timeout) {
+    try {
+      const info = JSON.parse(fs.readFileSync(file, "utf8"));
+      if (info.pid === pid) {
+        return info;
+      }
+    } catch { }
+    await new Promise((resolve) => setTimeout(resolve, 250));
+  }
+  return null;
+}
+
 if (isMcpMode) {
   // MCP mode: handle port conflicts silently (no interactive prompts)
   (async () => {
@@ -468,7 +519,8 @@ if (isMcpMode) {
     const host = process.env.HOST || "127.0.0.1";
 
     // Check for port conflicts (MCP mode = true, no interactive prompts)
-    const canProceed = await handlePortConflict(host, displayPort, true);
+    const canProceed =
+      isProbedPort(backendPort) || (await handlePortConflict(host, displayPort, true));
     if (!canProceed) {
       process.exit(1);
     }
@@ -496,7 +548,8 @@ if (isMcpMode) {
     const host = process.env.HOST || "127.0.0.1";
 
     // Check for port conflicts before extraction
-    const canProceed = await handlePortConflict(host, displayPort, false);
+    const canProceed =
+      isProbedPort(backendPort) || (await handlePortConflict(host, displayPort, false));
     if (!canProceed) {
       process.exit(1);
     }
@@ -518,13 +571,30 @@ if (isMcpMode) {
         process.env.RUST_LOG = "info";
       }
 
-      console.log(`http://${host}:${displayPort}/`);
-      console.log();
+      // A probed port is only known once the server writes server.json (printed below)
+      if (!isProbedPort(backendPort)) {
+        console.log(`http://${host}:${displayPort}/`);
+        console.log();
+      }
 
-      if (platform === "win32") {
-        execSync(`"${bin}"`, { stdio: "inherit" });
-      } else {
-        execSync(`"${bin}"`, { stdio: "inherit" });
+      const proc = spawn(bin, [], { stdio: "inherit" });
+      proc.on("exit", (c) => process.exit(c || 0));
+      proc.on("error", (e) => {
+        console.error("❌ Failed to launch automagik-forge:", e.message);
+        process.exit(1);
+      });
+      // The child gets the terminal's signals too; wait for it to shut down
+      process.on("SIGINT", () => proc.kill("SIGINT"));
+      process.on("SIGTERM", () => proc.kill("SIGTERM"));
+
+      if (isProbedPort(backendPort)) {
+        waitForServerInfo(proc.pid).then((info) => {
+          if (info && info.url) {
+            console.log(`\n🌐 automagik-forge is running at ${info.url}/\n`);
+          } else if (info && info.socket) {
+            console.log(`\n🌐 automagik-forge is listening on ${info.socket}\n`);
+          }
+        });
       }
     });
   })();
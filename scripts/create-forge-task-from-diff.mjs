@@ -17,6 +17,8 @@
  *     --title "Codex: regenerate Playwright tests"
  *
  * The Forge server must be running locally (make dev / npx @automagik/forge).
+ * When it runs with AUTH_REQUIRED, set FORGE_API_TOKEN to a token with the
+ * `tasks:write` scope (POST /api/forge/tokens).
  */
 
 import { execSync } from 'child_process';
@@ -28,6 +30,7 @@ const DEFAULT_HEAD = 'HEAD';
 const DEFAULT_BRANCH = 'dev';
 const DEFAULT_EXECUTOR = 'CODEX';
 const DEFAULT_API_URL = process.env.FORGE_API_URL || 'http://127.0.0.1:8887';
+const API_TOKEN = process.env.FORGE_API_TOKEN;
 const DIFF_LIMIT = 15_000; // avoid blowing up request payloads
 
 function parseArgs() {
@@ -75,7 +78,8 @@ Options:
   --help             Show this message
 
 Env:
-  FORGE_API_URL   (default http://127.0.0.1:8887)
+  FORGE_API_URL     (default http://127.0.0.1:8887)
+  FORGE_API_TOKEN   API token with the tasks:write scope, sent as a Bearer token
 `);
       process.exit(0);
     }
@@ -106,6 +110,7 @@ async function fetchJson(url, init) {
     ...init,
     headers: {
       'Content-Type': 'application/json',
+      ...(API_TOKEN ? { Authorization: `Bearer ${API_TOKEN}` } : {}),
       ...(init?.headers || {}),
     },
   });
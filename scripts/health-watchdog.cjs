@@ -0,0 +1,51 @@
+#!/usr/bin/env node
+// ===================================================================
+// Restarts the pm2-managed Forge server when /health/ready keeps failing.
+// pm2 only restarts processes that exit; a wedged server keeps running.
+// ===================================================================
+const { execFileSync } = require('child_process');
+
+const port = process.env.BACKEND_PORT || process.env.PORT || '8887';
+const url = process.env.FORGE_HEALTH_URL || `http://127.0.0.1:${port}/health/ready`;
+const appName = process.env.FORGE_PM2_APP || '8887: Forge';
+const intervalMs = Number(process.env.FORGE_HEALTH_INTERVAL_MS || 30000);
+const maxFailures = Number(process.env.FORGE_HEALTH_MAX_FAILURES || 3);
+
+// Failures only count once the server has been ready, so slow builds and
+// startups are not mistaken for a wedged server
+let seenReady = false;
+let failures = 0;
+
+async function probe() {
+  try {
+    const res = await fetch(url, { signal: AbortSignal.timeout(10000) });
+    if (res.ok) {
+      seenReady = true;
+      failures = 0;
+      return;
+    }
+    const body = await res.text();
+    fail(`HTTP ${res.status}: ${body}`);
+  } catch (err) {
+    fail(err.message);
+  }
+}
+
+function fail(reason) {
+  if (!seenReady) return;
+  failures += 1;
+  console.error(`[health-watchdog] ${url} failed (${failures}/${maxFailures}): ${reason}`);
+  if (failures < maxFailures) return;
+
+  console.error(`[health-watchdog] Restarting "${appName}"`);
+  try {
+    execFileSync('pm2', ['restart', appName], { stdio: 'inherit' });
+  } catch (err) {
+    console.error(`[health-watchdog] pm2 restart failed: ${err.message}`);
+  }
+  seenReady = false;
+  failures = 0;
+}
+
+console.log(`[health-watchdog] Watching ${url} every ${intervalMs / 1000}s`);
+setInterval(probe, intervalMs);
@@ -18,3 +18,100 @@ export type OmniInstance = { instance_name: string, channel_type: string, displa
 export type SendTextRequest = { phone_number: string | null, user_id: string | null, text: string, };
 
 export type SendTextResponse = { success: boolean, message_id: string | null, status: string, error: string | null, };
+
+export type OmniWorkerStatus = { running: boolean, circuit: CircuitState, consecutive_failures: number, open_until: string | null, last_error: string | null, last_success_at: string | null, restarts: number, };
+
+export type CircuitState = "closed" | "open" | "half_open";
+
+/**
+ * Per-project notification settings owned by forge-app
+ */
+export type NotificationSettings = { sinks: Array<NotificationSinkConfig>, templates: NotificationTemplates, events: Array<NotificationEvent>, 
+/**
+ * Only notify `execution_completed` for coding agent runs, skipping setup and cleanup scripts
+ */
+coding_agent_only: boolean, };
+
+/**
+ * `id` must be unique within a project and is what delivery status is recorded
+ * against, so renaming a sink re-sends pending notifications.
+ */
+export type NotificationSinkConfig = { id: string, enabled: boolean, sink: NotificationSinkKind, 
+/**
+ * Set in API responses when the sink's secret was redacted; ignored on save
+ */
+has_secret: boolean, };
+
+export type NotificationSinkKind = { "type": "webhook", url: string, secret: string | null, } | { "type": "slack", webhook_url: string, } | { "type": "ntfy", server_url: string | null, topic: string, token: string | null, } | { "type": "email", smtp_host: string, smtp_port: number | null, security: SmtpSecurity, username: string | null, password: string | null, from: string, to: Array<string>, };
+
+export type SmtpSecurity = "tls" | "starttls" | "none";
+
+/**
+ * Delivery status of one notification at one sink
+ */
+export type NotificationDelivery = { sink_id: string, status: string, attempts: number, error_message: string | null, delivered_at: string | null, updated_at: string, };
+
+/**
+ * Unset templates fall back to DEFAULT_TITLE_TEMPLATE / DEFAULT_BODY_TEMPLATE
+ */
+export type NotificationTemplates = { title: string | null, body: string | null, };
+
+/**
+ * Everything a template can reference
+ */
+export type NotificationContext = { project_id: string, task_id: string, task_attempt_id: string, event: NotificationEvent, title: string, description: string | null, status: string, executor: string, variant: string | null, branch: string, target_branch: string, exit_code: number | null, duration_seconds: number | null, input_tokens: number | null, output_tokens: number | null, cache_creation_tokens: number | null, cache_read_tokens: number | null, diff: DiffStats | null, url: string, details: JsonValue, };
+
+export type DiffStats = { files_changed: number, insertions: number, deletions: number, };
+
+export type RenderedNotification = { title: string, body: string, };
+
+/**
+ * Serialized names double as `forge_omni_notifications.notification_type`
+ */
+export type NotificationEvent = "execution_completed" | "approval_pending" | "pr_opened" | "pr_merged" | "pr_closed" | "merge_conflict" | "rebase_conflict" | "dev_server_crashed" | "task_in_review";
+
+/**
+ * Filters for listing and purging notifications. Dates are RFC 3339 and compared
+ * against `created_at`; `limit`/`offset` only apply to listing.
+ */
+export type NotificationFilter = { project_id: string | null, status: string | null, notification_type: string | null, since: string | null, until: string | null, limit: number | null, offset: number | null, };
+
+/**
+ * One row of the notification queue
+ */
+export type NotificationRecord = { id: string, notification_type: string, status: string, project_id: string | null, task_id: string | null, task_attempt_id: string | null, execution_process_id: string | null, message: string, error_message: string | null, attempts: number, metadata: JsonValue, created_at: string | null, sent_at: string | null, next_attempt_at: string | null, };
+
+export type NotificationPurgeResult = { deleted: number, };
+
+/**
+ * What a token may do. Each scope includes the ones before it:
+ * `read` < `tasks:write` < `admin`.
+ */
+export type TokenScope = "read" | "tasks:write" | "admin";
+
+/**
+ * Request body for minting a token
+ */
+export type CreateApiToken = { name: string, scopes: Array<TokenScope>, 
+/**
+ * Days until the token stops working; omit for a token that never expires
+ */
+expires_in_days: number | null, };
+
+/**
+ * A stored token, without its secret
+ */
+export type ApiTokenRecord = { id: string, name: string, 
+/**
+ * First characters of the token, enough to tell tokens apart
+ */
+token_prefix: string, scopes: Array<TokenScope>, created_at: string, last_used_at: string | null, expires_at: string | null, revoked_at: string | null, };
+
+/**
+ * Returned once by the create endpoint; `token` cannot be retrieved again
+ */
+export type CreatedApiToken = { token: string, id: string, name: string, 
+/**
+ * First characters of the token, enough to tell tokens apart
+ */
+token_prefix: string, scopes: Array<TokenScope>, created_at: string, last_used_at: string | null, expires_at: string | null, revoked_at: string | null, };